@@ -0,0 +1,19 @@
+ALTER TABLE pocket_articles
+	DROP CONSTRAINT pocket_articles_user_id_fkey,
+	ADD CONSTRAINT pocket_articles_user_id_fkey
+		FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE pocket_article_videos
+	DROP CONSTRAINT pocket_article_videos_pocket_article_id_fkey,
+	ADD CONSTRAINT pocket_article_videos_pocket_article_id_fkey
+		FOREIGN KEY (pocket_article_id) REFERENCES pocket_articles(id) ON DELETE CASCADE;
+
+ALTER TABLE pocket_article_images
+	DROP CONSTRAINT pocket_article_images_pocket_article_id_fkey,
+	ADD CONSTRAINT pocket_article_images_pocket_article_id_fkey
+		FOREIGN KEY (pocket_article_id) REFERENCES pocket_articles(id) ON DELETE CASCADE;
+
+ALTER TABLE pocket_article_authors
+	DROP CONSTRAINT pocket_article_authors_pocket_article_id_fkey,
+	ADD CONSTRAINT pocket_article_authors_pocket_article_id_fkey
+		FOREIGN KEY (pocket_article_id) REFERENCES pocket_articles(id) ON DELETE CASCADE;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS user_settings (
+	user_id INT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+	schema_version INT NOT NULL,
+	settings JSONB NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS pocket_credentials (
+	user_id INT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+	-- JWE of the access token, encrypted with the master key `key_id`
+	encrypted_access_token TEXT NOT NULL,
+	key_id TEXT NOT NULL,
+	revoked BOOLEAN NOT NULL DEFAULT FALSE,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS pocket_credentials_key_id_idx ON pocket_credentials (key_id);
@@ -0,0 +1,5 @@
+ALTER TABLE pocket_articles
+	DROP COLUMN IF EXISTS open_count,
+	DROP COLUMN IF EXISTS first_opened_at,
+	DROP COLUMN IF EXISTS last_opened_at,
+	DROP COLUMN IF EXISTS auto_archived_at;
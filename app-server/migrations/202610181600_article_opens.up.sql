@@ -0,0 +1,6 @@
+ALTER TABLE pocket_articles
+	ADD COLUMN IF NOT EXISTS open_count INT NOT NULL DEFAULT 0,
+	ADD COLUMN IF NOT EXISTS first_opened_at TIMESTAMPTZ,
+	ADD COLUMN IF NOT EXISTS last_opened_at TIMESTAMPTZ,
+	-- Set once an open archived the item in Pocket, so following opens don't archive it again
+	ADD COLUMN IF NOT EXISTS auto_archived_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS article_events;
+
+ALTER TABLE pocket_articles ALTER COLUMN tags TYPE VARCHAR(255) USING LEFT(tags, 255);
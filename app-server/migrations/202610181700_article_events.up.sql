@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS article_events (
+	id BIGSERIAL PRIMARY KEY,
+	user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	pocket_article_id INT NOT NULL REFERENCES pocket_articles(id) ON DELETE CASCADE,
+	-- added, opened, archived, favorited or deleted
+	kind TEXT NOT NULL,
+	occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS article_events_user_id_kind_occurred_at_idx
+	ON article_events (user_id, kind, occurred_at);
+
+-- Tags are stored by syncs from now on, as Pocket sends them
+ALTER TABLE pocket_articles ALTER COLUMN tags TYPE TEXT;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS pocket_articles_search_vector_idx;
+ALTER TABLE pocket_articles DROP COLUMN IF EXISTS search_vector;
+DROP TABLE IF EXISTS article_highlights;
+DROP TABLE IF EXISTS article_notes;
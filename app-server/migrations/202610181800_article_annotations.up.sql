@@ -0,0 +1,40 @@
+CREATE TABLE IF NOT EXISTS article_notes (
+	id SERIAL PRIMARY KEY,
+	pocket_article_id INT NOT NULL REFERENCES pocket_articles(id) ON DELETE CASCADE,
+	body TEXT NOT NULL,
+	search_vector TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', body)) STORED,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS article_notes_pocket_article_id_idx ON article_notes (pocket_article_id);
+CREATE INDEX IF NOT EXISTS article_notes_search_vector_idx ON article_notes USING GIN (search_vector);
+
+CREATE TABLE IF NOT EXISTS article_highlights (
+	id SERIAL PRIMARY KEY,
+	pocket_article_id INT NOT NULL REFERENCES pocket_articles(id) ON DELETE CASCADE,
+	quote TEXT NOT NULL,
+	-- Character offsets of the quote in the text of the article
+	start_offset INT NOT NULL CHECK (start_offset >= 0),
+	end_offset INT NOT NULL,
+	note TEXT,
+	search_vector TSVECTOR GENERATED ALWAYS AS (
+		to_tsvector('simple', quote || ' ' || COALESCE(note, ''))
+	) STORED,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	CHECK (end_offset > start_offset)
+);
+
+CREATE INDEX IF NOT EXISTS article_highlights_pocket_article_id_idx ON article_highlights (pocket_article_id);
+CREATE INDEX IF NOT EXISTS article_highlights_search_vector_idx ON article_highlights USING GIN (search_vector);
+
+ALTER TABLE pocket_articles ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+	to_tsvector(
+		'simple',
+		COALESCE(resolved_title, '') || ' ' || COALESCE(given_title, '') || ' ' ||
+		COALESCE(excerpt, '') || ' ' || COALESCE(tags, '')
+	)
+) STORED;
+
+CREATE INDEX IF NOT EXISTS pocket_articles_search_vector_idx ON pocket_articles USING GIN (search_vector);
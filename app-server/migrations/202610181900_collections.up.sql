@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS collections (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	title TEXT NOT NULL,
+	description TEXT,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS collections_user_id_idx ON collections (user_id);
+
+CREATE TABLE IF NOT EXISTS collection_items (
+	collection_id INT NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+	pocket_article_id INT NOT NULL REFERENCES pocket_articles(id) ON DELETE CASCADE,
+	-- Manual order of the items, gaps are left when items are removed
+	position INT NOT NULL,
+	commentary TEXT,
+	added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	PRIMARY KEY (collection_id, pocket_article_id)
+);
+
+CREATE INDEX IF NOT EXISTS collection_items_pocket_article_id_idx ON collection_items (pocket_article_id);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS share_links (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- Hash of the token of the link, the token itself is only ever shown to its creator
+	token_hash TEXT NOT NULL UNIQUE,
+	collection_id INT REFERENCES collections(id) ON DELETE CASCADE,
+	pocket_article_id INT REFERENCES pocket_articles(id) ON DELETE CASCADE,
+	tag TEXT,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	expires_at TIMESTAMPTZ,
+	revoked_at TIMESTAMPTZ,
+	CONSTRAINT share_links_one_target CHECK (num_nonnulls(collection_id, pocket_article_id, tag) = 1)
+);
+
+CREATE INDEX IF NOT EXISTS share_links_user_id_idx ON share_links (user_id);
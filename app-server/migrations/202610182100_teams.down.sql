@@ -0,0 +1,4 @@
+DELETE FROM collections WHERE team_id IS NOT NULL;
+ALTER TABLE collections DROP COLUMN IF EXISTS team_id;
+DROP TABLE IF EXISTS team_members;
+DROP TABLE IF EXISTS teams;
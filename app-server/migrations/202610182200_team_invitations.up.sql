@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS team_invitations (
+	id SERIAL PRIMARY KEY,
+	team_id INT NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+	-- Hash of the random id in the invitation token, which is only ever shown to its sender
+	id_hash TEXT NOT NULL UNIQUE,
+	role TEXT NOT NULL CHECK (role IN ('editor', 'viewer')),
+	invited_by INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	expires_at TIMESTAMPTZ NOT NULL,
+	-- Invitations are single use
+	used_at TIMESTAMPTZ,
+	used_by INT REFERENCES users(id) ON DELETE SET NULL,
+	revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS team_invitations_team_id_idx ON team_invitations (team_id);
@@ -0,0 +1,259 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    error::{ApiError, Error},
+    validation::{from_json, optional_text, required_text},
+};
+
+const MAX_NOTE_LEN: usize = 10_000;
+const MAX_QUOTE_LEN: usize = 5_000;
+
+/// An article of the user's library that annotations are attached to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedArticle {
+    pub id: i32,
+    pub item_id: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleNote {
+    pub id: i32,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A passage of an article the user highlighted, located by the character offsets of the quote in
+/// the text of the article.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleHighlight {
+    pub id: i32,
+    pub quote: String,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NoteInput {
+    pub body: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NewHighlight {
+    pub quote: String,
+    pub start_offset: u32,
+    pub end_offset: u32,
+    pub note: Option<String>,
+}
+
+/// The quote and position of a highlight are what the user selected, only its note changes. A
+/// `null` note removes it.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HighlightPatch {
+    pub note: Option<String>,
+}
+
+/// What part of an article matched a search.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchSource {
+    /// Title, excerpt or tags
+    Article,
+    Note,
+    Highlight,
+}
+
+impl MatchSource {
+    pub fn parse(source: &str) -> Option<Self> {
+        match source {
+            "article" => Some(MatchSource::Article),
+            "note" => Some(MatchSource::Note),
+            "highlight" => Some(MatchSource::Highlight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub item_id: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub matched_in: Vec<MatchSource>,
+    pub rank: f32,
+}
+
+impl NoteInput {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "note")?;
+        Ok(Self {
+            body: required_text(&input.body, "body", MAX_NOTE_LEN)?,
+        })
+    }
+}
+
+impl NewHighlight {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "highlight")?;
+        if input.end_offset <= input.start_offset || input.end_offset > i32::MAX as u32 {
+            return Err(Error::Api(ApiError::BadRequest(
+                "endOffset must come after startOffset".to_string(),
+            )));
+        }
+
+        Ok(Self {
+            quote: required_text(&input.quote, "quote", MAX_QUOTE_LEN)?,
+            note: optional_text(input.note.as_deref(), "note", MAX_NOTE_LEN)?,
+            ..input
+        })
+    }
+}
+
+impl HighlightPatch {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "highlight")?;
+        Ok(Self {
+            note: optional_text(input.note.as_deref(), "note", MAX_NOTE_LEN)?,
+        })
+    }
+}
+
+/// The annotations of an article as Markdown: a heading linking to the article, its highlights as
+/// block quotes in reading order, each followed by its note, then the notes.
+pub fn annotations_markdown(
+    article: &AnnotatedArticle,
+    highlights: &[ArticleHighlight],
+    notes: &[ArticleNote],
+) -> String {
+    let title = article
+        .title
+        .as_deref()
+        .map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|title| !title.is_empty())
+        .or_else(|| article.url.clone())
+        .unwrap_or_else(|| article.item_id.clone());
+
+    let mut markdown = String::new();
+    match &article.url {
+        Some(url) => {
+            let _ = writeln!(markdown, "# [{}](<{url}>)", escape_link_text(&title));
+        }
+        None => {
+            let _ = writeln!(markdown, "# {title}");
+        }
+    }
+
+    if !highlights.is_empty() {
+        markdown.push_str("\n## Highlights\n");
+        for highlight in highlights {
+            markdown.push('\n');
+            for line in highlight.quote.lines() {
+                let _ = writeln!(markdown, "> {line}");
+            }
+            if let Some(note) = &highlight.note {
+                let _ = write!(markdown, "\n{note}\n");
+            }
+        }
+    }
+
+    if !notes.is_empty() {
+        markdown.push_str("\n## Notes\n");
+        for note in notes {
+            let _ = write!(markdown, "\n{}\n", note.body);
+        }
+    }
+
+    markdown
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn highlight(quote: &str, note: Option<&str>) -> ArticleHighlight {
+        ArticleHighlight {
+            id: 1,
+            quote: quote.to_string(),
+            start_offset: 0,
+            end_offset: quote.len() as i32,
+            note: note.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn exports_annotations_as_markdown() {
+        let article = AnnotatedArticle {
+            id: 1,
+            item_id: "229279689".to_string(),
+            title: Some("The [Rust]\nBook".to_string()),
+            url: Some("https://doc.rust-lang.org/book/".to_string()),
+        };
+        let note = ArticleNote {
+            id: 1,
+            body: "Read chapter 4 again.".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let markdown = annotations_markdown(
+            &article,
+            &[
+                highlight("Ownership is\nRust's most unique feature", Some("Key idea")),
+                highlight("Borrowing", None),
+            ],
+            &[note],
+        );
+
+        assert_eq!(
+            markdown,
+            "# [The \\[Rust\\] Book](<https://doc.rust-lang.org/book/>)\n\
+             \n## Highlights\n\
+             \n> Ownership is\n> Rust's most unique feature\n\
+             \nKey idea\n\
+             \n> Borrowing\n\
+             \n## Notes\n\
+             \nRead chapter 4 again.\n"
+        );
+    }
+
+    #[test]
+    fn validates_highlights() {
+        let parse = |body: &str| NewHighlight::from_json(body.as_bytes());
+
+        let highlight =
+            parse(r#"{"quote":" Borrowing ","startOffset":4,"endOffset":13,"note":" "}"#).unwrap();
+        assert_eq!(highlight.quote, "Borrowing");
+        assert_eq!(highlight.note, None);
+
+        for body in [
+            r#"{"quote":"Borrowing","startOffset":13,"endOffset":4}"#,
+            r#"{"quote":"  ","startOffset":4,"endOffset":13}"#,
+            r#"{"quote":"Borrowing","startOffset":-1,"endOffset":13}"#,
+            r#"{"quote":"Borrowing","startOffset":4,"endOffset":13,"color":"red"}"#,
+        ] {
+            assert_eq!(parse(body).unwrap_err().code(), "bad_request", "{body}");
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    annotations::{
+        annotations_markdown, AnnotatedArticle, ArticleHighlight, ArticleNote, HighlightPatch,
+        NewHighlight, NoteInput, SearchResult,
+    },
+    api::me::user_id,
+    db::{
+        delete_article_highlight, delete_article_note, fetch_annotated_article,
+        fetch_article_highlights, fetch_article_notes, insert_article_highlight,
+        insert_article_note, search_articles, update_article_highlight_note, update_article_note,
+    },
+    error::{ApiError, Error},
+    session::AuthzedSessionData,
+    ApiResult, Store, TypedResponse,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct GetNotesResponse {
+    notes: Vec<ArticleNote>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GetHighlightsResponse {
+    highlights: Vec<ArticleHighlight>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchArticlesResponse {
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Words to look for, `"quoted phrases"` and `-excluded` words are supported
+    pub q: String,
+    /// 20 by default, at most 100
+    pub limit: Option<i64>,
+}
+
+impl SearchQuery {
+    const DEFAULT_LIMIT: i64 = 20;
+    const MAX_LIMIT: i64 = 100;
+}
+
+/// The signed in user's article, which annotations are only ever read and written through.
+async fn annotated_article(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+    item_id: &str,
+) -> Result<AnnotatedArticle, Error> {
+    let user_id = user_id(store, session_data).await?;
+    fetch_annotated_article(store.clone(), user_id, item_id)
+        .await?
+        .ok_or_else(|| Error::Api(ApiError::NotFound("Article not found".to_string())))
+}
+
+fn note_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Note not found".to_string()))
+}
+
+fn highlight_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Highlight not found".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/articles/{item_id}/notes",
+    tag = "annotations",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    responses(
+        (status = 200, description = "Notes of the article, oldest first", body = GetNotesResponse),
+        (status = "4XX", description = "Not signed in or unknown article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_notes(
+    State(store): State<Store>,
+    Path(item_id): Path<String>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetNotesResponse> {
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let notes = fetch_article_notes(store, article.id).await?;
+
+    Ok(TypedResponse::new(Some(GetNotesResponse { notes })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/articles/{item_id}/notes",
+    tag = "annotations",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    request_body = NoteInput,
+    responses(
+        (status = 201, description = "The new note", body = ArticleNote),
+        (status = "4XX", description = "Invalid note, not signed in or unknown article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_note(
+    State(store): State<Store>,
+    Path(item_id): Path<String>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<ArticleNote> {
+    let input = NoteInput::from_json(&body)?;
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let note = insert_article_note(store, article.id, &input.body).await?;
+
+    Ok(TypedResponse::new(Some(note)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/articles/{item_id}/notes/{note_id}",
+    tag = "annotations",
+    params(
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+        ("note_id" = i32, Path, description = "Id of the note"),
+    ),
+    request_body = NoteInput,
+    responses(
+        (status = 200, description = "The changed note", body = ArticleNote),
+        (status = "4XX", description = "Invalid note, not signed in or unknown note", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_note(
+    State(store): State<Store>,
+    Path((item_id, note_id)): Path<(String, i32)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<ArticleNote> {
+    let input = NoteInput::from_json(&body)?;
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let note = update_article_note(store, article.id, note_id, &input.body)
+        .await?
+        .ok_or_else(note_not_found)?;
+
+    Ok(TypedResponse::new(Some(note)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/articles/{item_id}/notes/{note_id}",
+    tag = "annotations",
+    params(
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+        ("note_id" = i32, Path, description = "Id of the note"),
+    ),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = "4XX", description = "Not signed in or unknown note", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn delete_note(
+    State(store): State<Store>,
+    Path((item_id, note_id)): Path<(String, i32)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    if !delete_article_note(store, article.id, note_id).await? {
+        return Err(note_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/articles/{item_id}/highlights",
+    tag = "annotations",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    responses(
+        (status = 200, description = "Highlights of the article, in reading order", body = GetHighlightsResponse),
+        (status = "4XX", description = "Not signed in or unknown article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_highlights(
+    State(store): State<Store>,
+    Path(item_id): Path<String>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetHighlightsResponse> {
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let highlights = fetch_article_highlights(store, article.id).await?;
+
+    Ok(TypedResponse::new(Some(GetHighlightsResponse {
+        highlights,
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/articles/{item_id}/highlights",
+    tag = "annotations",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    request_body = NewHighlight,
+    responses(
+        (status = 201, description = "The new highlight", body = ArticleHighlight),
+        (status = "4XX", description = "Invalid highlight, not signed in or unknown article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_highlight(
+    State(store): State<Store>,
+    Path(item_id): Path<String>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<ArticleHighlight> {
+    let highlight = NewHighlight::from_json(&body)?;
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let highlight = insert_article_highlight(store, article.id, &highlight).await?;
+
+    Ok(TypedResponse::new(Some(highlight)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/articles/{item_id}/highlights/{highlight_id}",
+    tag = "annotations",
+    params(
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+        ("highlight_id" = i32, Path, description = "Id of the highlight"),
+    ),
+    request_body = HighlightPatch,
+    responses(
+        (status = 200, description = "The changed highlight", body = ArticleHighlight),
+        (status = "4XX", description = "Invalid note, not signed in or unknown highlight", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_highlight(
+    State(store): State<Store>,
+    Path((item_id, highlight_id)): Path<(String, i32)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<ArticleHighlight> {
+    let patch = HighlightPatch::from_json(&body)?;
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let highlight =
+        update_article_highlight_note(store, article.id, highlight_id, patch.note.as_deref())
+            .await?
+            .ok_or_else(highlight_not_found)?;
+
+    Ok(TypedResponse::new(Some(highlight)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/articles/{item_id}/highlights/{highlight_id}",
+    tag = "annotations",
+    params(
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+        ("highlight_id" = i32, Path, description = "Id of the highlight"),
+    ),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = "4XX", description = "Not signed in or unknown highlight", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn delete_highlight(
+    State(store): State<Store>,
+    Path((item_id, highlight_id)): Path<(String, i32)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    if !delete_article_highlight(store, article.id, highlight_id).await? {
+        return Err(highlight_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The highlights and notes of an article as Markdown, to paste into other notes.
+#[utoipa::path(
+    get,
+    path = "/v2/articles/{item_id}/annotations.md",
+    tag = "annotations",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    responses(
+        (status = 200, description = "Annotations of the article", body = String, content_type = "text/markdown"),
+        (status = "4XX", description = "Not signed in or unknown article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn export_annotations(
+    State(store): State<Store>,
+    Path(item_id): Path<String>,
+    session_data: AuthzedSessionData,
+) -> Result<impl IntoResponse, Error> {
+    let article = annotated_article(&store, &session_data, &item_id).await?;
+    let highlights = fetch_article_highlights(store.clone(), article.id).await?;
+    let notes = fetch_article_notes(store, article.id).await?;
+
+    Ok((
+        [(CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        annotations_markdown(&article, &highlights, &notes),
+    ))
+}
+
+/// Searches the synced articles of the user, along with their notes and highlights.
+#[utoipa::path(
+    get,
+    path = "/v2/articles/search",
+    tag = "annotations",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching articles, best matches first", body = SearchArticlesResponse),
+        (status = "4XX", description = "Invalid query or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn search(
+    State(store): State<Store>,
+    Query(query): Query<SearchQuery>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<SearchArticlesResponse> {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(Error::Api(ApiError::BadRequest(
+            "q can't be empty".to_string(),
+        )));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(SearchQuery::DEFAULT_LIMIT)
+        .clamp(1, SearchQuery::MAX_LIMIT);
+
+    let user_id = user_id(&store, &session_data).await?;
+    let results = search_articles(store, user_id, q, limit).await?;
+
+    Ok(TypedResponse::new(Some(SearchArticlesResponse { results })))
+}
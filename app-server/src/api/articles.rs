@@ -6,30 +6,268 @@ use axum::{
         Sse,
     },
 };
-use futures::{stream, Stream, StreamExt, TryFutureExt, TryStreamExt};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{stream, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use pockety::{
     models::{ItemAuthor, ItemHas, ItemImage, ItemStatus, ItemVideo, PocketItem, Timestamp},
     Pockety,
 };
 use serde::{Deserialize, Serialize};
 use std::{convert::Infallible, time::Duration};
+use url::Url;
 // use tokio_stream::StreamExt as _;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, Instrument};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    db::{
-        convert_article_to_article_author_models, convert_article_to_article_image_models,
-        convert_article_to_article_model, convert_article_to_article_video_models, fetch_user,
-        ArticleStore,
+    credentials::CredentialsKeys,
+    db::{fetch_articles, fetch_user},
+    error::{ApiError, Error},
+    metrics::metrics,
+    quota::{PocketQuotaStore, QuotaPriority},
+    reading_time::{
+        reading_queue, reading_time, EstimatedArticle, Estimator, ReadingFilter, ReadingQueue,
+        ReadingTime,
     },
-    error::Error,
-    session::AuthzedSessionData,
-    ApiResult, Store, TypedResponse, WithRateLimits,
+    session::{invalidate_on_pocket_auth_failure, AuthzedSessionData},
+    shutdown::Shutdown,
+    sync::store_article,
+    telemetry::pocket_span,
+    ApiResult, Cache, Config, RateLimits, Store, TypedResponse, WithRateLimits,
 };
 
-#[derive(Serialize, Debug, Clone)]
+/// An article of the user's Pocket library, with the string encoded fields of Pocket parsed into
+/// proper types. Empty strings sent by Pocket are `null`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Article {
+    pub item_id: String,
+    pub resolved_id: Option<String>,
+    pub given_url: Option<String>,
+    pub given_title: Option<String>,
+    pub resolved_url: Option<String>,
+    pub resolved_title: Option<String>,
+    /// Host of the resolved url, or of the given one, without `www.`
+    pub domain: Option<String>,
+    pub excerpt: Option<String>,
+    pub status: ArticleStatus,
+    pub favorite: bool,
+    pub is_article: bool,
+    pub is_index: bool,
+    pub has_image: MediaPresence,
+    pub has_video: MediaPresence,
+    pub word_count: Option<u32>,
+    pub tags: Vec<String>,
+    pub authors: Vec<ArticleAuthor>,
+    pub images: Vec<ArticleImage>,
+    pub videos: Vec<ArticleVideo>,
+    pub lang: Option<String>,
+    /// Estimated reading time in minutes
+    pub time_to_read: Option<u32>,
+    /// Estimated listening time in seconds
+    pub listen_duration_estimate: Option<u32>,
+    pub top_image_url: Option<String>,
+    pub sort_id: Option<u32>,
+    pub time_added: Option<DateTime<Utc>>,
+    pub time_updated: Option<DateTime<Utc>>,
+    pub time_read: Option<DateTime<Utc>>,
+    pub time_favorited: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ArticleStatus {
+    Unread,
+    Archived,
+    Deleted,
+}
+
+impl From<ItemStatus> for ArticleStatus {
+    fn from(status: ItemStatus) -> Self {
+        match status {
+            ItemStatus::Normal => ArticleStatus::Unread,
+            ItemStatus::Archived => ArticleStatus::Archived,
+            ItemStatus::Deleted => ArticleStatus::Deleted,
+        }
+    }
+}
+
+/// Whether an article embeds images or videos, or is one itself.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaPresence {
+    None,
+    Contains,
+    Is,
+}
+
+impl From<Option<ItemHas>> for MediaPresence {
+    fn from(has: Option<ItemHas>) -> Self {
+        match has {
+            None | Some(ItemHas::No) => MediaPresence::None,
+            Some(ItemHas::Yes) => MediaPresence::Contains,
+            Some(ItemHas::Is) => MediaPresence::Is,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleAuthor {
+    pub author_id: String,
+    pub name: String,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleImage {
+    pub image_id: String,
+    pub src: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub credit: Option<String>,
+    pub caption: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleVideo {
+    pub video_id: String,
+    pub src: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Length in seconds
+    pub length: Option<u32>,
+    pub vid: Option<String>,
+}
+
+impl From<PocketItem> for Article {
+    fn from(item: PocketItem) -> Self {
+        let given_url = non_empty(item.given_url);
+        let resolved_url = non_empty(item.resolved_url);
+        let domain = resolved_url
+            .as_deref()
+            .or(given_url.as_deref())
+            .and_then(domain_of);
+
+        Self {
+            item_id: item.item_id.0,
+            resolved_id: item.resolved_id.map(|id| id.0).filter(|id| id != "0"),
+            given_url,
+            given_title: non_empty(item.given_title),
+            resolved_url,
+            resolved_title: non_empty(item.resolved_title),
+            domain,
+            excerpt: non_empty(item.excerpt),
+            status: item.status.into(),
+            favorite: parse_flag(item.favorite.as_deref(), false),
+            is_article: parse_flag(item.is_article.as_deref(), true),
+            is_index: parse_flag(item.is_index.as_deref(), true),
+            has_image: item.has_image.into(),
+            has_video: item.has_video.into(),
+            word_count: item.word_count.and_then(|count| count.parse().ok()),
+            tags: parse_tags(item.tags.as_deref()),
+            authors: item
+                .authors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|author| ArticleAuthor {
+                    author_id: author.id.0,
+                    name: author.name,
+                    url: non_empty(Some(author.url)),
+                })
+                .collect(),
+            images: item
+                .images
+                .unwrap_or_default()
+                .into_iter()
+                .map(|image| ArticleImage {
+                    image_id: image.image_id.0,
+                    src: image.src,
+                    width: parse_dimension(&image.width),
+                    height: parse_dimension(&image.height),
+                    credit: non_empty(Some(image.credit)),
+                    caption: non_empty(Some(image.caption)),
+                })
+                .collect(),
+            videos: item
+                .videos
+                .unwrap_or_default()
+                .into_iter()
+                .map(|video| ArticleVideo {
+                    video_id: video.video_id.0,
+                    src: video.src,
+                    width: parse_dimension(&video.width),
+                    height: parse_dimension(&video.height),
+                    length: video.length.as_deref().and_then(parse_dimension),
+                    vid: non_empty(Some(video.vid)),
+                })
+                .collect(),
+            lang: non_empty(item.lang),
+            time_to_read: item.time_to_read.filter(|minutes| *minutes > 0),
+            listen_duration_estimate: item.listen_duration_estimate.filter(|secs| *secs > 0),
+            top_image_url: non_empty(item.top_image_url),
+            sort_id: item.sort_id,
+            time_added: parse_timestamp(item.time_added),
+            time_updated: parse_timestamp(item.time_updated),
+            time_read: parse_timestamp(item.time_read),
+            time_favorited: parse_timestamp(item.time_favorited),
+        }
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+/// Pocket encodes flags as `"0"` and `"1"`. Anything but `"0"` counts as set.
+pub(crate) fn parse_flag(flag: Option<&str>, default: bool) -> bool {
+    flag.map(|flag| flag.trim() != "0").unwrap_or(default)
+}
+
+/// Pocket sends `0` for dimensions and lengths it doesn't know.
+fn parse_dimension(value: &str) -> Option<u32> {
+    value.trim().parse().ok().filter(|value| *value > 0)
+}
+
+/// Pocket sends `0` for events that haven't happened, e.g. `time_read` of an unread article.
+fn parse_timestamp(timestamp: Option<Timestamp>) -> Option<DateTime<Utc>> {
+    timestamp
+        .filter(|timestamp| timestamp.0 > 0)
+        .and_then(|timestamp| Utc.timestamp_opt(timestamp.0, 0).single())
+}
+
+/// Tags come either as Pocket's JSON object keyed by tag name, or as a comma separated list.
+pub(crate) fn parse_tags(tags: Option<&str>) -> Vec<String> {
+    let Some(tags) = tags.filter(|tags| !tags.trim().is_empty()) else {
+        return vec![];
+    };
+
+    let mut tags = match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(tags) {
+        Ok(tags) => tags.into_iter().map(|(tag, _)| tag).collect::<Vec<_>>(),
+        Err(_) => tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+    };
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub(crate) fn domain_of(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+}
+
+/// Article as Pocket sends it, served by the deprecated `/v1/articles` and stored by syncs.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PocketArticle {
     pub item_id: String,
     pub resolved_id: Option<String>,
     pub given_url: Option<String>,
@@ -59,7 +297,7 @@ pub struct Article {
     pub top_image_url: Option<String>,
 }
 
-impl From<PocketItem> for Article {
+impl From<PocketItem> for PocketArticle {
     fn from(item: PocketItem) -> Self {
         Self {
             item_id: item.item_id.0,
@@ -93,7 +331,8 @@ impl From<PocketItem> for Article {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Pagination {
     pub page: u32,
 }
@@ -102,78 +341,212 @@ impl Pagination {
     const PER_PAGE: u32 = 30;
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct GetArticlesResponse {
     articles: Vec<Article>,
 }
 
-pub async fn get_articles(
-    State(pockety): State<Pockety>,
-    pagination: Query<Pagination>,
-    session_data: AuthzedSessionData,
-) -> ApiResult<WithRateLimits<GetArticlesResponse>> {
+#[derive(Serialize)]
+pub struct GetArticlesV1Response {
+    articles: Vec<PocketArticle>,
+}
+
+/// Retrieves a page of the user's library, leaving out the items Pocket couldn't parse, which
+/// have neither a title nor a url.
+async fn retrieve_articles(
+    pockety: &Pockety,
+    store: &Store,
+    session_store: &Cache,
+    keys: &CredentialsKeys,
+    session_data: &AuthzedSessionData,
+    pagination: Pagination,
+) -> Result<(Vec<PocketItem>, RateLimits), Error> {
     const LOG_TAG: &str = "[get_articles]";
 
-    let pagination: Pagination = pagination.0;
+    session_store
+        .check_pocket_quota(Some(&session_data.username), QuotaPriority::Interactive)
+        .await?;
 
-    pockety
+    let res = match pockety
         .retrieve()
-        .access_token(session_data.access_token)
+        .access_token(session_data.access_token.clone())
         .count(Pagination::PER_PAGE)
         .offset(Pagination::PER_PAGE * pagination.page)
         .execute()
-        .map_ok(|res| {
-            let rate_limits = res.rate_limits.into();
-            let articles = res
-                .data
-                .into_iter()
-                .map(Article::from)
-                .filter(|article| article.given_title.is_some() && article.given_url.is_some())
-                .collect::<Vec<_>>();
-
-            info!(
-                "{LOG_TAG} fetched {count} articles for user {username}. Current rate limits: {rate_limits:?}",
-                LOG_TAG = LOG_TAG,
-                count = articles.len(),
-                username = session_data.username
-            );
-
-            let mut headers = HeaderMap::new();
-            headers.append("cache-control", "max-age=3600".parse::<HeaderValue>().unwrap());
-            headers.append("age", "0".parse::<HeaderValue>().unwrap());
-
-            TypedResponse::new(Some(WithRateLimits {
-                data: GetArticlesResponse { articles },
-                rate_limits
-            })).headers(headers)
-        })
+        .instrument(pocket_span("retrieve"))
+        .inspect(|result| metrics().record_pocket_call("retrieve", result))
         .inspect_err(|e| debug!("{LOG_TAG} failed to fetch articles with error: {e:?}"))
-        .map_err(Error::from)
         .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            let e = session_store
+                .record_failed_pocket_call(Some(&session_data.username), e.into())
+                .await;
+            return Err(invalidate_on_pocket_auth_failure(
+                session_store,
+                store,
+                keys,
+                session_data,
+                e,
+            )
+            .await);
+        }
+    };
+
+    let rate_limits: RateLimits = res.rate_limits.into();
+    let _ = session_store
+        .record_pocket_call(Some(&session_data.username), Some(rate_limits))
+        .await;
+
+    let items = res
+        .data
+        .into_iter()
+        .filter(|item| item.given_title.is_some() && item.given_url.is_some())
+        .collect::<Vec<_>>();
+
+    info!(
+        "{LOG_TAG} fetched {count} articles for user {username}. Current rate limits: {rate_limits:?}",
+        count = items.len(),
+        username = session_data.username
+    );
+
+    Ok((items, rate_limits))
+}
+
+fn cache_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.append(
+        "cache-control",
+        "max-age=3600".parse::<HeaderValue>().unwrap(),
+    );
+    headers.append("age", "0".parse::<HeaderValue>().unwrap());
+    headers
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/articles",
+    tag = "articles",
+    params(Pagination),
+    responses(
+        (status = 200, description = "A page of the user's Pocket library", body = ArticlesWithRateLimits),
+        (status = "4XX", description = "Not signed in, Pocket authorization revoked or rate limited", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_articles(
+    State(pockety): State<Pockety>,
+    State(store): State<Store>,
+    State(session_store): State<Cache>,
+    State(config): State<Config>,
+    pagination: Query<Pagination>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<WithRateLimits<GetArticlesResponse>> {
+    let (items, rate_limits) = retrieve_articles(
+        &pockety,
+        &store,
+        &session_store,
+        &config.pocket_credentials_keys,
+        &session_data,
+        pagination.0,
+    )
+    .await?;
+    let articles = items.into_iter().map(Article::from).collect();
+
+    Ok(TypedResponse::new(Some(WithRateLimits {
+        data: GetArticlesResponse { articles },
+        rate_limits,
+    }))
+    .headers(cache_headers()))
+}
+
+/// Serves articles as Pocket sends them, for the clients of `/v1` that haven't moved to `/v2`.
+pub async fn get_articles_v1(
+    State(pockety): State<Pockety>,
+    State(store): State<Store>,
+    State(session_store): State<Cache>,
+    State(config): State<Config>,
+    pagination: Query<Pagination>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<WithRateLimits<GetArticlesV1Response>> {
+    let (items, rate_limits) = retrieve_articles(
+        &pockety,
+        &store,
+        &session_store,
+        &config.pocket_credentials_keys,
+        &session_data,
+        pagination.0,
+    )
+    .await?;
+    let articles = items.into_iter().map(PocketArticle::from).collect();
+
+    Ok(TypedResponse::new(Some(WithRateLimits {
+        data: GetArticlesV1Response { articles },
+        rate_limits,
+    }))
+    .headers(cache_headers()))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v2/articles/simulated-sync",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Same events as `/articles/sync`, without calling Pocket", body = String, content_type = "text/event-stream"),
+        (status = 503, description = "Server is shutting down", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn simulate_sync_articles(
+    State(shutdown): State<Shutdown>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let sync_guard = shutdown.track_sync()?;
+
     let ceil = 10;
-    let stream = stream::iter(0..ceil)
-        .enumerate()
+    let stream = shutdown
+        .until_draining(stream::iter(0..ceil).enumerate())
         .then(move |(idx, _)| async move {
             tokio::time::sleep(Duration::from_millis(500)).await;
             Ok(Event::default().data(format!("{},{}", idx, ceil)))
         })
+        .chain(shutdown.drain_notice())
         .inspect_ok(|event| info!("Published event: {event:?}"))
-        .inspect_err(|e| error!("Failed to publish event: {e:?}"));
+        .inspect_err(|e| error!("Failed to publish event: {e:?}"))
+        // Keep the sync registered until the stream is done or the client hung up
+        .map(move |event| {
+            let _ = &sync_guard;
+            event
+        });
 
-    return Ok(Sse::new(stream).keep_alive(KeepAlive::default()));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
+/// Stores the user's whole Pocket library, reporting progress as server-sent events with
+/// `<index>,<total>` as data. A final `shutdown` event tells that the sync was interrupted.
+#[utoipa::path(
+    get,
+    path = "/v2/articles/sync",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Progress of the sync", body = String, content_type = "text/event-stream"),
+        (status = "4XX", description = "Not signed in, Pocket authorization revoked or rate limited", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 503, description = "Server is shutting down", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
 pub async fn sync_articles(
     State(pockety): State<Pockety>,
     State(store): State<Store>,
+    State(session_store): State<Cache>,
+    State(shutdown): State<Shutdown>,
+    State(config): State<Config>,
     session_data: AuthzedSessionData,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
     const LOG_TAG: &str = "[sync_articles]";
 
+    let sync_guard = shutdown.track_sync()?;
+    let sync_timer = metrics().start_sync("manual");
+
     let user_id: i32 = match fetch_user(store.clone(), &session_data.username).await? {
         Some(user) => user.id,
         None => {
@@ -181,54 +554,257 @@ pub async fn sync_articles(
         }
     };
 
-    let articles = pockety
+    session_store
+        .check_pocket_quota(Some(&session_data.username), QuotaPriority::Background)
+        .await?;
+
+    let res = match pockety
         .retrieve()
-        .access_token(session_data.access_token)
+        .access_token(session_data.access_token.clone())
         .execute()
-        .map_ok(|res| res.data)
+        .instrument(pocket_span("retrieve"))
+        .inspect(|result| metrics().record_pocket_call("retrieve", result))
         .inspect_err(|e| debug!("{LOG_TAG} Failed to fetch articles with error: {e:?}"))
-        .await?;
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            let e = session_store
+                .record_failed_pocket_call(Some(&session_data.username), e.into())
+                .await;
+            return Err(invalidate_on_pocket_auth_failure(
+                &session_store,
+                &store,
+                &config.pocket_credentials_keys,
+                &session_data,
+                e,
+            )
+            .await);
+        }
+    };
+
+    let _ = session_store
+        .record_pocket_call(Some(&session_data.username), Some(res.rate_limits.into()))
+        .await;
+    let articles = res.data;
 
     let article_len = articles.len();
 
-    let stream = stream::iter(articles)
-        .enumerate()
+    let stream = shutdown
+        .until_draining(stream::iter(articles).enumerate())
         .then(move |(idx, article)| {
             let store = store.clone();
             async move {
-                let article = Article::from(article);
-                let article_model =
-                    convert_article_to_article_model(article.clone(), user_id).unwrap();
-                let article_id = store.upsert_article(article_model).await.unwrap();
-
-                let article_video_models =
-                    convert_article_to_article_video_models(article.clone(), article_id).unwrap();
-                for article_video_model in article_video_models {
-                    let _ = store
-                        .upsert_article_video(article_id, article_video_model)
-                        .await;
-                }
-
-                let article_image_models =
-                    convert_article_to_article_image_models(article.clone(), article_id).unwrap();
-                for article_image_model in article_image_models {
-                    let _ = store
-                        .upsert_article_image(article_id, article_image_model)
-                        .await;
-                }
-
-                let article_author_models =
-                    convert_article_to_article_author_models(article, article_id).unwrap();
-                for article_author_model in article_author_models {
-                    let _ = store
-                        .upsert_article_author(article_id, article_author_model)
-                        .await;
+                let item_id = article.item_id.0.clone();
+                let stored = store_article(&store, article, user_id).await;
+                if let Err(e) = &stored {
+                    error!("{LOG_TAG} Failed to store article {item_id}. Error: {e:?}");
                 }
+                metrics().record_sync_item(stored.is_ok());
 
                 Ok(Event::default().data(format!("{idx},{article_len}")))
             }
+        })
+        .chain(shutdown.drain_notice())
+        // Keep the sync registered and timed until the stream is done or the client hung up
+        .map(move |event| {
+            let _ = (&sync_guard, &sync_timer);
+            event
         });
 
     Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
     // }
 }
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReadingTimeQuery {
+    /// Only the articles with this tag
+    pub tag: Option<String>,
+    /// Only the articles in this language, e.g. `en`
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReadingQueueQuery {
+    /// Time to fill, at most a day
+    pub minutes: u32,
+    /// Only the articles with this tag
+    pub tag: Option<String>,
+    /// Only the articles in this language, e.g. `en`
+    pub lang: Option<String>,
+}
+
+impl ReadingQueueQuery {
+    const MAX_MINUTES: u32 = 24 * 60;
+}
+
+/// Estimates of the unread articles of the user's stored library, oldest first.
+async fn estimate_unread(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+    filter: ReadingFilter,
+) -> Result<Vec<EstimatedArticle>, Error> {
+    let user_id = fetch_user(store.clone(), &session_data.username)
+        .await?
+        .map(|user| user.id)
+        .ok_or_else(|| Error::Api(ApiError::Unauthorized("User not found".to_string())))?;
+    let articles = fetch_articles(store.clone(), user_id).await?;
+
+    Ok(Estimator::new(&articles).unread(&articles, &filter))
+}
+
+/// Time it takes to read the unread articles synced from Pocket. Articles Pocket has no reading
+/// time for are estimated from their word count, or the average of their domain.
+#[utoipa::path(
+    get,
+    path = "/v2/articles/reading-time",
+    tag = "articles",
+    params(ReadingTimeQuery),
+    responses(
+        (status = 200, description = "Reading time of the unread articles", body = ReadingTime),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_reading_time(
+    State(store): State<Store>,
+    Query(query): Query<ReadingTimeQuery>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<ReadingTime> {
+    let filter = ReadingFilter {
+        tag: query.tag,
+        lang: query.lang,
+    };
+    let unread = estimate_unread(&store, &session_data, filter).await?;
+
+    Ok(TypedResponse::new(Some(reading_time(&unread))))
+}
+
+/// Unread articles to read in the given time, oldest first.
+#[utoipa::path(
+    get,
+    path = "/v2/articles/reading-queue",
+    tag = "articles",
+    params(ReadingQueueQuery),
+    responses(
+        (status = 200, description = "Articles fitting in the time budget", body = ReadingQueue),
+        (status = "4XX", description = "Invalid budget or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_reading_queue(
+    State(store): State<Store>,
+    Query(query): Query<ReadingQueueQuery>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<ReadingQueue> {
+    if !(1..=ReadingQueueQuery::MAX_MINUTES).contains(&query.minutes) {
+        return Err(Error::Api(ApiError::BadRequest(format!(
+            "minutes must be between 1 and {}",
+            ReadingQueueQuery::MAX_MINUTES
+        ))));
+    }
+
+    let filter = ReadingFilter {
+        tag: query.tag,
+        lang: query.lang,
+    };
+    let unread = estimate_unread(&store, &session_data, filter).await?;
+
+    Ok(TypedResponse::new(Some(reading_queue(
+        unread,
+        query.minutes,
+    ))))
+}
+
+#[cfg(test)]
+mod test {
+    use pockety::models::ItemId;
+
+    use super::*;
+
+    fn pocket_item() -> PocketItem {
+        PocketItem {
+            item_id: ItemId("229279689".to_string()),
+            resolved_id: Some(ItemId("229279689".to_string())),
+            given_url: Some("http://www.grantland.com/blog/the-triangle/post".to_string()),
+            given_title: Some("The Massive Ryder Cup Preview".to_string()),
+            favorite: Some("1".to_string()),
+            status: ItemStatus::Archived,
+            time_added: Some(Timestamp(1473000000)),
+            time_updated: Some(Timestamp(1473000060)),
+            time_read: Some(Timestamp(0)),
+            time_favorited: Some(Timestamp(0)),
+            sort_id: Some(0),
+            resolved_url: Some("https://www.grantland.com/blog/the-triangle/post/".to_string()),
+            resolved_title: Some("".to_string()),
+            excerpt: Some("The list of things I love about the Ryder Cup".to_string()),
+            is_article: Some("1".to_string()),
+            is_index: Some("0".to_string()),
+            has_image: Some(ItemHas::Yes),
+            has_video: Some(ItemHas::No),
+            word_count: Some("3197".to_string()),
+            tags: Some(r#"{"sports":{"tag":"sports"},"golf":{"tag":"golf"}}"#.to_string()),
+            authors: None,
+            images: Some(vec![ItemImage {
+                item_id: ItemId("229279689".to_string()),
+                image_id: ItemId("1".to_string()),
+                src: "https://example.com/cup.jpg".to_string(),
+                width: "0".to_string(),
+                height: "480".to_string(),
+                credit: "".to_string(),
+                caption: "The cup".to_string(),
+            }]),
+            videos: None,
+            lang: Some("en".to_string()),
+            time_to_read: Some(15),
+            listen_duration_estimate: Some(1238),
+            top_image_url: None,
+        }
+    }
+
+    #[test]
+    fn parses_pocket_fields_into_types() {
+        let article = Article::from(pocket_item());
+
+        assert!(article.favorite);
+        assert!(article.is_article);
+        assert!(!article.is_index);
+        assert_eq!(article.status, ArticleStatus::Archived);
+        assert_eq!(article.has_image, MediaPresence::Contains);
+        assert_eq!(article.has_video, MediaPresence::None);
+        assert_eq!(article.word_count, Some(3197));
+        assert_eq!(article.tags, vec!["golf", "sports"]);
+        assert_eq!(article.domain.as_deref(), Some("grantland.com"));
+        assert_eq!(article.resolved_title, None);
+        assert_eq!(
+            article.time_added.map(|time| time.to_rfc3339()).as_deref(),
+            Some("2016-09-04T14:40:00+00:00")
+        );
+        assert_eq!(article.time_read, None);
+        assert_eq!(article.images[0].width, None);
+        assert_eq!(article.images[0].height, Some(480));
+        assert_eq!(article.images[0].credit, None);
+    }
+
+    #[test]
+    fn parses_comma_separated_tags() {
+        assert_eq!(
+            parse_tags(Some("reading, rust,,reading")),
+            vec!["reading", "rust"]
+        );
+        assert!(parse_tags(Some("")).is_empty());
+        assert!(parse_tags(None).is_empty());
+    }
+
+    #[test]
+    fn serializes_timestamps_as_rfc3339() {
+        let json = serde_json::to_value(Article::from(pocket_item())).unwrap();
+
+        assert_eq!(json["timeAdded"], "2016-09-04T14:40:00Z");
+        assert_eq!(json["status"], "archived");
+        assert_eq!(json["hasImage"], "contains");
+        assert_eq!(json["favorite"], true);
+    }
+}
@@ -9,26 +9,34 @@ use axum::{
     },
     Json, TypedHeader,
 };
-use axum_extra::extract::cookie::{Cookie, Expiration, SameSite};
+use axum_extra::extract::cookie::{Cookie, Expiration};
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
-use futures::TryFutureExt;
+use futures::{FutureExt, TryFutureExt};
 use once_cell::sync::Lazy;
 use pockety::{
     GetAccessTokenResponse as PocketyGetAccessTokenResponse,
     GetRequestTokenResponse as PocketyGetRequestTokenResponse, Pockety,
 };
-use redis::AsyncCommands;
+use redis::{AsyncCommands, SetExpiry, SetOptions};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use time::{Duration, OffsetDateTime};
-use tracing::{debug, error, info};
+use time::OffsetDateTime;
+use tracing::{debug, error, info, Instrument};
+use utoipa::ToSchema;
 
 use crate::{
+    credentials::store_pocket_credentials,
     db::{create_new_user_if_not_exists, fetch_user},
     error::{ApiError, Error},
+    metrics::metrics,
     oauth::{generate_csrf_token, OAuthState},
-    session::{generate_session_id, hash, AuthzedSessionData, ConPool, RequestTokenSessionData},
+    quota::{unix_now, PocketQuotaStore, QuotaPriority},
+    session::{
+        generate_session_id, hash, user_sessions_key, AuthzedSessionData, ConPool,
+        RequestTokenSessionData, REQUEST_TOKEN_SESSION_LIFETIME,
+    },
+    telemetry::{pocket_span, redis_span},
     ApiResult, Config, TypedResponse, SESSION_ID_COOKIE_NAME,
 };
 
@@ -39,6 +47,15 @@ pub struct GetRequestTokenResponse {
     pub auth_uri: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/v2/auth/authn",
+    tag = "auth",
+    responses(
+        (status = 303, description = "Redirects to Pocket's authorization page", headers(("location" = String))),
+        (status = "4XX", description = "Rate limited or rejected by Pocket", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn get_request_token(
     State(pockety): State<Pockety>,
     State(config): State<Config>,
@@ -46,21 +63,36 @@ pub async fn get_request_token(
 ) -> ApiResult<()> {
     const LOG_TAG: &str = "[get_request_token]";
 
-    // TODO: implement rate limiting
     // TODO: is there a way to check if a user is already authed?
     // TODO: use an actual cookie manager, since we're currently not signing or encrypting them
-    let PocketyGetRequestTokenResponse { code, .. } = pockety
+    session_store
+        .check_pocket_quota(None, QuotaPriority::Interactive)
+        .await?;
+
+    let PocketyGetRequestTokenResponse { code, .. } = match pockety
         .get_request_token(None)
-        .inspect_ok(|r| debug!("{LOG_TAG} got request token from pocket: res: {r:?}"))
+        .instrument(pocket_span("oauth/request"))
+        .inspect(|result| metrics().record_pocket_call("oauth/request", result))
+        .inspect_ok(|_| debug!("{LOG_TAG} got request token from pocket"))
         .inspect_err(|e| debug!("{LOG_TAG} failed to get request token from pocket. err: {e}"))
         .map_ok(|res| res.data)
-        .await?;
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            return Err(session_store
+                .record_failed_pocket_call(None, e.into())
+                .await)
+        }
+    };
+    let _ = session_store.record_pocket_call(None, None).await;
 
     let csrf_token = generate_csrf_token();
 
     let session_data = RequestTokenSessionData {
         request_token: code.clone(),
         csrf_token: csrf_token.clone(),
+        created_at: Some(unix_now()),
     };
     let (session_id, hashed_session_id) = generate_session_id()?;
 
@@ -73,27 +105,28 @@ pub async fn get_request_token(
         .await?;
 
     let stringified_session_data = serde_json::to_string(&session_data)?;
-    con.set(hashed_session_id.0.clone(), stringified_session_data)
-        .inspect_ok(|_| {
-            info!(
-                "{LOG_TAG} Set new session: {} to store!",
-                hashed_session_id.0
-            );
-        })
-        .map_err(|e| {
-            error!("{LOG_TAG} Failed to store session. Error: {e:?}");
-            Error::Session("Failed to store session".to_string())
-        })
-        .await?;
-
-    debug!("{LOG_TAG} generated following session_data: {session_data:?}");
+    con.set_ex::<_, _, ()>(
+        hashed_session_id.0.clone(),
+        stringified_session_data,
+        REQUEST_TOKEN_SESSION_LIFETIME.as_secs() as usize,
+    )
+    .instrument(redis_span("SETEX"))
+    .inspect_ok(|_| {
+        info!(
+            "{LOG_TAG} Set new session: {} to store!",
+            hashed_session_id.0
+        );
+    })
+    .map_err(|e| {
+        error!("{LOG_TAG} Failed to store session. Error: {e:?}");
+        Error::Session("Failed to store session".to_string())
+    })
+    .await?;
 
     // state
     let token = OAuthState::new(code.clone(), session_id.0, csrf_token)
         .into_token(config.jws_signing_secret, config.jwe_encryption_key)?;
 
-    debug!("{LOG_TAG} create encrypted token: {token:?}");
-
     let auth_uri: HeaderValue = format!(
         "{}?request_token={code}&redirect_uri={}%3Fstate={token}&state={token}",
         Pockety::AUTHORIZE_URL,
@@ -114,18 +147,28 @@ pub async fn get_request_token(
         .status_code(StatusCode::SEE_OTHER))
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAccessTokenRequest {
     pub state: String,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAccessTokenResponse {
     pub username: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/v2/auth/authz",
+    tag = "auth",
+    request_body = GetAccessTokenRequest,
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = GetAccessTokenResponse, headers(("set-cookie" = String))),
+        (status = "4XX", description = "Invalid state or rejected by Pocket", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn get_access_token(
     State(pockety): State<Pockety>,
     State(config): State<Config>,
@@ -158,6 +201,7 @@ pub async fn get_access_token(
     let hashed_session_id = hashed_session_id.as_str();
     let session_data = con
         .get(hashed_session_id)
+        .instrument(redis_span("GET"))
         .inspect_ok(|_| {
             info!("{LOG_TAG} Found session: {hashed_session_id} in store!")
         })
@@ -180,16 +224,31 @@ pub async fn get_access_token(
         )));
     }
 
-    let res: PocketyGetAccessTokenResponse = pockety
+    session_store
+        .check_pocket_quota(None, QuotaPriority::Interactive)
+        .await?;
+
+    let res: PocketyGetAccessTokenResponse = match pockety
         .get_access_token(request_token.clone())
-        .inspect_ok(|r| debug!("{LOG_TAG} successfully acquired access_token from pocket: {r:?}"))
+        .instrument(pocket_span("oauth/authorize"))
+        .inspect(|result| metrics().record_pocket_call("oauth/authorize", result))
+        .inspect_ok(|_| debug!("{LOG_TAG} successfully acquired access_token from pocket"))
         .inspect_err(|e| debug!("{LOG_TAG} failed to get access_token from pocket: {e:?}"))
         .map_ok(|res| res.data)
-        .map_err(Error::from)
-        .await?;
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            return Err(session_store
+                .record_failed_pocket_call(None, e.into())
+                .await)
+        }
+    };
+    let _ = session_store.record_pocket_call(None, None).await;
 
     // create new session with new crsf token and destroy previous session
     con.del(hashed_session_id)
+        .instrument(redis_span("DEL"))
         .map_err(|e| {
             // TODO: consider whether failing to destroy an old session should fail the entire
             // request
@@ -202,17 +261,49 @@ pub async fn get_access_token(
     let session_data = AuthzedSessionData {
         access_token: res.access_token.clone(),
         username: res.username.clone(),
+        csrf_token: generate_csrf_token(),
+        reauth_required: false,
+        created_at: Some(unix_now()),
+        hashed_session_id: hashed_session_id.clone(),
     };
 
-    if fetch_user(db_pool.clone(), &session_data.username)
-        .await?
-        .is_none()
-    {
-        create_new_user_if_not_exists(db_pool.clone(), &session_data.username).await?;
-    }
+    let user = match fetch_user(db_pool.clone(), &session_data.username).await? {
+        Some(user) => user,
+        None => {
+            create_new_user_if_not_exists(db_pool.clone(), &session_data.username).await?;
+            fetch_user(db_pool.clone(), &session_data.username)
+                .await?
+                .ok_or_else(|| Error::Db("User not found!".to_string()))?
+        }
+    };
+
+    // Kept for the jobs acting for the user once the session expired, e.g. scheduled syncs
+    store_pocket_credentials(
+        &db_pool,
+        &config.pocket_credentials_keys,
+        user.id,
+        &session_data.access_token,
+    )
+    .await?;
 
+    // Indexed under the user, so their sessions can be signed out without scanning the store
     let stringified_session_data = serde_json::to_string(&session_data)?;
-    con.set(hashed_session_id.0.clone(), stringified_session_data)
+    let session_lifetime = config.session_lifetime.as_secs() as usize;
+    let user_sessions = user_sessions_key(&session_data.username);
+    redis::pipe()
+        .atomic()
+        .set_ex(
+            &hashed_session_id.0,
+            stringified_session_data,
+            session_lifetime,
+        )
+        .ignore()
+        .sadd(&user_sessions, &hashed_session_id.0)
+        .ignore()
+        .expire(&user_sessions, session_lifetime)
+        .ignore()
+        .query_async::<_, ()>(&mut *con)
+        .instrument(redis_span("MULTI"))
         .map_err(|e| {
             error!(
                 "{LOG_TAG} failed to set new session: {} with error: {e:?}",
@@ -228,7 +319,7 @@ pub async fn get_access_token(
         Cookie::build(SESSION_ID_COOKIE_NAME, session_id.0)
             .http_only(true)
             .expires(Expiration::from(
-                OffsetDateTime::now_utc() + Duration::minutes(60),
+                OffsetDateTime::now_utc() + config.session_lifetime,
             ))
             .path("/")
             .finish()
@@ -243,25 +334,33 @@ pub async fn get_access_token(
     .headers(headers))
 }
 
-#[derive(Serialize, Default, Debug, Clone)]
+#[derive(Serialize, Default, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetSessionResponse {
     has_session: bool,
     username: Option<String>,
+    csrf_token: Option<String>,
+    /// The session exists, but its Pocket authorization was revoked or expired
+    reauth_required: bool,
 }
 
 static NOT_AUTHZED_RESPONSE: Lazy<TypedResponse<GetSessionResponse>> =
     Lazy::new(|| TypedResponse::new(Some(GetSessionResponse::default())));
 
 // responds with user authenticated or not
+#[utoipa::path(
+    get,
+    path = "/v2/auth/session",
+    tag = "auth",
+    responses((status = 200, description = "Whether the user is signed in", body = GetSessionResponse)),
+    security((), ("session" = []))
+)]
 pub async fn get_session(
     State(session_store): State<Arc<Pool<RedisConnectionManager>>>,
     TypedHeader(cookies): TypedHeader<headers::Cookie>,
 ) -> ApiResult<GetSessionResponse> {
     const LOG_TAG: &str = "[get_session]";
 
-    tracing::debug!("{LOG_TAG} cookies: {cookies:?}", cookies = cookies);
-
     let session_cookie = match cookies.get(SESSION_ID_COOKIE_NAME) {
         Some(cookie) => cookie,
         None => return Ok(NOT_AUTHZED_RESPONSE.clone()),
@@ -274,11 +373,10 @@ pub async fn get_session(
 
     let hashed_session_id = hash(session_cookie);
     match con
-        .get(hashed_session_id)
+        .get(&hashed_session_id)
+        .instrument(redis_span("GET"))
         .map_err(|e| {
-            tracing::error!(
-                "{LOG_TAG} Failed to get SessionData with key: {session_cookie}. Error: {e}"
-            );
+            tracing::error!("{LOG_TAG} Failed to get SessionData. Error: {e}");
             Error::Session("Failed to get SessionData".to_string())
         })
         .and_then(|v: String| async move {
@@ -291,10 +389,43 @@ pub async fn get_session(
         })
         .await
     {
-        Ok(session_data) => Ok(TypedResponse::new(Some(GetSessionResponse {
-            has_session: true,
-            username: Some(session_data.username),
-        }))),
+        Ok(session_data) if session_data.reauth_required => {
+            Ok(TypedResponse::new(Some(GetSessionResponse {
+                has_session: false,
+                username: Some(session_data.username),
+                csrf_token: None,
+                reauth_required: true,
+            })))
+        }
+        Ok(mut session_data) => {
+            // Sessions created before csrf tokens were issued get one on their next read
+            if session_data.csrf_token.is_empty() {
+                session_data.csrf_token = generate_csrf_token();
+                let stored = match serde_json::to_string(&session_data) {
+                    Ok(stringified_session_data) => con
+                        .set_options::<_, _, ()>(
+                            &hashed_session_id,
+                            stringified_session_data,
+                            SetOptions::default().with_expiration(SetExpiry::KEEPTTL),
+                        )
+                        .instrument(redis_span("SET"))
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                if let Err(e) = stored {
+                    tracing::error!("{LOG_TAG} Failed to store csrf token. Error: {e}");
+                    return Err(Error::Session("Failed to store csrf token".to_string()));
+                }
+            }
+
+            Ok(TypedResponse::new(Some(GetSessionResponse {
+                has_session: true,
+                username: Some(session_data.username),
+                csrf_token: Some(session_data.csrf_token),
+                reauth_required: false,
+            })))
+        }
         Err(_) => Ok(NOT_AUTHZED_RESPONSE.clone()),
     }
 }
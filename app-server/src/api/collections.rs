@@ -0,0 +1,312 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::me::user_id,
+    collections::{
+        Collection, CollectionInput, CollectionItem, CollectionItemPatch, CollectionOrder,
+        CollectionOwner, NewCollectionItem,
+    },
+    db::{
+        delete_collection, delete_collection_item, fetch_collectable_article_id, fetch_collection,
+        fetch_collection_items, fetch_collections, insert_collection, insert_collection_item,
+        reorder_collection_items, update_collection, update_collection_item,
+    },
+    error::{ApiError, Error},
+    session::AuthzedSessionData,
+    ApiResult, Store, TypedResponse,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct GetCollectionsResponse {
+    pub(crate) collections: Vec<Collection>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GetCollectionResponse {
+    pub(crate) collection: Collection,
+    pub(crate) items: Vec<CollectionItem>,
+}
+
+/// The signed in user's collection, which items are only ever read and written through.
+async fn owned_collection(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+    collection_id: i32,
+) -> Result<Collection, Error> {
+    let user_id = user_id(store, session_data).await?;
+    fetch_collection(store.clone(), CollectionOwner::User(user_id), collection_id)
+        .await?
+        .ok_or_else(collection_not_found)
+}
+
+fn collection_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Collection not found".to_string()))
+}
+
+fn item_not_found() -> Error {
+    Error::Api(ApiError::NotFound(
+        "Article not found in the collection".to_string(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/collections",
+    tag = "collections",
+    responses(
+        (status = 200, description = "Collections of the user, last updated first", body = GetCollectionsResponse),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_collections(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetCollectionsResponse> {
+    let user_id = user_id(&store, &session_data).await?;
+    let collections = fetch_collections(store, CollectionOwner::User(user_id)).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionsResponse {
+        collections,
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/collections",
+    tag = "collections",
+    request_body = CollectionInput,
+    responses(
+        (status = 201, description = "The new, empty collection", body = Collection),
+        (status = "4XX", description = "Invalid collection or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_collection(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Collection> {
+    let input = CollectionInput::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+    let collection =
+        insert_collection(store, CollectionOwner::User(user_id), user_id, &input).await?;
+
+    Ok(TypedResponse::new(Some(collection)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/collections/{collection_id}",
+    tag = "collections",
+    params(("collection_id" = i32, Path, description = "Id of the collection")),
+    responses(
+        (status = 200, description = "The collection and its articles, in order", body = GetCollectionResponse),
+        (status = "4XX", description = "Not signed in or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_collection(
+    State(store): State<Store>,
+    Path(collection_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetCollectionResponse> {
+    let collection = owned_collection(&store, &session_data, collection_id).await?;
+    let items = fetch_collection_items(store, collection.id).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionResponse {
+        collection,
+        items,
+    })))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/collections/{collection_id}",
+    tag = "collections",
+    params(("collection_id" = i32, Path, description = "Id of the collection")),
+    request_body = CollectionInput,
+    responses(
+        (status = 200, description = "The changed collection", body = Collection),
+        (status = "4XX", description = "Invalid collection, not signed in or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_collection_details(
+    State(store): State<Store>,
+    Path(collection_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Collection> {
+    let input = CollectionInput::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+    let collection =
+        update_collection(store, CollectionOwner::User(user_id), collection_id, &input)
+            .await?
+            .ok_or_else(collection_not_found)?;
+
+    Ok(TypedResponse::new(Some(collection)))
+}
+
+/// Deletes the collection, the articles stay in the library.
+#[utoipa::path(
+    delete,
+    path = "/v2/collections/{collection_id}",
+    tag = "collections",
+    params(("collection_id" = i32, Path, description = "Id of the collection")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = "4XX", description = "Not signed in or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_collection(
+    State(store): State<Store>,
+    Path(collection_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let user_id = user_id(&store, &session_data).await?;
+    if !delete_collection(store, CollectionOwner::User(user_id), collection_id).await? {
+        return Err(collection_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Adds an article of the library at the end of the collection.
+#[utoipa::path(
+    post,
+    path = "/v2/collections/{collection_id}/items",
+    tag = "collections",
+    params(("collection_id" = i32, Path, description = "Id of the collection")),
+    request_body = NewCollectionItem,
+    responses(
+        (status = 201, description = "The added article", body = CollectionItem),
+        (status = "4XX", description = "Invalid item, article already in the collection, not signed in, or unknown collection or article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn add_collection_item(
+    State(store): State<Store>,
+    Path(collection_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CollectionItem> {
+    let input = NewCollectionItem::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+    let collection = fetch_collection(store.clone(), CollectionOwner::User(user_id), collection_id)
+        .await?
+        .ok_or_else(collection_not_found)?;
+    let article_id = fetch_collectable_article_id(store.clone(), user_id, &input.item_id)
+        .await?
+        .ok_or_else(|| Error::Api(ApiError::NotFound("Article not found".to_string())))?;
+
+    let item = insert_collection_item(
+        store,
+        collection.id,
+        article_id,
+        input.commentary.as_deref(),
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::Api(ApiError::BadRequest(
+            "Article is already in the collection".to_string(),
+        ))
+    })?;
+
+    Ok(TypedResponse::new(Some(item)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/collections/{collection_id}/items/{item_id}",
+    tag = "collections",
+    params(
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+    ),
+    request_body = CollectionItemPatch,
+    responses(
+        (status = 200, description = "The changed article", body = CollectionItem),
+        (status = "4XX", description = "Invalid commentary, not signed in or article not in the collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_collection_item_commentary(
+    State(store): State<Store>,
+    Path((collection_id, item_id)): Path<(i32, String)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CollectionItem> {
+    let patch = CollectionItemPatch::from_json(&body)?;
+    let collection = owned_collection(&store, &session_data, collection_id).await?;
+    let item = update_collection_item(store, collection.id, &item_id, patch.commentary.as_deref())
+        .await?
+        .ok_or_else(item_not_found)?;
+
+    Ok(TypedResponse::new(Some(item)))
+}
+
+/// Removes an article from the collection, it stays in the library.
+#[utoipa::path(
+    delete,
+    path = "/v2/collections/{collection_id}/items/{item_id}",
+    tag = "collections",
+    params(
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+    ),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = "4XX", description = "Not signed in or article not in the collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_collection_item(
+    State(store): State<Store>,
+    Path((collection_id, item_id)): Path<(i32, String)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let collection = owned_collection(&store, &session_data, collection_id).await?;
+    if !delete_collection_item(store, collection.id, &item_id).await? {
+        return Err(item_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reorders the articles of the collection. The order has to list every article of the collection.
+#[utoipa::path(
+    put,
+    path = "/v2/collections/{collection_id}/order",
+    tag = "collections",
+    params(("collection_id" = i32, Path, description = "Id of the collection")),
+    request_body = CollectionOrder,
+    responses(
+        (status = 200, description = "The articles of the collection, in their new order", body = GetCollectionResponse),
+        (status = "4XX", description = "Invalid order, not signed in or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn reorder_collection(
+    State(store): State<Store>,
+    Path(collection_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<GetCollectionResponse> {
+    let order = CollectionOrder::from_json(&body)?;
+    let collection = owned_collection(&store, &session_data, collection_id).await?;
+    reorder_collection_items(store.clone(), collection.id, &order).await?;
+    let items = fetch_collection_items(store, collection.id).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionResponse {
+        collection,
+        items,
+    })))
+}
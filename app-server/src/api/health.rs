@@ -0,0 +1,276 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use redis::aio::Connection;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{db::MIGRATOR, shutdown::Shutdown, Cache, Config, Store};
+
+/// Upper bound for a single dependency check, so a hung dependency fails the probe instead of
+/// hanging it.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ComponentStatus {
+    Up,
+    /// Working, but close to its limits. Doesn't make the instance unready.
+    Degraded,
+    Down,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentReport {
+    pub status: ComponentStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub status: ComponentStatus,
+    #[schema(value_type = BTreeMap<String, ComponentReport>)]
+    pub components: BTreeMap<&'static str, ComponentReport>,
+}
+
+impl HealthReport {
+    fn new(components: BTreeMap<&'static str, ComponentReport>) -> Self {
+        let status = components
+            .values()
+            .map(|component| component.status)
+            .max()
+            .unwrap_or(ComponentStatus::Up);
+        Self { status, components }
+    }
+}
+
+impl IntoResponse for HealthReport {
+    fn into_response(self) -> Response {
+        let status_code = match self.status {
+            ComponentStatus::Up | ComponentStatus::Degraded => StatusCode::OK,
+            ComponentStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        (status_code, Json(self)).into_response()
+    }
+}
+
+/// Liveness only tells whether the process is able to serve requests at all, so that a broken
+/// dependency doesn't get every instance restarted.
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    responses((status = 200, description = "The process is up", body = HealthReport))
+)]
+pub async fn livez() -> HealthReport {
+    HealthReport::new(BTreeMap::new())
+}
+
+/// Readiness checks every dependency a request may need, so traffic is only routed to instances
+/// that can actually serve it.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every dependency is up or degraded", body = HealthReport),
+        (status = 503, description = "A dependency is down or the server is shutting down", body = HealthReport),
+    )
+)]
+pub async fn readyz(
+    State(store): State<Store>,
+    State(cache): State<Cache>,
+    State(shutdown): State<Shutdown>,
+    State(config): State<Config>,
+) -> HealthReport {
+    let (postgres, redis) = tokio::join!(
+        timed(check_postgres(&store)),
+        timed(check_redis(&cache, config.redis_max_connections)),
+    );
+
+    let mut components = BTreeMap::from([("postgres", postgres), ("redis", redis)]);
+    if config.readiness_check_pocket {
+        components.insert("pocket", timed(check_pocket(&config)).await);
+    }
+    if shutdown.is_draining() {
+        components.insert(
+            "server",
+            ComponentReport {
+                status: ComponentStatus::Down,
+                latency_ms: 0,
+                detail: Some("Shutting down".to_string()),
+            },
+        );
+    }
+
+    HealthReport::new(components)
+}
+
+async fn timed<F>(check: F) -> ComponentReport
+where
+    F: Future<Output = (ComponentStatus, Option<String>)>,
+{
+    let start = Instant::now();
+    let (status, detail) = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| (ComponentStatus::Down, Some("Timed out".to_string())));
+
+    ComponentReport {
+        status,
+        latency_ms: start.elapsed().as_millis() as u64,
+        detail,
+    }
+}
+
+async fn check_postgres(store: &Store) -> (ComponentStatus, Option<String>) {
+    let applied = match sqlx::query_scalar::<_, i64>(
+        "SELECT version FROM _sqlx_migrations WHERE success = TRUE",
+    )
+    .fetch_all(&**store)
+    .await
+    {
+        Ok(applied) => applied,
+        Err(e) => {
+            tracing::warn!("Postgres readiness check failed. Error: {e:?}");
+            return (ComponentStatus::Down, Some("Unreachable".to_string()));
+        }
+    };
+
+    let pending = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .count();
+
+    if pending > 0 {
+        (
+            ComponentStatus::Down,
+            Some(format!("{pending} pending migration(s)")),
+        )
+    } else {
+        (ComponentStatus::Up, None)
+    }
+}
+
+async fn check_redis(cache: &Cache, max_connections: u32) -> (ComponentStatus, Option<String>) {
+    // Read before taking a connection for the ping, which would skew it
+    let state = cache.state();
+    let in_use = state.connections - state.idle_connections;
+    let saturated = state.idle_connections == 0 && state.connections >= max_connections;
+
+    // Waiting for a pooled connection would time the probe out on a saturated pool, which is
+    // degraded rather than down, so the ping goes through a connection of its own instead
+    let ping = if saturated {
+        match cache.dedicated_connection().await {
+            Ok(mut con) => ping_redis(&mut con).await,
+            Err(e) => Err(format!("{e:?}")),
+        }
+    } else {
+        match cache.get().await {
+            Ok(mut con) => ping_redis(&mut con).await,
+            Err(e) => Err(format!("{e:?}")),
+        }
+    };
+
+    match ping {
+        Err(e) => {
+            tracing::warn!("Redis readiness check failed. Error: {e}");
+            (ComponentStatus::Down, Some("Unreachable".to_string()))
+        }
+        Ok(_) if saturated || in_use >= max_connections => (
+            ComponentStatus::Degraded,
+            Some(format!(
+                "Pool saturated, {in_use} of {max_connections} connections in use"
+            )),
+        ),
+        Ok(_) => (ComponentStatus::Up, None),
+    }
+}
+
+async fn ping_redis(con: &mut Connection) -> Result<String, String> {
+    redis::cmd("PING")
+        .query_async(con)
+        .await
+        .map_err(|e| format!("{e:?}"))
+}
+
+async fn check_pocket(config: &Config) -> (ComponentStatus, Option<String>) {
+    if is_well_formed_consumer_key(&config.pocket_consumer_key) {
+        (ComponentStatus::Up, None)
+    } else {
+        (
+            ComponentStatus::Down,
+            Some("Consumer key is missing or malformed".to_string()),
+        )
+    }
+}
+
+/// Pocket consumer keys look like `12345-0123456789abcdef01234567`. Checked locally, since
+/// spending quota on probes isn't worth it.
+fn is_well_formed_consumer_key(key: &str) -> bool {
+    match key.split_once('-') {
+        Some((app_id, secret)) => {
+            !app_id.is_empty()
+                && app_id.chars().all(|c| c.is_ascii_digit())
+                && !secret.is_empty()
+                && secret.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn component(status: ComponentStatus) -> ComponentReport {
+        ComponentReport {
+            status,
+            latency_ms: 1,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn reports_the_worst_component_status() {
+        let report = HealthReport::new(BTreeMap::from([
+            ("postgres", component(ComponentStatus::Up)),
+            ("redis", component(ComponentStatus::Degraded)),
+        ]));
+        assert_eq!(report.status, ComponentStatus::Degraded);
+        assert_eq!(report.into_response().status(), StatusCode::OK);
+
+        let report = HealthReport::new(BTreeMap::from([
+            ("postgres", component(ComponentStatus::Down)),
+            ("redis", component(ComponentStatus::Degraded)),
+        ]));
+        assert_eq!(report.status, ComponentStatus::Down);
+        assert_eq!(
+            report.into_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[test]
+    fn validates_consumer_key_format() {
+        assert!(is_well_formed_consumer_key(
+            "12345-0123456789abcdef01234567"
+        ));
+        assert!(!is_well_formed_consumer_key(""));
+        assert!(!is_well_formed_consumer_key("consumer-key"));
+        assert!(!is_well_formed_consumer_key("12345-"));
+    }
+}
@@ -0,0 +1,138 @@
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::info;
+use utoipa::IntoParams;
+
+use crate::{
+    db::{delete_pocket_credentials, fetch_user, fetch_user_settings, update_user_settings},
+    error::{ApiError, Error},
+    session::{delete_user_sessions, AuthzedSessionData},
+    stats::{reading_stats, ReadingStats, DEFAULT_STATS_DAYS},
+    user_settings::{UserSettings, UserSettingsPatch},
+    ApiResult, Cache, Store, TypedResponse,
+};
+
+/// Id of the signed in user.
+pub(crate) async fn user_id(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+) -> Result<i32, Error> {
+    fetch_user(store.clone(), &session_data.username)
+        .await?
+        .map(|user| user.id)
+        .ok_or_else(|| Error::Api(ApiError::Unauthorized("User not found".to_string())))
+}
+
+/// Settings of the signed in user, defaults until they change one.
+#[utoipa::path(
+    get,
+    path = "/v2/me/settings",
+    tag = "me",
+    responses(
+        (status = 200, description = "The user's settings", body = UserSettings),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_settings(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<UserSettings> {
+    let user_id = user_id(&store, &session_data).await?;
+    let settings = fetch_user_settings(store, user_id).await?;
+
+    Ok(TypedResponse::new(Some(settings)))
+}
+
+/// Changes the given settings, leaving the others as they are.
+#[utoipa::path(
+    patch,
+    path = "/v2/me/settings",
+    tag = "me",
+    request_body = UserSettingsPatch,
+    responses(
+        (status = 200, description = "The user's settings after the change", body = UserSettings),
+        (status = "4XX", description = "Invalid settings or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn patch_settings(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<UserSettings> {
+    let patch = UserSettingsPatch::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+    let settings = update_user_settings(store, user_id, patch).await?;
+
+    Ok(TypedResponse::new(Some(settings)))
+}
+
+/// Disconnects the user's Pocket account: deletes the stored credentials and signs out every
+/// session, so nothing acts for the user until they authorize just-links again.
+#[utoipa::path(
+    delete,
+    path = "/v2/me/pocket",
+    tag = "me",
+    responses(
+        (status = 204, description = "Disconnected"),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn disconnect_pocket(
+    State(store): State<Store>,
+    State(session_store): State<Cache>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let user_id = user_id(&store, &session_data).await?;
+    delete_pocket_credentials(store, user_id).await?;
+    let sessions = delete_user_sessions(&session_store, &session_data.username).await?;
+    info!(
+        "Disconnected Pocket of user {}, signed out {sessions} session(s)",
+        session_data.username
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    /// Number of days covered, up to and including today. 30 by default, at most 365.
+    pub days: Option<u32>,
+}
+
+/// Reading activity of the user, for the metrics view.
+#[utoipa::path(
+    get,
+    path = "/v2/me/stats",
+    tag = "me",
+    params(StatsQuery),
+    responses(
+        (status = 200, description = "The user's reading stats", body = ReadingStats),
+        (status = "4XX", description = "Invalid period or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_stats(
+    State(store): State<Store>,
+    Query(query): Query<StatsQuery>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<ReadingStats> {
+    let user_id = user_id(&store, &session_data).await?;
+    let stats = reading_stats(
+        &store,
+        user_id,
+        query.days.unwrap_or(DEFAULT_STATS_DAYS),
+        Utc::now().date_naive(),
+    )
+    .await?;
+
+    Ok(TypedResponse::new(Some(stats)))
+}
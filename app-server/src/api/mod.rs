@@ -1,7 +1,16 @@
 use axum::response::IntoResponse;
 
+pub mod annotations;
 pub mod articles;
 pub mod auth;
+pub mod collections;
+pub mod health;
+pub mod me;
+pub mod openapi;
+pub mod pocket;
+pub mod redirect;
+pub mod shares;
+pub mod teams;
 
 pub async fn health_check() -> impl IntoResponse {
     "Healthy!"
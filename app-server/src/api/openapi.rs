@@ -0,0 +1,239 @@
+use axum::Json;
+use once_cell::sync::Lazy;
+use utoipa::{
+    openapi::{
+        self,
+        security::{ApiKey, ApiKeyValue, SecurityScheme},
+    },
+    Modify, OpenApi,
+};
+
+use crate::{
+    annotations::{
+        ArticleHighlight, ArticleNote, HighlightPatch, MatchSource, NewHighlight, NoteInput,
+        SearchResult,
+    },
+    api::{annotations, articles, auth, collections, health, me, pocket, redirect, shares, teams},
+    collections::{
+        Collection, CollectionInput, CollectionItem, CollectionItemPatch, CollectionOrder,
+        NewCollectionItem,
+    },
+    error::ProblemDetails,
+    quota::{PocketQuota, QuotaWindow},
+    reading_time::{EstimateSource, EstimatedArticle, ReadingQueue, ReadingTime},
+    shares::{CreatedShareLink, NewShareLink, ShareLink, SharedArticle, SharedPage},
+    stats::{BacklogSize, DayCount, NamedCount, ReadingStats, WeekCount},
+    teams::{
+        AcceptTeamInvitation, CreatedTeamInvitation, MemberPatch, NewTeamInvitation,
+        PendingTeamInvitation, Team, TeamInput, TeamMember, TeamRole,
+    },
+    user_settings::{UserSettings, UserSettingsPatch},
+    ArticlesWithRateLimits, RateLimits,
+};
+
+/// Requests are authenticated by the session cookie set by `/auth/authz`. State-changing requests
+/// also have to echo the session's csrf token.
+struct SessionSecurity;
+
+impl Modify for SessionSecurity {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(
+                crate::SESSION_ID_COOKIE_NAME,
+            ))),
+        );
+        components.add_security_scheme(
+            "csrf",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(
+                crate::csrf::CSRF_TOKEN_HEADER_NAME.as_str(),
+            ))),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "just-links app server",
+        description = "Reads and syncs the Pocket library of the signed in user"
+    ),
+    paths(
+        articles::get_articles,
+        articles::sync_articles,
+        articles::simulate_sync_articles,
+        articles::get_reading_time,
+        articles::get_reading_queue,
+        annotations::search,
+        annotations::get_notes,
+        annotations::create_note,
+        annotations::update_note,
+        annotations::delete_note,
+        annotations::get_highlights,
+        annotations::create_highlight,
+        annotations::update_highlight,
+        annotations::delete_highlight,
+        annotations::export_annotations,
+        collections::get_collections,
+        collections::create_collection,
+        collections::get_collection,
+        collections::update_collection_details,
+        collections::remove_collection,
+        collections::add_collection_item,
+        collections::update_collection_item_commentary,
+        collections::remove_collection_item,
+        collections::reorder_collection,
+        auth::get_request_token,
+        auth::get_access_token,
+        auth::get_session,
+        pocket::get_pocket_quota,
+        me::get_settings,
+        me::patch_settings,
+        me::disconnect_pocket,
+        me::get_stats,
+        redirect::open_article,
+        shares::get_share_links,
+        shares::create_share_link,
+        shares::revoke_share,
+        shares::view_share,
+        teams::get_teams,
+        teams::create_team,
+        teams::join_team,
+        teams::get_team,
+        teams::update_team,
+        teams::remove_team,
+        teams::get_team_invitations,
+        teams::create_team_invitation,
+        teams::remove_team_invitation,
+        teams::update_team_member,
+        teams::remove_team_member,
+        teams::get_team_collections,
+        teams::create_team_collection,
+        teams::get_team_collection,
+        teams::update_team_collection,
+        teams::remove_team_collection,
+        teams::add_team_collection_item,
+        teams::update_team_collection_item,
+        teams::remove_team_collection_item,
+        teams::reorder_team_collection,
+        health::livez,
+        health::readyz,
+    ),
+    components(schemas(
+        articles::Article,
+        articles::GetArticlesResponse,
+        ArticlesWithRateLimits,
+        articles::ArticleStatus,
+        articles::MediaPresence,
+        articles::ArticleAuthor,
+        articles::ArticleImage,
+        articles::ArticleVideo,
+        ReadingTime,
+        ReadingQueue,
+        EstimatedArticle,
+        EstimateSource,
+        ArticleNote,
+        ArticleHighlight,
+        NoteInput,
+        NewHighlight,
+        HighlightPatch,
+        SearchResult,
+        MatchSource,
+        annotations::GetNotesResponse,
+        annotations::GetHighlightsResponse,
+        annotations::SearchArticlesResponse,
+        Collection,
+        CollectionItem,
+        CollectionInput,
+        NewCollectionItem,
+        CollectionItemPatch,
+        CollectionOrder,
+        collections::GetCollectionsResponse,
+        collections::GetCollectionResponse,
+        ShareLink,
+        CreatedShareLink,
+        NewShareLink,
+        SharedArticle,
+        SharedPage,
+        shares::GetShareLinksResponse,
+        Team,
+        TeamRole,
+        TeamMember,
+        TeamInput,
+        MemberPatch,
+        NewTeamInvitation,
+        PendingTeamInvitation,
+        CreatedTeamInvitation,
+        AcceptTeamInvitation,
+        teams::GetTeamsResponse,
+        teams::GetTeamResponse,
+        teams::GetTeamInvitationsResponse,
+        auth::GetAccessTokenRequest,
+        auth::GetAccessTokenResponse,
+        auth::GetSessionResponse,
+        health::ComponentStatus,
+        health::ComponentReport,
+        health::HealthReport,
+        PocketQuota,
+        QuotaWindow,
+        RateLimits,
+        UserSettings,
+        UserSettingsPatch,
+        ReadingStats,
+        DayCount,
+        WeekCount,
+        BacklogSize,
+        NamedCount,
+        ProblemDetails,
+    )),
+    modifiers(&SessionSecurity)
+)]
+pub struct ApiDoc;
+
+static OPENAPI: Lazy<openapi::OpenApi> = Lazy::new(ApiDoc::openapi);
+
+/// Serves the OpenAPI 3 document describing every api route.
+pub async fn get_openapi() -> Json<&'static openapi::OpenApi> {
+    Json(&OPENAPI)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::Path};
+
+    use super::*;
+
+    /// Fails when the document changes without `openapi.json` being regenerated, so clients
+    /// generated from it don't drift. Regenerate with `UPDATE_OPENAPI=1 cargo test openapi`.
+    #[test]
+    fn openapi_document_is_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+        let generated = format!("{}\n", ApiDoc::openapi().to_pretty_json().unwrap());
+
+        if env::var_os("UPDATE_OPENAPI").is_some() {
+            fs::write(&path, generated).unwrap();
+            return;
+        }
+
+        let committed = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "openapi.json is out of date, regenerate it with `UPDATE_OPENAPI=1 cargo test openapi`"
+        );
+    }
+
+    /// Schemas are referenced by name only, so a type missing from `components` goes unnoticed
+    /// until a client fails to resolve it.
+    #[test]
+    fn every_schema_reference_resolves() {
+        let openapi = ApiDoc::openapi();
+        let schemas = &openapi.components.as_ref().unwrap().schemas;
+        let json = openapi.to_json().unwrap();
+
+        for reference in json.split("\"#/components/schemas/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(schemas.contains_key(name), "{name} isn't a component");
+        }
+    }
+}
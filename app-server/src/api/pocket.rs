@@ -0,0 +1,29 @@
+use axum::extract::State;
+
+use crate::{
+    quota::{PocketQuota, PocketQuotaStore},
+    session::AuthzedSessionData,
+    ApiResult, Cache, TypedResponse,
+};
+
+/// Reports how much of the Pocket quota is left for the current user and for the app as a whole.
+#[utoipa::path(
+    get,
+    path = "/v2/pocket/quota",
+    tag = "pocket",
+    responses(
+        (status = 200, description = "Quotas left", body = PocketQuota),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_pocket_quota(
+    State(session_store): State<Cache>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<PocketQuota> {
+    let quota = session_store
+        .fetch_pocket_quota(Some(&session_data.username))
+        .await?;
+
+    Ok(TypedResponse::new(Some(quota)))
+}
@@ -0,0 +1,352 @@
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{CACHE_CONTROL, LOCATION, REFERER, REFERRER_POLICY},
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures::{FutureExt, TryFutureExt};
+use pockety::{models::ItemId, Pockety};
+use tracing::{debug, info, warn, Instrument};
+use url::Url;
+
+use crate::{
+    credentials::CredentialsKeys,
+    csrf::is_allowed_origin,
+    db::{
+        claim_article_auto_archive, complete_article_auto_archive, fetch_user, fetch_user_settings,
+        record_article_events, record_article_open,
+    },
+    domain::{ArticleEvent, ArticleEventKind, OpenedArticle},
+    error::{ApiError, Error},
+    metrics::metrics,
+    quota::{PocketQuotaStore, QuotaPriority},
+    session::{invalidate_on_pocket_auth_failure, AuthzedSessionData},
+    telemetry::pocket_span,
+    Cache, Config, Store,
+};
+
+/// Opens an article of the user's library: records the open and redirects to the article. When the
+/// user turned on automatic read detection and followed the link from the app, the article is
+/// archived in Pocket in the background.
+///
+/// Only the urls Pocket stored for the article are redirected to, so the link can't be abused to
+/// send someone elsewhere. An article is archived by its first open only, an article the user
+/// unarchived afterwards stays so.
+#[utoipa::path(
+    get,
+    path = "/r/{item_id}",
+    tag = "articles",
+    params(("item_id" = String, Path, description = "Pocket id of the article")),
+    responses(
+        (status = 302, description = "Redirects to the article"),
+        (status = "4XX", description = "Not signed in, or the article isn't in the user's library", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn open_article(
+    State(pockety): State<Pockety>,
+    State(store): State<Store>,
+    State(session_store): State<Cache>,
+    State(config): State<Config>,
+    Path(item_id): Path<String>,
+    headers: HeaderMap,
+    session_data: AuthzedSessionData,
+) -> Result<Response, Error> {
+    let user_id = fetch_user(store.clone(), &session_data.username)
+        .await?
+        .map(|user| user.id)
+        .ok_or_else(|| Error::Api(ApiError::Unauthorized("User not found".to_string())))?;
+
+    let article = record_article_open(store.clone(), user_id, &item_id)
+        .await?
+        .ok_or_else(|| Error::Api(ApiError::NotFound("Article not found".to_string())))?;
+    record_event(&store, user_id, article.id, ArticleEventKind::Opened).await;
+    let location = redirect_target(&article)
+        .and_then(|url| HeaderValue::from_str(url.as_str()).ok())
+        .ok_or_else(|| {
+            Error::Api(ApiError::NotFound(
+                "Article has no url that can be opened".to_string(),
+            ))
+        })?;
+
+    // Opens from links on other sites could archive articles behind the user's back, only the
+    // ones from the app are trusted. The redirect doesn't wait on Pocket.
+    if !article.archived
+        && !article.auto_archived
+        && is_same_site_navigation(&headers, &config.user_agent_url)
+    {
+        tokio::spawn(archive_on_open(
+            pockety,
+            store,
+            session_store,
+            config.pocket_credentials_keys,
+            session_data,
+            user_id,
+            article,
+        ));
+    }
+
+    Ok((
+        StatusCode::FOUND,
+        [
+            (LOCATION, location),
+            // Every open has to reach the server to be recorded
+            (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+            (REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+        ],
+    )
+        .into_response())
+}
+
+/// The resolved url of the article, or the one the user saved when Pocket couldn't resolve it.
+/// Anything but absolute http(s) urls is refused.
+fn redirect_target(article: &OpenedArticle) -> Option<Url> {
+    [&article.resolved_url, &article.given_url]
+        .into_iter()
+        .flatten()
+        .filter_map(|url| Url::parse(url.trim()).ok())
+        .find(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// Whether the browser says the link was followed from our own site, or, for browsers that don't
+/// send `Sec-Fetch-Site`, from the app.
+fn is_same_site_navigation(headers: &HeaderMap, user_agent_url: &str) -> bool {
+    static SEC_FETCH_SITE: HeaderName = HeaderName::from_static("sec-fetch-site");
+
+    let same_site = headers
+        .get(&SEC_FETCH_SITE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|site| matches!(site, "same-origin" | "same-site"));
+    let from_app = headers
+        .get(REFERER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|referer| is_allowed_origin(referer, user_agent_url));
+
+    same_site || from_app
+}
+
+/// Archives the opened article when the user turned on automatic read detection.
+async fn archive_on_open(
+    pockety: Pockety,
+    store: Store,
+    session_store: Cache,
+    keys: CredentialsKeys,
+    session_data: AuthzedSessionData,
+    user_id: i32,
+    article: OpenedArticle,
+) {
+    const LOG_TAG: &str = "[open_article]";
+
+    match fetch_user_settings(store.clone(), user_id).await {
+        Ok(settings) if settings.enable_automatic_read => {
+            if auto_archive(
+                &pockety,
+                &store,
+                &session_store,
+                &keys,
+                &session_data,
+                &article,
+            )
+            .await
+            {
+                record_event(&store, user_id, article.id, ArticleEventKind::Archived).await;
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("{LOG_TAG} failed to fetch settings, not archiving. Error: {e:?}"),
+    }
+}
+
+/// Recorded for the reading stats, which aren't worth failing the redirect for.
+async fn record_event(store: &Store, user_id: i32, article_id: i32, kind: ArticleEventKind) {
+    let event = ArticleEvent {
+        kind,
+        occurred_at: Utc::now(),
+    };
+    if let Err(e) = record_article_events(store.clone(), user_id, article_id, &[event]).await {
+        warn!(
+            "[open_article] failed to record {} event. Error: {e:?}",
+            kind.as_str()
+        );
+    }
+}
+
+/// Archives the article in Pocket unless another open already does, returning whether it did.
+/// Failures are only logged, the user is redirected anyway and the next open tries again.
+async fn auto_archive(
+    pockety: &Pockety,
+    store: &Store,
+    session_store: &Cache,
+    keys: &CredentialsKeys,
+    session_data: &AuthzedSessionData,
+    article: &OpenedArticle,
+) -> bool {
+    const LOG_TAG: &str = "[auto_archive]";
+
+    match claim_article_auto_archive(store.clone(), article.id).await {
+        Ok(true) => {}
+        Ok(false) => return false,
+        Err(e) => {
+            warn!("{LOG_TAG} failed to claim archiving, not archiving. Error: {e:?}");
+            return false;
+        }
+    }
+
+    let archived =
+        archive_in_pocket(pockety, store, session_store, keys, session_data, article).await;
+    match &archived {
+        Ok(()) => info!(
+            "{LOG_TAG} archived article {item_id} of user {username} on open",
+            item_id = article.item_id,
+            username = session_data.username
+        ),
+        Err(e) => warn!(
+            "{LOG_TAG} failed to archive article {item_id}. Error: {e:?}",
+            item_id = article.item_id
+        ),
+    }
+
+    if let Err(e) = complete_article_auto_archive(store.clone(), article.id, archived.is_ok()).await
+    {
+        warn!("{LOG_TAG} failed to record archiving. Error: {e:?}");
+    }
+    archived.is_ok()
+}
+
+async fn archive_in_pocket(
+    pockety: &Pockety,
+    store: &Store,
+    session_store: &Cache,
+    keys: &CredentialsKeys,
+    session_data: &AuthzedSessionData,
+    article: &OpenedArticle,
+) -> Result<(), Error> {
+    const LOG_TAG: &str = "[archive_in_pocket]";
+
+    session_store
+        .check_pocket_quota(Some(&session_data.username), QuotaPriority::Interactive)
+        .await?;
+
+    let res = match pockety
+        .modify()
+        .access_token(session_data.access_token.clone())
+        .archive(ItemId(article.item_id.clone()))
+        .execute()
+        .instrument(pocket_span("send"))
+        .inspect(|result| metrics().record_pocket_call("send", result))
+        .inspect_err(|e| debug!("{LOG_TAG} failed to archive article with error: {e:?}"))
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            let e = session_store
+                .record_failed_pocket_call(Some(&session_data.username), e.into())
+                .await;
+            return Err(invalidate_on_pocket_auth_failure(
+                session_store,
+                store,
+                keys,
+                session_data,
+                e,
+            )
+            .await);
+        }
+    };
+
+    let _ = session_store
+        .record_pocket_call(Some(&session_data.username), Some(res.rate_limits.into()))
+        .await;
+
+    if res.data.action_results.iter().all(|succeeded| *succeeded) {
+        Ok(())
+    } else {
+        Err(Error::from_pocket_message(
+            "Pocket refused to archive the article".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn article(resolved_url: Option<&str>, given_url: Option<&str>) -> OpenedArticle {
+        OpenedArticle {
+            id: 1,
+            item_id: "229279689".to_string(),
+            given_url: given_url.map(str::to_string),
+            resolved_url: resolved_url.map(str::to_string),
+            archived: false,
+            auto_archived: false,
+        }
+    }
+
+    #[test]
+    fn prefers_the_resolved_url() {
+        let target = redirect_target(&article(
+            Some("https://www.example.com/article"),
+            Some("http://example.com/a"),
+        ));
+
+        assert_eq!(target.unwrap().as_str(), "https://www.example.com/article");
+    }
+
+    #[test]
+    fn archives_only_on_same_site_navigations() {
+        let app = "https://just-links.dev";
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_str(value).unwrap());
+            }
+            headers
+        };
+
+        assert!(is_same_site_navigation(
+            &headers(&[("sec-fetch-site", "same-site")]),
+            app
+        ));
+        assert!(is_same_site_navigation(
+            &headers(&[
+                ("sec-fetch-site", "cross-site"),
+                ("referer", "https://just-links.dev/")
+            ]),
+            app
+        ));
+        assert!(is_same_site_navigation(
+            &headers(&[("referer", "https://just-links.dev/articles")]),
+            app
+        ));
+        assert!(!is_same_site_navigation(
+            &headers(&[
+                ("sec-fetch-site", "cross-site"),
+                ("referer", "https://evil.example/")
+            ]),
+            app
+        ));
+        assert!(!is_same_site_navigation(
+            &headers(&[("sec-fetch-site", "none")]),
+            app
+        ));
+        assert!(!is_same_site_navigation(&HeaderMap::new(), app));
+    }
+
+    #[test]
+    fn refuses_urls_that_arent_http() {
+        for url in [
+            "javascript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "//evil.example.com",
+            "/relative",
+            "file:///etc/passwd",
+        ] {
+            assert_eq!(redirect_target(&article(Some(url), None)), None, "{url}");
+        }
+
+        let target = redirect_target(&article(Some("javascript:alert(1)"), Some("http://a.b/c")));
+        assert_eq!(target.unwrap().as_str(), "http://a.b/c");
+    }
+}
@@ -0,0 +1,281 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{
+        header::{
+            ACCEPT, CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY, VARY,
+        },
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::me::user_id,
+    collections::CollectionOwner,
+    db::{
+        fetch_active_share, fetch_articles, fetch_collectable_article_id, fetch_collection,
+        fetch_collection_items, fetch_share_links, fetch_shared_article, insert_share_link,
+        revoke_share_link,
+    },
+    error::{ApiError, Error},
+    session::{generate_token, hash, AuthzedSessionData},
+    shares::{
+        shared_page_html, tagged_articles, ActiveShare, CreatedShareLink, NewShareLink, ShareLink,
+        SharedArticle, SharedPage, MAX_SHARED_ARTICLES, SHARE_PATH_PREFIX,
+    },
+    ApiResult, Store, TypedResponse,
+};
+
+/// Shared pages only load the images of the articles and their own styles.
+const SHARED_PAGE_CSP: &str =
+    "default-src 'none'; img-src http: https:; style-src 'unsafe-inline'; base-uri 'none'; form-action 'none'; frame-ancestors 'none'";
+
+#[derive(Serialize, ToSchema)]
+pub struct GetShareLinksResponse {
+    shares: Vec<ShareLink>,
+}
+
+fn share_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Share link not found".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/shares",
+    tag = "shares",
+    responses(
+        (status = 200, description = "Share links of the user, latest first, including the revoked and expired ones", body = GetShareLinksResponse),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_share_links(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetShareLinksResponse> {
+    let user_id = user_id(&store, &session_data).await?;
+    let shares = fetch_share_links(store, user_id).await?;
+
+    Ok(TypedResponse::new(Some(GetShareLinksResponse { shares })))
+}
+
+/// Creates a link to a read-only page of a collection, an article or the articles with a tag.
+#[utoipa::path(
+    post,
+    path = "/v2/shares",
+    tag = "shares",
+    request_body = NewShareLink,
+    responses(
+        (status = 201, description = "The new link, along with its token", body = CreatedShareLink),
+        (status = "4XX", description = "Invalid link, not signed in, or unknown collection or article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_share_link(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CreatedShareLink> {
+    let input = NewShareLink::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+
+    let collection_id = match input.collection_id {
+        Some(collection_id) => Some(
+            fetch_collection(store.clone(), CollectionOwner::User(user_id), collection_id)
+                .await?
+                .ok_or_else(|| Error::Api(ApiError::NotFound("Collection not found".to_string())))?
+                .id,
+        ),
+        None => None,
+    };
+    let pocket_article_id = match &input.item_id {
+        Some(item_id) => Some(
+            fetch_collectable_article_id(store.clone(), user_id, item_id)
+                .await?
+                .ok_or_else(|| Error::Api(ApiError::NotFound("Article not found".to_string())))?,
+        ),
+        None => None,
+    };
+    let share = ActiveShare {
+        user_id,
+        collection_id,
+        pocket_article_id,
+        tag: input.tag,
+    };
+    let expires_at = input
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(days as i64));
+
+    let (token, token_hash) = generate_token();
+    let share = insert_share_link(store, user_id, &token_hash, &share, expires_at).await?;
+
+    Ok(TypedResponse::new(Some(CreatedShareLink {
+        share,
+        path: format!("{SHARE_PATH_PREFIX}{token}"),
+        token,
+    }))
+    .status_code(StatusCode::CREATED))
+}
+
+/// Revokes the link, its page can't be opened anymore.
+#[utoipa::path(
+    delete,
+    path = "/v2/shares/{share_id}",
+    tag = "shares",
+    params(("share_id" = i32, Path, description = "Id of the share link")),
+    responses(
+        (status = 204, description = "Revoked"),
+        (status = "4XX", description = "Not signed in or unknown link", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn revoke_share(
+    State(store): State<Store>,
+    Path(share_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let user_id = user_id(&store, &session_data).await?;
+    if !revoke_share_link(store, user_id, share_id).await? {
+        return Err(share_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The page of a share link, for anyone who has it. Served as HTML, or as JSON to clients that
+/// accept `application/json`. Revoked, expired and unknown links are all not found.
+#[utoipa::path(
+    get,
+    path = "/s/{token}",
+    tag = "shares",
+    params(("token" = String, Path, description = "Token of the share link")),
+    responses(
+        (status = 200, description = "The shared articles", body = SharedPage, content_type = ["text/html", "application/json"]),
+        (status = "4XX", description = "No link can be opened with the token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn view_share(
+    State(store): State<Store>,
+    Path(token): Path<String>,
+    request_headers: HeaderMap,
+) -> Result<Response, Error> {
+    let share = fetch_active_share(store.clone(), &hash(&token))
+        .await?
+        .ok_or_else(share_not_found)?;
+    let page = shared_page(&store, share).await?;
+
+    let headers = [
+        // Revoking a link has to take effect right away
+        (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        // Keeps the token out of the Referer of the shared articles
+        (REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+        (
+            HeaderName::from_static("x-robots-tag"),
+            HeaderValue::from_static("noindex"),
+        ),
+        (VARY, HeaderValue::from_static("accept")),
+    ];
+
+    if accepts_json(&request_headers) {
+        return Ok((headers, Json(page)).into_response());
+    }
+    Ok((
+        headers,
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/html; charset=utf-8"),
+            ),
+            (
+                CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static(SHARED_PAGE_CSP),
+            ),
+        ],
+        shared_page_html(&page),
+    )
+        .into_response())
+}
+
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            media_type
+                .split(';')
+                .next()
+                .is_some_and(|media_type| media_type.trim() == "application/json")
+        })
+}
+
+/// Reads what the link shares as the user who created it.
+async fn shared_page(store: &Store, share: ActiveShare) -> Result<SharedPage, Error> {
+    if let Some(collection_id) = share.collection_id {
+        let collection = fetch_collection(
+            store.clone(),
+            CollectionOwner::User(share.user_id),
+            collection_id,
+        )
+        .await?
+        .ok_or_else(share_not_found)?;
+        let items = fetch_collection_items(store.clone(), collection.id).await?;
+        return Ok(SharedPage {
+            title: collection.title,
+            description: collection.description,
+            articles: items
+                .into_iter()
+                .take(MAX_SHARED_ARTICLES)
+                .map(SharedArticle::from)
+                .collect(),
+        });
+    }
+
+    if let Some(article_id) = share.pocket_article_id {
+        let article = fetch_shared_article(store.clone(), article_id)
+            .await?
+            .ok_or_else(share_not_found)?;
+        return Ok(SharedPage {
+            title: article
+                .title
+                .clone()
+                .or_else(|| article.url.clone())
+                .unwrap_or_else(|| "Shared article".to_string()),
+            description: None,
+            articles: vec![article],
+        });
+    }
+
+    let tag = share.tag.ok_or_else(share_not_found)?;
+    let articles = fetch_articles(store.clone(), share.user_id).await?;
+    Ok(SharedPage {
+        articles: tagged_articles(&articles, &tag),
+        title: format!("Articles tagged {tag}"),
+        description: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negotiates_json() {
+        let accepts = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT, HeaderValue::from_str(accept).unwrap());
+            accepts_json(&headers)
+        };
+
+        assert!(accepts("application/json"));
+        assert!(accepts("text/html;q=0.9, application/json; q=1"));
+        assert!(!accepts("text/html,application/xhtml+xml,*/*;q=0.8"));
+        assert!(!accepts_json(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,769 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::{
+        collections::{GetCollectionResponse, GetCollectionsResponse},
+        me::user_id,
+    },
+    collections::{
+        Collection, CollectionInput, CollectionItem, CollectionItemPatch, CollectionOrder,
+        CollectionOwner, NewCollectionItem,
+    },
+    db::{
+        accept_team_invitation, delete_collection, delete_collection_item, delete_team,
+        delete_team_member, fetch_collectable_article_id, fetch_collection, fetch_collection_items,
+        fetch_collections, fetch_team, fetch_team_invitations, fetch_team_members, fetch_team_role,
+        fetch_teams, insert_collection, insert_collection_item, insert_team,
+        insert_team_invitation, reorder_collection_items, revoke_team_invitation,
+        update_collection, update_collection_item, update_team_member_role, update_team_name,
+    },
+    error::{ApiError, Error},
+    session::{generate_token, hash, AuthzedSessionData},
+    teams::{
+        AcceptTeamInvitation, CreatedTeamInvitation, MemberPatch, NewTeamInvitation,
+        PendingTeamInvitation, Team, TeamInput, TeamInvitation, TeamMember, TeamRole,
+    },
+    ApiResult, Config, Store, TypedResponse,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct GetTeamsResponse {
+    teams: Vec<Team>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GetTeamResponse {
+    team: Team,
+    members: Vec<TeamMember>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GetTeamInvitationsResponse {
+    invitations: Vec<PendingTeamInvitation>,
+}
+
+/// The signed in user as a member of a team.
+struct Membership {
+    user_id: i32,
+    role: TeamRole,
+}
+
+/// Checks the signed in user is a member of the team whose role allows `required`. Every team
+/// scoped endpoint goes through it. Teams the user isn't a member of are not found, so their ids
+/// can't be probed.
+async fn team_member(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+    team_id: i32,
+    required: TeamRole,
+) -> Result<Membership, Error> {
+    let user_id = user_id(store, session_data).await?;
+    let role = fetch_team_role(store.clone(), team_id, user_id)
+        .await?
+        .ok_or_else(team_not_found)?;
+    if !role.allows(required) {
+        return Err(Error::Api(ApiError::Forbidden(format!(
+            "Requires the {} role in the team",
+            required.as_str()
+        ))));
+    }
+
+    Ok(Membership { user_id, role })
+}
+
+/// A collection of the team, after checking the signed in user's role allows `required`.
+async fn team_collection(
+    store: &Store,
+    session_data: &AuthzedSessionData,
+    team_id: i32,
+    collection_id: i32,
+    required: TeamRole,
+) -> Result<(Membership, Collection), Error> {
+    let member = team_member(store, session_data, team_id, required).await?;
+    let collection = fetch_collection(store.clone(), CollectionOwner::Team(team_id), collection_id)
+        .await?
+        .ok_or_else(collection_not_found)?;
+
+    Ok((member, collection))
+}
+
+fn team_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Team not found".to_string()))
+}
+
+fn member_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Member not found".to_string()))
+}
+
+fn collection_not_found() -> Error {
+    Error::Api(ApiError::NotFound("Collection not found".to_string()))
+}
+
+fn item_not_found() -> Error {
+    Error::Api(ApiError::NotFound(
+        "Article not found in the collection".to_string(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/teams",
+    tag = "teams",
+    responses(
+        (status = 200, description = "Teams the user is a member of", body = GetTeamsResponse),
+        (status = "4XX", description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_teams(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetTeamsResponse> {
+    let user_id = user_id(&store, &session_data).await?;
+    let teams = fetch_teams(store, user_id).await?;
+
+    Ok(TypedResponse::new(Some(GetTeamsResponse { teams })))
+}
+
+/// Creates a team, owned by the signed in user.
+#[utoipa::path(
+    post,
+    path = "/v2/teams",
+    tag = "teams",
+    request_body = TeamInput,
+    responses(
+        (status = 201, description = "The new team", body = Team),
+        (status = "4XX", description = "Invalid team or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_team(
+    State(store): State<Store>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Team> {
+    let input = TeamInput::from_json(&body)?;
+    let user_id = user_id(&store, &session_data).await?;
+    let team = insert_team(store, user_id, &input.name).await?;
+
+    Ok(TypedResponse::new(Some(team)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/teams/{team_id}",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    responses(
+        (status = 200, description = "The team and its members", body = GetTeamResponse),
+        (status = "4XX", description = "Not signed in or not a member of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_team(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetTeamResponse> {
+    let member = team_member(&store, &session_data, team_id, TeamRole::Viewer).await?;
+    let team = fetch_team(store.clone(), team_id, member.user_id)
+        .await?
+        .ok_or_else(team_not_found)?;
+    let members = fetch_team_members(store, team_id).await?;
+
+    Ok(TypedResponse::new(Some(GetTeamResponse { team, members })))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/teams/{team_id}",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    request_body = TeamInput,
+    responses(
+        (status = 200, description = "The renamed team", body = Team),
+        (status = "4XX", description = "Invalid team, not signed in or not an owner of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_team(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Team> {
+    let input = TeamInput::from_json(&body)?;
+    let member = team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+    update_team_name(store.clone(), team_id, &input.name).await?;
+    let team = fetch_team(store, team_id, member.user_id)
+        .await?
+        .ok_or_else(team_not_found)?;
+
+    Ok(TypedResponse::new(Some(team)))
+}
+
+/// Deletes the team and its collections, the articles stay in the libraries of the members.
+#[utoipa::path(
+    delete,
+    path = "/v2/teams/{team_id}",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = "4XX", description = "Not signed in or not an owner of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_team(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+    delete_team(store, team_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/teams/{team_id}/invitations",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    responses(
+        (status = 200, description = "Invitations of the team that can still be accepted, latest first", body = GetTeamInvitationsResponse),
+        (status = "4XX", description = "Not signed in or not an owner of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_team_invitations(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetTeamInvitationsResponse> {
+    team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+    let invitations = fetch_team_invitations(store, team_id).await?;
+
+    Ok(TypedResponse::new(Some(GetTeamInvitationsResponse {
+        invitations,
+    })))
+}
+
+/// Creates an invitation to join the team, which a single signed in user can accept with its token
+/// until it expires or is revoked, or its sender stops being an owner of the team.
+#[utoipa::path(
+    post,
+    path = "/v2/teams/{team_id}/invitations",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    request_body = NewTeamInvitation,
+    responses(
+        (status = 201, description = "The invitation, along with its token", body = CreatedTeamInvitation),
+        (status = "4XX", description = "Invalid invitation, not signed in or not an owner of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_team_invitation(
+    State(store): State<Store>,
+    State(config): State<Config>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CreatedTeamInvitation> {
+    let input = NewTeamInvitation::from_json(&body)?;
+    let member = team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+
+    let expires_at = input.expires_at(Utc::now());
+    let (token_id, id_hash) = generate_token();
+    let invitation = insert_team_invitation(
+        store,
+        team_id,
+        member.user_id,
+        &id_hash,
+        input.role,
+        expires_at,
+    )
+    .await?;
+    let token = TeamInvitation {
+        token_id,
+        team_id,
+        role: input.role,
+        invited_by: member.user_id,
+        expires_at: expires_at.timestamp(),
+    }
+    .into_token(config.jws_signing_secret, &config.jwe_encryption_key)?;
+
+    Ok(
+        TypedResponse::new(Some(CreatedTeamInvitation { invitation, token }))
+            .status_code(StatusCode::CREATED),
+    )
+}
+
+/// Revokes the invitation, its token can't be used to join the team anymore.
+#[utoipa::path(
+    delete,
+    path = "/v2/teams/{team_id}/invitations/{invitation_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("invitation_id" = i32, Path, description = "Id of the invitation"),
+    ),
+    responses(
+        (status = 204, description = "Revoked"),
+        (status = "4XX", description = "Not signed in, not an owner of the team, or no such invitation waiting to be accepted", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_team_invitation(
+    State(store): State<Store>,
+    Path((team_id, invitation_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+    if !revoke_team_invitation(store, team_id, invitation_id).await? {
+        return Err(Error::Api(ApiError::NotFound(
+            "Invitation not found".to_string(),
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Joins the team of the invitation and uses it up, unless already a member of the team.
+#[utoipa::path(
+    post,
+    path = "/v2/teams/join",
+    tag = "teams",
+    request_body = AcceptTeamInvitation,
+    responses(
+        (status = 200, description = "The joined team", body = Team),
+        (status = "4XX", description = "Invalid, used, revoked or expired invitation, or not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn join_team(
+    State(store): State<Store>,
+    State(config): State<Config>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Team> {
+    const LOG_TAG: &str = "[join_team]";
+
+    let input = AcceptTeamInvitation::from_json(&body)?;
+    let invitation = TeamInvitation::from_token(
+        input.token.trim(),
+        config.jws_signing_secret,
+        &config.jwe_encryption_key,
+        Utc::now(),
+    )?;
+
+    let user_id = user_id(&store, &session_data).await?;
+    let invitation = accept_team_invitation(
+        store.clone(),
+        invitation.team_id,
+        &hash(&invitation.token_id),
+        user_id,
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::Api(ApiError::BadRequest(
+            "Invitation is no longer valid".to_string(),
+        ))
+    })?;
+
+    if invitation.joined {
+        tracing::info!(
+            "{LOG_TAG} {username} joined team {team_id} as {role}",
+            username = session_data.username,
+            team_id = invitation.team_id,
+            role = invitation.role.as_str()
+        );
+    }
+    let team = fetch_team(store, invitation.team_id, user_id)
+        .await?
+        .ok_or_else(team_not_found)?;
+
+    Ok(TypedResponse::new(Some(team)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/teams/{team_id}/members/{username}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("username" = String, Path, description = "Pocket username of the member"),
+    ),
+    request_body = MemberPatch,
+    responses(
+        (status = 200, description = "The member with their new role", body = TeamMember),
+        (status = "4XX", description = "Invalid role, last owner of the team, not signed in, not an owner of the team or unknown member", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_team_member(
+    State(store): State<Store>,
+    Path((team_id, username)): Path<(i32, String)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<TeamMember> {
+    let patch = MemberPatch::from_json(&body)?;
+    team_member(&store, &session_data, team_id, TeamRole::Owner).await?;
+    let member = update_team_member_role(store, team_id, &username, patch.role)
+        .await?
+        .ok_or_else(member_not_found)?;
+
+    Ok(TypedResponse::new(Some(member)))
+}
+
+/// Removes a member from the team, along with the articles of their library in the team
+/// collections. Owners remove anyone, the other members only themselves, to leave the team.
+#[utoipa::path(
+    delete,
+    path = "/v2/teams/{team_id}/members/{username}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("username" = String, Path, description = "Pocket username of the member"),
+    ),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = "4XX", description = "Last owner of the team, not signed in, not allowed to remove the member or unknown member", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_team_member(
+    State(store): State<Store>,
+    Path((team_id, username)): Path<(i32, String)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let member = team_member(&store, &session_data, team_id, TeamRole::Viewer).await?;
+    if member.role != TeamRole::Owner && username != session_data.username {
+        return Err(Error::Api(ApiError::Forbidden(
+            "Only owners remove other members".to_string(),
+        )));
+    }
+    if !delete_team_member(store, team_id, &username).await? {
+        return Err(member_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/teams/{team_id}/collections",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    responses(
+        (status = 200, description = "Collections of the team, last updated first", body = GetCollectionsResponse),
+        (status = "4XX", description = "Not signed in or not a member of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_team_collections(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetCollectionsResponse> {
+    team_member(&store, &session_data, team_id, TeamRole::Viewer).await?;
+    let collections = fetch_collections(store, CollectionOwner::Team(team_id)).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionsResponse {
+        collections,
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/teams/{team_id}/collections",
+    tag = "teams",
+    params(("team_id" = i32, Path, description = "Id of the team")),
+    request_body = CollectionInput,
+    responses(
+        (status = 201, description = "The new, empty collection", body = Collection),
+        (status = "4XX", description = "Invalid collection, not signed in or not an editor of the team", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn create_team_collection(
+    State(store): State<Store>,
+    Path(team_id): Path<i32>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Collection> {
+    let input = CollectionInput::from_json(&body)?;
+    let member = team_member(&store, &session_data, team_id, TeamRole::Editor).await?;
+    let collection = insert_collection(
+        store,
+        CollectionOwner::Team(team_id),
+        member.user_id,
+        &input,
+    )
+    .await?;
+
+    Ok(TypedResponse::new(Some(collection)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/teams/{team_id}/collections/{collection_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+    ),
+    responses(
+        (status = 200, description = "The collection and its articles, in order", body = GetCollectionResponse),
+        (status = "4XX", description = "Not signed in, not a member of the team or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = []))
+)]
+pub async fn get_team_collection(
+    State(store): State<Store>,
+    Path((team_id, collection_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+) -> ApiResult<GetCollectionResponse> {
+    let (_, collection) = team_collection(
+        &store,
+        &session_data,
+        team_id,
+        collection_id,
+        TeamRole::Viewer,
+    )
+    .await?;
+    let items = fetch_collection_items(store, collection.id).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionResponse {
+        collection,
+        items,
+    })))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/teams/{team_id}/collections/{collection_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+    ),
+    request_body = CollectionInput,
+    responses(
+        (status = 200, description = "The changed collection", body = Collection),
+        (status = "4XX", description = "Invalid collection, not signed in, not an editor of the team or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_team_collection(
+    State(store): State<Store>,
+    Path((team_id, collection_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<Collection> {
+    let input = CollectionInput::from_json(&body)?;
+    team_member(&store, &session_data, team_id, TeamRole::Editor).await?;
+    let collection =
+        update_collection(store, CollectionOwner::Team(team_id), collection_id, &input)
+            .await?
+            .ok_or_else(collection_not_found)?;
+
+    Ok(TypedResponse::new(Some(collection)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/teams/{team_id}/collections/{collection_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+    ),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = "4XX", description = "Not signed in, not an editor of the team or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_team_collection(
+    State(store): State<Store>,
+    Path((team_id, collection_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    team_member(&store, &session_data, team_id, TeamRole::Editor).await?;
+    if !delete_collection(store, CollectionOwner::Team(team_id), collection_id).await? {
+        return Err(collection_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Adds an article of the signed in member's own library at the end of the team collection.
+#[utoipa::path(
+    post,
+    path = "/v2/teams/{team_id}/collections/{collection_id}/items",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+    ),
+    request_body = NewCollectionItem,
+    responses(
+        (status = 201, description = "The added article", body = CollectionItem),
+        (status = "4XX", description = "Invalid item, article already in the collection, not signed in, not an editor of the team, or unknown collection or article", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn add_team_collection_item(
+    State(store): State<Store>,
+    Path((team_id, collection_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CollectionItem> {
+    let input = NewCollectionItem::from_json(&body)?;
+    let (member, collection) = team_collection(
+        &store,
+        &session_data,
+        team_id,
+        collection_id,
+        TeamRole::Editor,
+    )
+    .await?;
+    let article_id = fetch_collectable_article_id(store.clone(), member.user_id, &input.item_id)
+        .await?
+        .ok_or_else(|| Error::Api(ApiError::NotFound("Article not found".to_string())))?;
+
+    let item = insert_collection_item(
+        store,
+        collection.id,
+        article_id,
+        input.commentary.as_deref(),
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::Api(ApiError::BadRequest(
+            "Article is already in the collection".to_string(),
+        ))
+    })?;
+
+    Ok(TypedResponse::new(Some(item)).status_code(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/teams/{team_id}/collections/{collection_id}/items/{item_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+    ),
+    request_body = CollectionItemPatch,
+    responses(
+        (status = 200, description = "The changed article", body = CollectionItem),
+        (status = "4XX", description = "Invalid commentary, not signed in, not an editor of the team or article not in the collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn update_team_collection_item(
+    State(store): State<Store>,
+    Path((team_id, collection_id, item_id)): Path<(i32, i32, String)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<CollectionItem> {
+    let patch = CollectionItemPatch::from_json(&body)?;
+    let (_, collection) = team_collection(
+        &store,
+        &session_data,
+        team_id,
+        collection_id,
+        TeamRole::Editor,
+    )
+    .await?;
+    let item = update_collection_item(store, collection.id, &item_id, patch.commentary.as_deref())
+        .await?
+        .ok_or_else(item_not_found)?;
+
+    Ok(TypedResponse::new(Some(item)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/teams/{team_id}/collections/{collection_id}/items/{item_id}",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+        ("item_id" = String, Path, description = "Pocket id of the article"),
+    ),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = "4XX", description = "Not signed in, not an editor of the team or article not in the collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn remove_team_collection_item(
+    State(store): State<Store>,
+    Path((team_id, collection_id, item_id)): Path<(i32, i32, String)>,
+    session_data: AuthzedSessionData,
+) -> Result<StatusCode, Error> {
+    let (_, collection) = team_collection(
+        &store,
+        &session_data,
+        team_id,
+        collection_id,
+        TeamRole::Editor,
+    )
+    .await?;
+    if !delete_collection_item(store, collection.id, &item_id).await? {
+        return Err(item_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/teams/{team_id}/collections/{collection_id}/order",
+    tag = "teams",
+    params(
+        ("team_id" = i32, Path, description = "Id of the team"),
+        ("collection_id" = i32, Path, description = "Id of the collection"),
+    ),
+    request_body = CollectionOrder,
+    responses(
+        (status = 200, description = "The articles of the collection, in their new order", body = GetCollectionResponse),
+        (status = "4XX", description = "Invalid order, not signed in, not an editor of the team or unknown collection", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("session" = [], "csrf" = []))
+)]
+pub async fn reorder_team_collection(
+    State(store): State<Store>,
+    Path((team_id, collection_id)): Path<(i32, i32)>,
+    session_data: AuthzedSessionData,
+    body: Bytes,
+) -> ApiResult<GetCollectionResponse> {
+    let order = CollectionOrder::from_json(&body)?;
+    let (_, collection) = team_collection(
+        &store,
+        &session_data,
+        team_id,
+        collection_id,
+        TeamRole::Editor,
+    )
+    .await?;
+    reorder_collection_items(store.clone(), collection.id, &order).await?;
+    let items = fetch_collection_items(store, collection.id).await?;
+
+    Ok(TypedResponse::new(Some(GetCollectionResponse {
+        collection,
+        items,
+    })))
+}
@@ -0,0 +1,483 @@
+use std::{
+    error::Error as StdError,
+    fmt,
+    fs::File,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process,
+    sync::Arc,
+};
+
+use app_server::{
+    credentials::{load_pocket_credentials, revoke_on_pocket_auth_failure, CredentialsKeys},
+    db::{
+        delete_user, fetch_articles, fetch_pocket_credentials_to_rotate, fetch_user, fetch_users,
+        record_sync_run, replace_rotated_pocket_credentials, MIGRATOR,
+    },
+    domain::{SyncRun, SyncRunStatus, User},
+    logging::init_logging,
+    metrics::metrics,
+    quota::{PocketQuotaStore, QuotaPriority},
+    session::{delete_user_sessions, purge_expired_sessions},
+    settings::{Settings, StoreSettings},
+    sync::store_article,
+    telemetry::pocket_span,
+    Cache, Store,
+};
+use bb8::Pool;
+use bb8_redis::RedisConnectionManager;
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+use dotenvy::dotenv;
+use pockety::Pockety;
+use sqlx::{
+    migrate::{Migrate, MigrationType},
+    postgres::PgPoolOptions,
+};
+use tracing::Instrument;
+
+type CliResult = Result<(), Box<dyn StdError>>;
+
+/// Recorded as the trigger of syncs run from the cli
+const SYNC_TRIGGER: &str = "cli";
+
+/// Administrative tasks for the just-links app server. Reads the same configuration as the
+/// server itself, commands that only maintain the databases just need their urls.
+#[derive(Parser)]
+#[command(name = "app-server-admin")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply, revert or inspect database migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// List or delete users
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Sync a user's Pocket library with their stored Pocket credentials
+    Sync { username: String },
+    /// Export a user's library as JSON
+    Export {
+        username: String,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Maintain the session store
+    #[command(subcommand)]
+    Sessions(SessionsCommand),
+    /// Maintain the stored Pocket credentials
+    #[command(subcommand)]
+    Credentials(CredentialsCommand),
+    /// Print the effective configuration, with secrets redacted
+    Config,
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Apply every pending migration
+    Run,
+    /// Revert applied migrations, by default only the latest one
+    Revert {
+        /// Revert every migration newer than this version
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    /// List migrations and whether they have been applied
+    Status,
+}
+
+#[derive(Subcommand)]
+enum UsersCommand {
+    List,
+    /// Delete a user along with their articles and sessions
+    Delete {
+        username: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// Delete expired sessions and set an expiry on sessions stored without one
+    Purge {
+        /// Also walk the whole store for sessions created before they were indexed per user
+        #[arg(long)]
+        unindexed: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CredentialsCommand {
+    /// Re-encrypt the credentials still encrypted with an older key with the first key of
+    /// `pocket_credentials_keys`
+    Rotate,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+
+    let cli = Cli::parse();
+
+    let stores = StoreSettings::load().unwrap_or_else(|e| exit_with(e));
+
+    // Logs go to stderr, so they don't end up in exports written to stdout
+    init_logging(&stores.log_filter, stores.log_format, io::stderr, None);
+
+    let result = match cli.command {
+        Command::Migrate(MigrateCommand::Run) => migrate_run(&stores).await,
+        Command::Migrate(MigrateCommand::Revert { target }) => {
+            migrate_revert(&stores, target).await
+        }
+        Command::Migrate(MigrateCommand::Status) => migrate_status(&stores).await,
+        Command::Users(UsersCommand::List) => list_users(&stores).await,
+        Command::Users(UsersCommand::Delete { username, yes }) => {
+            remove_user(&stores, &username, yes).await
+        }
+        Command::Sync { username } => sync(&settings(), &username).await,
+        Command::Export { username, output } => export(&settings(), &username, output).await,
+        Command::Sessions(SessionsCommand::Purge { unindexed }) => {
+            purge_sessions(&stores, unindexed).await
+        }
+        Command::Credentials(CredentialsCommand::Rotate) => rotate_credentials(&settings()).await,
+        Command::Config => {
+            println!("{:#?}", settings());
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+/// Every setting of the server, for the commands that need more than the databases.
+fn settings() -> Settings {
+    Settings::load().unwrap_or_else(|e| exit_with(e))
+}
+
+fn exit_with(error: impl fmt::Display) -> ! {
+    eprintln!("{error}");
+    process::exit(1);
+}
+
+fn postgres(settings: &StoreSettings) -> Result<Store, Box<dyn StdError>> {
+    let pool = PgPoolOptions::new()
+        .max_connections(settings.database_max_connections)
+        .connect_lazy(&settings.database_url)?;
+    Ok(Arc::new(pool))
+}
+
+async fn redis(settings: &StoreSettings) -> Result<Cache, Box<dyn StdError>> {
+    let manager = RedisConnectionManager::new(settings.redis_url.as_str())?;
+    let pool = Pool::builder()
+        .max_size(settings.redis_max_connections)
+        .build(manager)
+        .await?;
+    Ok(Arc::new(pool))
+}
+
+async fn applied_versions(store: &Store) -> Result<Vec<i64>, Box<dyn StdError>> {
+    let mut con = store.acquire().await?;
+    con.ensure_migrations_table().await?;
+    let mut versions = con
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect::<Vec<_>>();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+async fn migrate_run(settings: &StoreSettings) -> CliResult {
+    let store = postgres(settings)?;
+    MIGRATOR.run(&*store).await?;
+    println!("Database is up to date");
+    Ok(())
+}
+
+async fn migrate_revert(settings: &StoreSettings, target: Option<i64>) -> CliResult {
+    let store = postgres(settings)?;
+    let applied = applied_versions(&store).await?;
+
+    let target = match target {
+        Some(target) => target,
+        None => match applied.as_slice() {
+            [] => {
+                println!("No migrations to revert");
+                return Ok(());
+            }
+            [.., previous, _] => *previous,
+            [_] => 0,
+        },
+    };
+
+    // Migrations without a down script can't be undone, refuse instead of skipping them silently
+    let irreversible = applied
+        .iter()
+        .filter(|version| **version > target)
+        .filter(|version| {
+            !MIGRATOR.iter().any(|migration| {
+                migration.version == **version
+                    && migration.migration_type == MigrationType::ReversibleDown
+            })
+        })
+        .collect::<Vec<_>>();
+    if !irreversible.is_empty() {
+        return Err(format!("Migrations {irreversible:?} can't be reverted").into());
+    }
+
+    MIGRATOR.undo(&*store, target).await?;
+    println!("Reverted migrations newer than {target}");
+    Ok(())
+}
+
+async fn migrate_status(settings: &StoreSettings) -> CliResult {
+    let store = postgres(settings)?;
+    let applied = applied_versions(&store).await?;
+
+    for migration in MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+    {
+        let status = if applied.contains(&migration.version) {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!(
+            "{:<14} {:<8} {}",
+            migration.version, status, migration.description
+        );
+    }
+    Ok(())
+}
+
+async fn list_users(settings: &StoreSettings) -> CliResult {
+    let store = postgres(settings)?;
+    let users = fetch_users(store).await?;
+
+    println!(
+        "{:>6}  {:<36}  {:<24}  CREATED AT",
+        "ID", "UUID", "USERNAME"
+    );
+    for user in users {
+        println!(
+            "{:>6}  {:<36}  {:<24}  {}",
+            user.id,
+            user.uuid,
+            user.username,
+            user.created_at.to_rfc3339()
+        );
+    }
+    Ok(())
+}
+
+async fn remove_user(settings: &StoreSettings, username: &str, yes: bool) -> CliResult {
+    let store = postgres(settings)?;
+    let cache = redis(settings).await?;
+
+    let user = fetch_user(store.clone(), username)
+        .await?
+        .ok_or_else(|| format!("User {username} not found"))?;
+
+    if !yes
+        && !confirm(&format!(
+            "Delete user {username} and all of their articles?"
+        ))?
+    {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let sessions = delete_user_sessions(&cache, username).await?;
+    delete_user(store, user.id).await?;
+    println!("Deleted user {username} and {sessions} session(s)");
+    Ok(())
+}
+
+async fn sync(settings: &Settings, username: &str) -> CliResult {
+    let stores = settings.stores();
+    let store = postgres(&stores)?;
+    let cache = redis(&stores).await?;
+
+    let user = fetch_user(store.clone(), username)
+        .await?
+        .ok_or_else(|| format!("User {username} not found"))?;
+
+    let started_at = Utc::now();
+    let result = sync_user(settings, &store, &cache, &user).await;
+    let (status, (stored, failed), error) = match &result {
+        Ok((stored, 0)) => (SyncRunStatus::Succeeded, (*stored, 0), None),
+        Ok(report) => (
+            SyncRunStatus::Failed,
+            *report,
+            Some("Some articles couldn't be stored".to_string()),
+        ),
+        Err(e) => (SyncRunStatus::Failed, (0, 0), Some(e.to_string())),
+    };
+    let run = SyncRun {
+        user_id: user.id,
+        trigger: SYNC_TRIGGER,
+        status,
+        started_at,
+        finished_at: Utc::now(),
+        articles_stored: stored,
+        articles_failed: failed,
+        error,
+    };
+    if let Err(e) = record_sync_run(store, &run).await {
+        eprintln!("Failed to record the sync run: {e}");
+    }
+    result?;
+
+    println!(
+        "Synced {stored} of {} articles for {username}",
+        stored + failed
+    );
+    Ok(())
+}
+
+/// Stores the user's library, returning how many articles were stored and how many failed.
+async fn sync_user(
+    settings: &Settings,
+    store: &Store,
+    cache: &Cache,
+    user: &User,
+) -> Result<(i32, i32), Box<dyn StdError>> {
+    let username = &user.username;
+    let keys = CredentialsKeys::new(&settings.pocket_credentials_keys);
+    let access_token = load_pocket_credentials(store, &keys, user.id)
+        .await?
+        .ok_or_else(|| {
+            format!("No valid Pocket credentials stored for {username}, they have to sign in")
+        })?;
+
+    cache
+        .check_pocket_quota(Some(username), QuotaPriority::Background)
+        .await?;
+
+    let pockety = Pockety::new(
+        settings.pocket_consumer_key.clone(),
+        settings.pocket_redirect_uri.as_str(),
+    )?;
+    let _sync_timer = metrics().start_sync(SYNC_TRIGGER);
+    let result = pockety
+        .retrieve()
+        .access_token(access_token.clone())
+        .execute()
+        .instrument(pocket_span("retrieve"))
+        .await;
+    metrics().record_pocket_call("retrieve", &result);
+    let res = match result {
+        Ok(res) => res,
+        Err(e) => {
+            let e = cache
+                .record_failed_pocket_call(Some(username), e.into())
+                .await;
+            return Err(
+                revoke_on_pocket_auth_failure(store, &keys, username, &access_token, e)
+                    .await
+                    .into(),
+            );
+        }
+    };
+    let _ = cache
+        .record_pocket_call(Some(username), Some(res.rate_limits.into()))
+        .await;
+
+    let (mut stored, mut failed) = (0, 0);
+    for item in res.data {
+        let item_id = item.item_id.0.clone();
+        match store_article(store, item, user.id).await {
+            Ok(_) => stored += 1,
+            Err(e) => {
+                eprintln!("Failed to store article {item_id}: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    Ok((stored, failed))
+}
+
+async fn export(settings: &Settings, username: &str, output: Option<PathBuf>) -> CliResult {
+    let store = postgres(&settings.stores())?;
+
+    let user = fetch_user(store.clone(), username)
+        .await?
+        .ok_or_else(|| format!("User {username} not found"))?;
+    let articles = fetch_articles(store, user.id).await?;
+
+    match output {
+        Some(path) => {
+            serde_json::to_writer_pretty(File::create(&path)?, &articles)?;
+            eprintln!("Exported {} articles to {}", articles.len(), path.display());
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &articles)?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+async fn purge_sessions(settings: &StoreSettings, include_unindexed: bool) -> CliResult {
+    let cache = redis(settings).await?;
+    let report =
+        purge_expired_sessions(&cache, settings.session_lifetime, include_unindexed).await?;
+    println!(
+        "Deleted {} expired session(s), set an expiry on {} session(s), dropped {} stale index entries",
+        report.deleted, report.expiry_set, report.stale_index_entries
+    );
+    Ok(())
+}
+
+async fn rotate_credentials(settings: &Settings) -> CliResult {
+    let store = postgres(&settings.stores())?;
+    let keys = CredentialsKeys::new(&settings.pocket_credentials_keys);
+
+    let credentials =
+        fetch_pocket_credentials_to_rotate(store.clone(), keys.active_key_id()).await?;
+    let total = credentials.len();
+    let mut rotated = 0;
+    for (user_id, encrypted) in credentials {
+        let Some(reencrypted) = keys.rotate(&encrypted)? else {
+            continue;
+        };
+        if replace_rotated_pocket_credentials(
+            store.clone(),
+            user_id,
+            &encrypted.key_id,
+            &reencrypted,
+        )
+        .await?
+        {
+            rotated += 1;
+        }
+    }
+
+    println!(
+        "Re-encrypted {rotated} of {total} credential(s) with key {}",
+        keys.active_key_id()
+    );
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
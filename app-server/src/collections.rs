@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    error::{ApiError, Error},
+    validation::{from_json, optional_text, required_text},
+};
+
+const MAX_TITLE_LEN: usize = 200;
+const MAX_DESCRIPTION_LEN: usize = 2_000;
+const MAX_COMMENTARY_LEN: usize = 5_000;
+
+/// Who a collection belongs to: a user, or a team whose members curate it together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionOwner {
+    User(i32),
+    Team(i32),
+}
+
+impl CollectionOwner {
+    pub fn user_id(self) -> Option<i32> {
+        match self {
+            CollectionOwner::User(user_id) => Some(user_id),
+            CollectionOwner::Team(_) => None,
+        }
+    }
+
+    pub fn team_id(self) -> Option<i32> {
+        match self {
+            CollectionOwner::User(_) => None,
+            CollectionOwner::Team(team_id) => Some(team_id),
+        }
+    }
+}
+
+/// A list of articles curated by hand.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub item_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An article of a collection, in the collection's order.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    pub item_id: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub excerpt: Option<String>,
+    pub top_image_url: Option<String>,
+    pub position: i32,
+    /// What the curator has to say about the article
+    pub commentary: Option<String>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Creates a collection, or replaces the title and description of one.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CollectionInput {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+/// Adds an article of the library at the end of a collection.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NewCollectionItem {
+    pub item_id: String,
+    pub commentary: Option<String>,
+}
+
+/// A `null` commentary removes it.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CollectionItemPatch {
+    pub commentary: Option<String>,
+}
+
+/// Every article of a collection, in their new order.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CollectionOrder {
+    pub item_ids: Vec<String>,
+}
+
+impl CollectionInput {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "collection")?;
+        Ok(Self {
+            title: required_text(&input.title, "title", MAX_TITLE_LEN)?,
+            description: optional_text(
+                input.description.as_deref(),
+                "description",
+                MAX_DESCRIPTION_LEN,
+            )?,
+        })
+    }
+}
+
+impl NewCollectionItem {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "collection item")?;
+        Ok(Self {
+            item_id: input.item_id.trim().to_string(),
+            commentary: optional_text(
+                input.commentary.as_deref(),
+                "commentary",
+                MAX_COMMENTARY_LEN,
+            )?,
+        })
+    }
+}
+
+impl CollectionItemPatch {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "collection item")?;
+        Ok(Self {
+            commentary: optional_text(
+                input.commentary.as_deref(),
+                "commentary",
+                MAX_COMMENTARY_LEN,
+            )?,
+        })
+    }
+}
+
+impl CollectionOrder {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        from_json(body, "order")
+    }
+
+    /// Refuses an order that doesn't list every article of the collection exactly once, so items
+    /// added or removed meanwhile aren't silently misplaced.
+    pub fn check(&self, current: &[String]) -> Result<(), Error> {
+        let mut seen = HashSet::new();
+        if let Some(item_id) = self.item_ids.iter().find(|item_id| !seen.insert(*item_id)) {
+            return Err(Error::Api(ApiError::BadRequest(format!(
+                "Article {item_id} is listed more than once"
+            ))));
+        }
+
+        let current = current.iter().collect::<HashSet<_>>();
+        if seen != current {
+            return Err(Error::Api(ApiError::BadRequest(
+                "itemIds must list every article of the collection".to_string(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validates_collections() {
+        let collection =
+            CollectionInput::from_json(br#"{"title":" Rust ","description":"  "}"#).unwrap();
+        assert_eq!(collection.title, "Rust");
+        assert_eq!(collection.description, None);
+
+        for body in [
+            r#"{"title":"  "}"#,
+            r#"{"description":"Rust"}"#,
+            r#"{"title":"Rust","color":"red"}"#,
+        ] {
+            assert_eq!(
+                CollectionInput::from_json(body.as_bytes())
+                    .unwrap_err()
+                    .code(),
+                "bad_request",
+                "{body}"
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_only_orders_of_every_item() {
+        let current = ["1", "2", "3"].map(str::to_string);
+        let order = |item_ids: &[&str]| CollectionOrder {
+            item_ids: item_ids.iter().map(|item_id| item_id.to_string()).collect(),
+        };
+
+        assert!(order(&["3", "1", "2"]).check(&current).is_ok());
+        assert!(order(&[]).check(&[]).is_ok());
+        for item_ids in [
+            &["3", "1"][..],
+            &["3", "1", "2", "4"],
+            &["3", "1", "1", "2"],
+            &["3", "1", "4"],
+        ] {
+            assert!(order(item_ids).check(&current).is_err(), "{item_ids:?}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use biscuit::{
+    jwa::{ContentEncryptionAlgorithm, EncryptionOptions, KeyManagementAlgorithm},
+    jwe,
+    jwk::JWK,
+    CompactJson, Empty,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{
+        fetch_pocket_credentials, fetch_user, revoke_pocket_credentials, upsert_pocket_credentials,
+    },
+    error::{ApiError, Error, PocketError},
+    oauth::Jwt,
+    Store,
+};
+
+/// Length in bytes of the AES-256 master keys
+pub const CREDENTIALS_KEY_LEN: usize = 32;
+
+/// A master key encrypting Pocket access tokens at rest, written as `<key_id>:<key>` in the
+/// settings.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CredentialsKey {
+    pub id: String,
+    key: Vec<u8>,
+}
+
+impl FromStr for CredentialsKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, key) = s
+            .split_once(':')
+            .ok_or_else(|| "Expected `<key_id>:<key>`".to_string())?;
+        let id = id.trim();
+        if id.is_empty() {
+            return Err("Key id can't be empty".to_string());
+        }
+        if key.len() != CREDENTIALS_KEY_LEN {
+            return Err(format!(
+                "Key `{id}` must be exactly {CREDENTIALS_KEY_LEN} bytes long"
+            ));
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            key: key.as_bytes().to_vec(),
+        })
+    }
+}
+
+/// Keys are secrets, only their ids are printed.
+impl fmt::Debug for CredentialsKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:<redacted>", self.id)
+    }
+}
+
+/// The master keys of Pocket credentials. Tokens are encrypted with the first, active, key and
+/// decrypted with whichever key they were encrypted with, so a new key can be put in front while
+/// `rotate` re-encrypts the credentials still using the older ones.
+#[derive(Clone)]
+pub struct CredentialsKeys {
+    active: String,
+    keys: HashMap<String, JWK<Empty>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StoredAccessToken {
+    access_token: String,
+}
+
+impl CompactJson for StoredAccessToken {}
+
+/// Access token encrypted with the master key `key_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedAccessToken {
+    pub key_id: String,
+    pub token: String,
+}
+
+impl CredentialsKeys {
+    /// `keys` can't be empty, which settings validation makes sure of.
+    pub fn new(keys: &[CredentialsKey]) -> Self {
+        Self {
+            active: keys.first().map(|key| key.id.clone()).unwrap_or_default(),
+            keys: keys
+                .iter()
+                .map(|key| {
+                    (
+                        key.id.clone(),
+                        JWK::new_octet_key(&key.key, Default::default()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active
+    }
+
+    fn key(&self, key_id: &str) -> Result<&JWK<Empty>, Error> {
+        self.keys.get(key_id).ok_or_else(|| {
+            Error::Api(ApiError::InternalServerError(format!(
+                "Unknown credentials key `{key_id}`"
+            )))
+        })
+    }
+
+    pub fn encrypt(&self, access_token: &str) -> Result<EncryptedAccessToken, Error> {
+        let key = self.key(&self.active)?;
+        let options = EncryptionOptions::AES_GCM {
+            nonce: Jwt::generated_nonce(),
+        };
+
+        let encrypted = jwe::Compact::<_, Empty>::new_decrypted(
+            jwe::RegisteredHeader {
+                cek_algorithm: KeyManagementAlgorithm::A256GCMKW,
+                enc_algorithm: ContentEncryptionAlgorithm::A256GCM,
+                key_id: Some(self.active.clone()),
+                ..Default::default()
+            }
+            .into(),
+            StoredAccessToken {
+                access_token: access_token.to_string(),
+            },
+        )
+        .encrypt(key, &options)?;
+
+        match encrypted {
+            jwe::Compact::Encrypted(token) => Ok(EncryptedAccessToken {
+                key_id: self.active.clone(),
+                token: token.encode(),
+            }),
+            jwe::Compact::Decrypted { .. } => Err(Error::Api(ApiError::InternalServerError(
+                "Failed to encrypt access token".to_string(),
+            ))),
+        }
+    }
+
+    pub fn decrypt(&self, encrypted: &EncryptedAccessToken) -> Result<String, Error> {
+        let key = self.key(&encrypted.key_id)?;
+
+        jwe::Compact::<StoredAccessToken, Empty>::new_encrypted(&encrypted.token)
+            .into_decrypted(
+                key,
+                KeyManagementAlgorithm::A256GCMKW,
+                ContentEncryptionAlgorithm::A256GCM,
+            )
+            .and_then(|decrypted| decrypted.payload().cloned())
+            .map(|stored| stored.access_token)
+            .map_err(Error::from)
+    }
+
+    /// Re-encrypts a token with the active key, `None` when it already uses it.
+    pub fn rotate(
+        &self,
+        encrypted: &EncryptedAccessToken,
+    ) -> Result<Option<EncryptedAccessToken>, Error> {
+        if encrypted.key_id == self.active {
+            return Ok(None);
+        }
+        self.encrypt(&self.decrypt(encrypted)?).map(Some)
+    }
+}
+
+/// Keeps the user's access token, so offline jobs can act for them once their session expired.
+pub async fn store_pocket_credentials(
+    store: &Store,
+    keys: &CredentialsKeys,
+    user_id: i32,
+    access_token: &str,
+) -> Result<(), Error> {
+    let encrypted = keys.encrypt(access_token)?;
+    upsert_pocket_credentials(store.clone(), user_id, &encrypted).await
+}
+
+/// The user's access token, `None` when they never signed in since credentials are stored or
+/// Pocket revoked it.
+pub async fn load_pocket_credentials(
+    store: &Store,
+    keys: &CredentialsKeys,
+    user_id: i32,
+) -> Result<Option<String>, Error> {
+    match fetch_pocket_credentials(store.clone(), user_id).await? {
+        Some((encrypted, false)) => keys.decrypt(&encrypted).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Flags the stored credentials of `username` as revoked when Pocket rejected their access token,
+/// turning the error into `ApiError::ReauthRequired`. Any other error is passed through as is.
+///
+/// Credentials stored since the rejected token was read, e.g. by signing in again, are kept.
+pub async fn revoke_on_pocket_auth_failure(
+    store: &Store,
+    keys: &CredentialsKeys,
+    username: &str,
+    rejected_token: &str,
+    error: Error,
+) -> Error {
+    if !matches!(error, Error::Pocket(PocketError::Unauthorized(_))) {
+        return error;
+    }
+
+    match revoke_rejected_credentials(store, keys, username, rejected_token).await {
+        Ok(true) => tracing::info!(
+            "Pocket rejected the access token of user {username}, revoked credentials"
+        ),
+        Ok(false) => tracing::info!(
+            "Pocket rejected an access token of user {username} that isn't stored anymore"
+        ),
+        Err(e) => tracing::error!("Failed to revoke credentials of user {username}. Error: {e:?}"),
+    }
+
+    Error::Api(ApiError::ReauthRequired(
+        "Pocket authorization expired or was revoked".to_string(),
+    ))
+}
+
+async fn revoke_rejected_credentials(
+    store: &Store,
+    keys: &CredentialsKeys,
+    username: &str,
+    rejected_token: &str,
+) -> Result<bool, Error> {
+    let Some(user) = fetch_user(store.clone(), username).await? else {
+        return Ok(false);
+    };
+    let Some((encrypted, false)) = fetch_pocket_credentials(store.clone(), user.id).await? else {
+        return Ok(false);
+    };
+    if keys.decrypt(&encrypted)? != rejected_token {
+        return Ok(false);
+    }
+    revoke_pocket_credentials(store.clone(), user.id, &encrypted).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(entries: &[&str]) -> CredentialsKeys {
+        let keys = entries
+            .iter()
+            .map(|entry| entry.parse().unwrap())
+            .collect::<Vec<CredentialsKey>>();
+        CredentialsKeys::new(&keys)
+    }
+
+    #[test]
+    fn round_trips_access_tokens() {
+        let keys = keys(&["2026-10:i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb"]);
+        let encrypted = keys.encrypt("5678defg-5678").unwrap();
+
+        assert_eq!(encrypted.key_id, "2026-10");
+        assert!(!encrypted.token.contains("5678defg"));
+        assert_eq!(keys.decrypt(&encrypted).unwrap(), "5678defg-5678");
+    }
+
+    #[test]
+    fn rotates_to_the_active_key() {
+        let old = keys(&["old:i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb"]);
+        let rotated = keys(&[
+            "new:0123456789abcdef0123456789abcdef",
+            "old:i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb",
+        ]);
+        let encrypted = old.encrypt("5678defg-5678").unwrap();
+
+        let reencrypted = rotated.rotate(&encrypted).unwrap().unwrap();
+        assert_eq!(reencrypted.key_id, "new");
+        assert_eq!(rotated.decrypt(&reencrypted).unwrap(), "5678defg-5678");
+        assert_eq!(rotated.rotate(&reencrypted).unwrap(), None);
+        assert!(old.decrypt(&reencrypted).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!("i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb"
+            .parse::<CredentialsKey>()
+            .is_err());
+        assert!(":i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb"
+            .parse::<CredentialsKey>()
+            .is_err());
+        assert!("k:too-short".parse::<CredentialsKey>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::State,
+    http::{
+        header::{HeaderName, ORIGIN, REFERER},
+        Method, Request,
+    },
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    error::{ApiError, Error},
+    session::AuthzedSessionData,
+    Config,
+};
+
+pub static CSRF_TOKEN_HEADER_NAME: HeaderName = HeaderName::from_static("x-csrf-token");
+
+/// Guards every state-changing request against cross-site request forgery.
+///
+/// Requests carrying a valid session cookie have to come with an `Origin` (or, failing that,
+/// `Referer`) header matching the configured user agent url, and echo the session's csrf token
+/// back in the `x-csrf-token` header. The token is handed out to the client by `/auth/session`.
+///
+/// Requests without a session cookie, like calls from other servers, have no ambient credentials
+/// to forge, they're only refused when they name a foreign origin.
+pub async fn verify_csrf<B>(
+    State(config): State<Config>,
+    session_data: Option<AuthzedSessionData>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, Error> {
+    const LOG_TAG: &str = "[verify_csrf]";
+
+    if is_safe_method(request.method()) {
+        return Ok(next.run(request).await);
+    }
+
+    let headers = request.headers();
+    let origin = headers
+        .get(ORIGIN)
+        .or_else(|| headers.get(REFERER))
+        .and_then(|value| value.to_str().ok());
+
+    if let Err(e) = check_origin(origin, session_data.is_some(), &config.user_agent_url) {
+        tracing::debug!("{LOG_TAG} rejected request from origin: {origin:?}");
+        return Err(e);
+    }
+
+    if let Some(session_data) = session_data {
+        let csrf_token = headers
+            .get(&CSRF_TOKEN_HEADER_NAME)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        if session_data.csrf_token.is_empty()
+            || !constant_time_eq(csrf_token.as_bytes(), session_data.csrf_token.as_bytes())
+        {
+            tracing::debug!(
+                "{LOG_TAG} csrf token doesn't match for a cookie authenticated request"
+            );
+            return Err(Error::Api(ApiError::Forbidden(
+                "CSRF token doesn't match".to_string(),
+            )));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn check_origin(
+    origin: Option<&str>,
+    cookie_authenticated: bool,
+    user_agent_url: &str,
+) -> Result<(), Error> {
+    match origin {
+        Some(origin) if !is_allowed_origin(origin, user_agent_url) => Err(Error::Api(
+            ApiError::Forbidden("Origin not allowed".to_string()),
+        )),
+        None if cookie_authenticated => Err(Error::Api(ApiError::Forbidden(
+            "Origin or Referer required".to_string(),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Accepts both bare origins (`Origin`) and full urls (`Referer`) as long as they point at the
+/// configured user agent.
+pub(crate) fn is_allowed_origin(origin: &str, allowed: &str) -> bool {
+    let allowed = allowed.trim_end_matches('/');
+    match origin.strip_prefix(allowed) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allows_matching_origin_and_referer() {
+        let allowed = "https://just-links.dev/";
+        assert!(is_allowed_origin("https://just-links.dev", allowed));
+        assert!(is_allowed_origin(
+            "https://just-links.dev/settings",
+            allowed
+        ));
+    }
+
+    #[test]
+    fn rejects_foreign_origins() {
+        let allowed = "https://just-links.dev";
+        assert!(!is_allowed_origin("https://evil.dev", allowed));
+        assert!(!is_allowed_origin(
+            "https://just-links.dev.evil.dev",
+            allowed
+        ));
+    }
+
+    #[test]
+    fn requires_an_origin_from_cookie_authenticated_requests() {
+        let allowed = "https://just-links.dev";
+        assert!(check_origin(Some("https://just-links.dev"), true, allowed).is_ok());
+        assert!(check_origin(None, true, allowed).is_err());
+        assert!(check_origin(Some("https://evil.dev"), false, allowed).is_err());
+        assert!(check_origin(None, false, allowed).is_ok());
+    }
+
+    #[test]
+    fn compares_tokens() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token-longer"));
+    }
+}
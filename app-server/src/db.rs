@@ -1,14 +1,35 @@
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use futures::TryFutureExt;
 use std::sync::Arc;
 
-use sqlx::{PgPool, Pool, Postgres};
-use tracing::{error, info};
+use pockety::models::ItemStatus;
+use serde::Serialize;
+use sqlx::{migrate::Migrator, PgPool, Pool, Postgres, Transaction};
+use tracing::{error, info, instrument};
 
-use crate::{api::articles::Article, domain::User, error::Error};
+use crate::{
+    annotations::{
+        AnnotatedArticle, ArticleHighlight, ArticleNote, MatchSource, NewHighlight, SearchResult,
+    },
+    api::articles::{parse_flag, PocketArticle},
+    collections::{Collection, CollectionInput, CollectionItem, CollectionOrder, CollectionOwner},
+    credentials::EncryptedAccessToken,
+    domain::{
+        ArticleEvent, ArticleEventKind, ArticleState, OpenedArticle, SyncRun, SyncRunStatus, User,
+    },
+    error::{ApiError, Error},
+    shares::{ActiveShare, ShareLink, SharedArticle},
+    stats::{DailyEventCount, ReadArticle},
+    teams::{AcceptedTeamInvitation, PendingTeamInvitation, Team, TeamMember, TeamRole},
+    user_settings::{UserSettings, UserSettingsPatch},
+};
 
-#[derive(Debug, Clone)]
+/// Migrations embedded in the binary, shared by the server, the admin cli and the readiness probe.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ArticleModel {
     pub user_id: i32,
     pub item_id: String,
@@ -37,6 +58,40 @@ pub struct ArticleModel {
     pub top_image_url: Option<String>,
 }
 
+/// A saved article of user 1 Pocket knows nothing about, for tests to fill in what they need.
+#[cfg(test)]
+impl Default for ArticleModel {
+    fn default() -> Self {
+        Self {
+            user_id: 1,
+            item_id: "229279689".to_string(),
+            resolved_id: None,
+            given_url: Some("https://example.com".to_string()),
+            given_title: None,
+            favorite: false,
+            status: ItemStatus::Normal.as_u8() as i32,
+            time_added: None,
+            time_updated: None,
+            time_read: None,
+            time_favorited: None,
+            sort_id: None,
+            resolved_url: None,
+            resolved_title: None,
+            excerpt: None,
+            is_article: true,
+            is_index: false,
+            has_image: None,
+            has_video: None,
+            word_count: None,
+            tags: None,
+            lang: None,
+            time_to_read: None,
+            listen_duration_estimate: None,
+            top_image_url: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ArticleVideoModel {
     pub article_id: i32,
@@ -78,8 +133,8 @@ pub struct UserModel {
     pub updated_at: DateTime<Utc>,
 }
 
-pub fn convert_article_to_article_model(
-    article: Article,
+pub(crate) fn convert_article_to_article_model(
+    article: PocketArticle,
     user_id: i32,
 ) -> Result<ArticleModel, Error> {
     let article_model = ArticleModel {
@@ -88,15 +143,7 @@ pub fn convert_article_to_article_model(
         resolved_id: article.resolved_id,
         given_url: article.given_url,
         given_title: article.given_title,
-        favorite: article
-            .favorite
-            .and_then(|favorite| {
-                favorite
-                    .parse()
-                    .map_err(|_| Error::Db("Failed to parse favorite to bool.".to_string()))
-                    .ok()
-            })
-            .unwrap_or(false),
+        favorite: parse_flag(article.favorite.as_deref(), false),
         status: article.status.as_u8() as i32,
         time_added: article.time_added.map(|time| time.0),
         time_updated: article.time_updated.map(|time| time.0),
@@ -106,14 +153,8 @@ pub fn convert_article_to_article_model(
         resolved_url: article.resolved_url,
         resolved_title: article.resolved_title,
         excerpt: article.excerpt,
-        is_article: article
-            .is_article
-            .map(|is_article| !matches!(is_article.as_str(), "0"))
-            .unwrap_or(true),
-        is_index: article
-            .is_index
-            .map(|is_index| !matches!(is_index.as_str(), "0"))
-            .unwrap_or(true),
+        is_article: parse_flag(article.is_article.as_deref(), true),
+        is_index: parse_flag(article.is_index.as_deref(), true),
         has_image: article.has_image.map(|has_image| has_image.as_u8() as i32),
         has_video: article.has_video.map(|has_video| has_video.as_u8() as i32),
         word_count: article
@@ -128,8 +169,8 @@ pub fn convert_article_to_article_model(
     Ok(article_model)
 }
 
-pub fn convert_article_to_article_video_models(
-    article: Article,
+pub(crate) fn convert_article_to_article_video_models(
+    article: PocketArticle,
     article_id: i32,
 ) -> Result<Vec<ArticleVideoModel>, Error> {
     let article_video_model: Vec<ArticleVideoModel> = article
@@ -166,8 +207,8 @@ pub fn convert_article_to_article_video_models(
     Ok(article_video_model)
 }
 
-pub fn convert_article_to_article_image_models(
-    articles: Article,
+pub(crate) fn convert_article_to_article_image_models(
+    articles: PocketArticle,
     article_id: i32,
 ) -> Result<Vec<ArticleImageModel>, Error> {
     let article_image_models: Vec<ArticleImageModel> = articles
@@ -204,8 +245,8 @@ pub fn convert_article_to_article_image_models(
     Ok(article_image_models)
 }
 
-pub fn convert_article_to_article_author_models(
-    articles: Article,
+pub(crate) fn convert_article_to_article_author_models(
+    articles: PocketArticle,
     article_id: i32,
 ) -> Result<Vec<ArticleAuthorModel>, Error> {
     let article_author_models: Vec<ArticleAuthorModel> = articles
@@ -226,6 +267,11 @@ pub fn convert_article_to_article_author_models(
     Ok(article_author_models)
 }
 
+#[instrument(
+    name = "db.fetch_user",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
 pub async fn fetch_user(pool: Arc<PgPool>, username: &str) -> Result<Option<User>, Error> {
     sqlx::query!(
         r#"
@@ -251,6 +297,11 @@ pub async fn fetch_user(pool: Arc<PgPool>, username: &str) -> Result<Option<User
     .await
 }
 
+#[instrument(
+    name = "db.create_new_user_if_not_exists",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
 pub async fn create_new_user_if_not_exists(pool: Arc<PgPool>, username: &str) -> Result<(), Error> {
     sqlx::query!(
         r#"
@@ -271,6 +322,2380 @@ pub async fn create_new_user_if_not_exists(pool: Arc<PgPool>, username: &str) ->
     Ok(())
 }
 
+#[instrument(
+    name = "db.fetch_users",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_users(pool: Arc<PgPool>) -> Result<Vec<User>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT id, user_uuid, username, created_at, updated_at
+    FROM users
+    ORDER BY id"#
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| User {
+                id: record.id,
+                uuid: record.user_uuid.to_string(),
+                username: record.username,
+                created_at: record.created_at.with_timezone(&Utc),
+                updated_at: record.updated_at.with_timezone(&Utc),
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch users. Error: {e:?}");
+        Error::Db("Failed to fetch users.".to_string())
+    })
+    .await
+}
+
+/// Deletes the user along with their articles. Returns whether the user existed.
+#[instrument(
+    name = "db.delete_user",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_user(pool: Arc<PgPool>, user_id: i32) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM users
+    WHERE id = $1"#,
+        user_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete user. Error: {e:?}");
+        Error::Db("Failed to delete user.".to_string())
+    })
+    .inspect_ok(|_| info!("Deleted user with id: {user_id}"))
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_articles",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_articles(pool: Arc<PgPool>, user_id: i32) -> Result<Vec<ArticleModel>, Error> {
+    sqlx::query_as!(
+        ArticleModel,
+        r#"
+    SELECT
+        user_id AS "user_id!",
+        item_id,
+        resolved_id,
+        given_url,
+        given_title,
+        favorite,
+        status AS "status!",
+        time_added,
+        time_updated,
+        time_read,
+        time_favorited,
+        sort_id,
+        resolved_url,
+        resolved_title,
+        excerpt,
+        is_article,
+        is_index,
+        has_image,
+        has_video,
+        word_count,
+        tags,
+        lang,
+        time_to_read,
+        listen_duration_estimate,
+        top_image_url
+    FROM pocket_articles
+    WHERE user_id = $1
+    ORDER BY time_added DESC NULLS LAST, id"#,
+        user_id
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch articles. Error: {e:?}");
+        Error::Db("Failed to fetch articles.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_user_settings",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_user_settings(pool: Arc<PgPool>, user_id: i32) -> Result<UserSettings, Error> {
+    let record = sqlx::query!(
+        r#"
+    SELECT schema_version, settings
+    FROM user_settings
+    WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch user settings. Error: {e:?}");
+        Error::Db("Failed to fetch user settings.".to_string())
+    })
+    .await?;
+
+    match record {
+        Some(record) => UserSettings::from_stored(record.schema_version, record.settings),
+        None => Ok(UserSettings::default()),
+    }
+}
+
+/// Applies `patch` to the user's settings, upgrading them to the current schema version. The row
+/// is locked in between, so concurrent updates of different fields don't overwrite each other.
+#[instrument(
+    name = "db.update_user_settings",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_user_settings(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    patch: UserSettingsPatch,
+) -> Result<UserSettings, Error> {
+    let mut tx = pool
+        .begin()
+        .map_err(|e| {
+            error!("Failed to begin transaction. Error: {e:?}");
+            Error::Db("Failed to update user settings.".to_string())
+        })
+        .await?;
+
+    let record = sqlx::query!(
+        r#"
+    SELECT schema_version, settings
+    FROM user_settings
+    WHERE user_id = $1
+    FOR UPDATE"#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to fetch user settings. Error: {e:?}");
+        Error::Db("Failed to update user settings.".to_string())
+    })
+    .await?;
+
+    let settings = match record {
+        Some(record) => UserSettings::from_stored(record.schema_version, record.settings)?,
+        None => UserSettings::default(),
+    }
+    .apply(patch);
+    let (schema_version, stored) = settings.to_stored()?;
+
+    sqlx::query!(
+        r#"
+    INSERT INTO user_settings (user_id, schema_version, settings)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (user_id) DO UPDATE SET
+        schema_version = EXCLUDED.schema_version,
+        settings = EXCLUDED.settings,
+        updated_at = NOW()"#,
+        user_id,
+        schema_version,
+        stored
+    )
+    .execute(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to store user settings. Error: {e:?}");
+        Error::Db("Failed to update user settings.".to_string())
+    })
+    .await?;
+
+    tx.commit()
+        .map_err(|e| {
+            error!("Failed to commit user settings. Error: {e:?}");
+            Error::Db("Failed to update user settings.".to_string())
+        })
+        .inspect_ok(|_| info!("Updated settings of user with id: {user_id}"))
+        .await?;
+
+    Ok(settings)
+}
+
+/// Users who turned on `enableAutomaticSync` in their settings. Reads the key of the current
+/// settings schema, so a version renaming it has to update this query too.
+#[instrument(
+    name = "db.fetch_auto_sync_users",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_auto_sync_users(pool: Arc<PgPool>) -> Result<Vec<User>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT users.id, users.user_uuid, users.username, users.created_at, users.updated_at
+    FROM users
+    JOIN user_settings ON user_settings.user_id = users.id
+    WHERE (user_settings.settings ->> 'enableAutomaticSync')::BOOLEAN
+    ORDER BY users.id"#
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| User {
+                id: record.id,
+                uuid: record.user_uuid.to_string(),
+                username: record.username,
+                created_at: record.created_at.with_timezone(&Utc),
+                updated_at: record.updated_at.with_timezone(&Utc),
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch users with automatic sync. Error: {e:?}");
+        Error::Db("Failed to fetch users with automatic sync.".to_string())
+    })
+    .await
+}
+
+/// When the latest sync of the user that stored every article started.
+#[instrument(
+    name = "db.fetch_last_successful_sync",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_last_successful_sync(
+    pool: Arc<PgPool>,
+    user_id: i32,
+) -> Result<Option<DateTime<Utc>>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT MAX(started_at) AS started_at
+    FROM sync_runs
+    WHERE user_id = $1 AND status = $2"#,
+        user_id,
+        SyncRunStatus::Succeeded.as_str()
+    )
+    .fetch_one(&*pool)
+    .map_ok(|record| record.started_at)
+    .map_err(|e| {
+        error!("Failed to fetch last successful sync. Error: {e:?}");
+        Error::Db("Failed to fetch last successful sync.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.record_sync_run",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn record_sync_run(pool: Arc<PgPool>, run: &SyncRun) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+    INSERT INTO sync_runs (
+        user_id,
+        trigger,
+        status,
+        started_at,
+        finished_at,
+        articles_stored,
+        articles_failed,
+        error
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+        run.user_id,
+        run.trigger,
+        run.status.as_str(),
+        run.started_at,
+        run.finished_at,
+        run.articles_stored,
+        run.articles_failed,
+        run.error
+    )
+    .execute(&*pool)
+    .map_err(|e| {
+        error!("Failed to record sync run. Error: {e:?}");
+        Error::Db("Failed to record sync run.".to_string())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the user's encrypted access token, clearing the revoked flag of a previous one.
+#[instrument(
+    name = "db.upsert_pocket_credentials",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn upsert_pocket_credentials(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    encrypted: &EncryptedAccessToken,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+    INSERT INTO pocket_credentials (user_id, encrypted_access_token, key_id)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (user_id) DO UPDATE SET
+        encrypted_access_token = EXCLUDED.encrypted_access_token,
+        key_id = EXCLUDED.key_id,
+        revoked = FALSE,
+        updated_at = NOW()"#,
+        user_id,
+        encrypted.token,
+        encrypted.key_id
+    )
+    .execute(&*pool)
+    .map_err(|e| {
+        error!("Failed to store pocket credentials. Error: {e:?}");
+        Error::Db("Failed to store pocket credentials.".to_string())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The user's encrypted access token and whether it was revoked.
+#[instrument(
+    name = "db.fetch_pocket_credentials",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_pocket_credentials(
+    pool: Arc<PgPool>,
+    user_id: i32,
+) -> Result<Option<(EncryptedAccessToken, bool)>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT encrypted_access_token, key_id, revoked
+    FROM pocket_credentials
+    WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| {
+            (
+                EncryptedAccessToken {
+                    key_id: record.key_id,
+                    token: record.encrypted_access_token,
+                },
+                record.revoked,
+            )
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to fetch pocket credentials. Error: {e:?}");
+        Error::Db("Failed to fetch pocket credentials.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.revoke_pocket_credentials",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+/// Only revokes `encrypted`, returning whether it was still the stored token.
+pub async fn revoke_pocket_credentials(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    encrypted: &EncryptedAccessToken,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE pocket_credentials
+    SET revoked = TRUE, updated_at = NOW()
+    WHERE user_id = $1 AND key_id = $2 AND encrypted_access_token = $3"#,
+        user_id,
+        encrypted.key_id,
+        encrypted.token
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to revoke pocket credentials. Error: {e:?}");
+        Error::Db("Failed to revoke pocket credentials.".to_string())
+    })
+    .await
+}
+
+/// Returns whether the user had credentials stored.
+#[instrument(
+    name = "db.delete_pocket_credentials",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_pocket_credentials(pool: Arc<PgPool>, user_id: i32) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM pocket_credentials
+    WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete pocket credentials. Error: {e:?}");
+        Error::Db("Failed to delete pocket credentials.".to_string())
+    })
+    .inspect_ok(|_| info!("Deleted pocket credentials of user with id: {user_id}"))
+    .await
+}
+
+/// Credentials encrypted with another key than `active_key_id`, by user id.
+#[instrument(
+    name = "db.fetch_pocket_credentials_to_rotate",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_pocket_credentials_to_rotate(
+    pool: Arc<PgPool>,
+    active_key_id: &str,
+) -> Result<Vec<(i32, EncryptedAccessToken)>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT user_id, encrypted_access_token, key_id
+    FROM pocket_credentials
+    WHERE key_id <> $1
+    ORDER BY user_id"#,
+        active_key_id
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| {
+                (
+                    record.user_id,
+                    EncryptedAccessToken {
+                        key_id: record.key_id,
+                        token: record.encrypted_access_token,
+                    },
+                )
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch pocket credentials to rotate. Error: {e:?}");
+        Error::Db("Failed to fetch pocket credentials to rotate.".to_string())
+    })
+    .await
+}
+
+/// Replaces credentials encrypted with `previous_key_id` by their re-encrypted version. Returns
+/// false when they changed in the meantime, e.g. because the user signed in again.
+#[instrument(
+    name = "db.replace_rotated_pocket_credentials",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn replace_rotated_pocket_credentials(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    previous_key_id: &str,
+    rotated: &EncryptedAccessToken,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE pocket_credentials
+    SET encrypted_access_token = $3, key_id = $4, updated_at = NOW()
+    WHERE user_id = $1 AND key_id = $2"#,
+        user_id,
+        previous_key_id,
+        rotated.token,
+        rotated.key_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to replace rotated pocket credentials. Error: {e:?}");
+        Error::Db("Failed to replace rotated pocket credentials.".to_string())
+    })
+    .await
+}
+
+/// Counts an open of the user's article, `None` when it isn't in their library.
+#[instrument(
+    name = "db.record_article_open",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn record_article_open(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    item_id: &str,
+) -> Result<Option<OpenedArticle>, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE pocket_articles
+    SET
+        open_count = open_count + 1,
+        first_opened_at = COALESCE(first_opened_at, NOW()),
+        last_opened_at = NOW()
+    WHERE user_id = $1 AND item_id = $2
+    RETURNING id, item_id, given_url, resolved_url, status, auto_archived_at"#,
+        user_id,
+        item_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| OpenedArticle {
+            id: record.id,
+            item_id: record.item_id,
+            given_url: record.given_url,
+            resolved_url: record.resolved_url,
+            archived: record.status == Some(ItemStatus::Archived.as_u8() as i32),
+            auto_archived: record.auto_archived_at.is_some(),
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to record article open. Error: {e:?}");
+        Error::Db("Failed to record article open.".to_string())
+    })
+    .await
+}
+
+/// Claims the automatic archiving of an article. Only the first of concurrent opens gets it, and
+/// none once the article was archived.
+#[instrument(
+    name = "db.claim_article_auto_archive",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn claim_article_auto_archive(pool: Arc<PgPool>, article_id: i32) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE pocket_articles
+    SET auto_archived_at = NOW()
+    WHERE id = $1 AND auto_archived_at IS NULL AND status IS DISTINCT FROM $2"#,
+        article_id,
+        ItemStatus::Archived.as_u8() as i32
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to claim article auto archive. Error: {e:?}");
+        Error::Db("Failed to claim article auto archive.".to_string())
+    })
+    .await
+}
+
+/// Settles a claim of `claim_article_auto_archive`: marks the article as archived when Pocket
+/// archived it, or gives the claim back so the next open tries again.
+#[instrument(
+    name = "db.complete_article_auto_archive",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn complete_article_auto_archive(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    archived: bool,
+) -> Result<(), Error> {
+    let query = if archived {
+        sqlx::query!(
+            r#"
+    UPDATE pocket_articles
+    SET status = $2, time_read = EXTRACT(EPOCH FROM NOW())::BIGINT
+    WHERE id = $1"#,
+            article_id,
+            ItemStatus::Archived.as_u8() as i32
+        )
+    } else {
+        sqlx::query!(
+            r#"
+    UPDATE pocket_articles
+    SET auto_archived_at = NULL
+    WHERE id = $1"#,
+            article_id
+        )
+    };
+
+    query
+        .execute(&*pool)
+        .map_err(|e| {
+            error!("Failed to complete article auto archive. Error: {e:?}");
+            Error::Db("Failed to complete article auto archive.".to_string())
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Status and favorite flag of the user's article as last stored, `None` when it's new.
+#[instrument(
+    name = "db.fetch_article_state",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_article_state(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    item_id: &str,
+) -> Result<Option<ArticleState>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT status, favorite
+    FROM pocket_articles
+    WHERE user_id = $1 AND item_id = $2"#,
+        user_id,
+        item_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| ArticleState {
+            status: record.status.unwrap_or_default(),
+            favorite: record.favorite,
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to fetch article state. Error: {e:?}");
+        Error::Db("Failed to fetch article state.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.record_article_events",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn record_article_events(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    article_id: i32,
+    events: &[ArticleEvent],
+) -> Result<(), Error> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let kinds = events
+        .iter()
+        .map(|event| event.kind.as_str().to_string())
+        .collect::<Vec<_>>();
+    let occurred_at = events
+        .iter()
+        .map(|event| event.occurred_at)
+        .collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+    INSERT INTO article_events (user_id, pocket_article_id, kind, occurred_at)
+    SELECT $1, $2, kind, occurred_at
+    FROM UNNEST($3::TEXT[], $4::TIMESTAMPTZ[]) AS events (kind, occurred_at)"#,
+        user_id,
+        article_id,
+        &kinds,
+        &occurred_at
+    )
+    .execute(&*pool)
+    .map_err(|e| {
+        error!("Failed to record article events. Error: {e:?}");
+        Error::Db("Failed to record article events.".to_string())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Number of events of each kind per UTC day, since `since`.
+#[instrument(
+    name = "db.fetch_daily_event_counts",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_daily_event_counts(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    since: DateTime<Utc>,
+) -> Result<Vec<DailyEventCount>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT
+        (occurred_at AT TIME ZONE 'UTC')::DATE AS "day!",
+        kind,
+        COUNT(*) AS "count!"
+    FROM article_events
+    WHERE user_id = $1 AND occurred_at >= $2
+    GROUP BY 1, 2
+    ORDER BY 1"#,
+        user_id,
+        since
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| DailyEventCount {
+                day: record.day,
+                kind: record.kind,
+                count: record.count,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch daily event counts. Error: {e:?}");
+        Error::Db("Failed to fetch daily event counts.".to_string())
+    })
+    .await
+}
+
+/// Every UTC day the user read an article on, oldest first.
+#[instrument(
+    name = "db.fetch_read_days",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_read_days(pool: Arc<PgPool>, user_id: i32) -> Result<Vec<NaiveDate>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT DISTINCT (occurred_at AT TIME ZONE 'UTC')::DATE AS "day!"
+    FROM article_events
+    WHERE user_id = $1 AND kind = $2
+    ORDER BY 1"#,
+        user_id,
+        ArticleEventKind::Archived.as_str()
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| records.into_iter().map(|record| record.day).collect())
+    .map_err(|e| {
+        error!("Failed to fetch read days. Error: {e:?}");
+        Error::Db("Failed to fetch read days.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.count_unread_articles",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn count_unread_articles(pool: Arc<PgPool>, user_id: i32) -> Result<i64, Error> {
+    sqlx::query!(
+        r#"
+    SELECT COUNT(*) AS "count!"
+    FROM pocket_articles
+    WHERE user_id = $1 AND status = $2"#,
+        user_id,
+        ItemStatus::Normal.as_u8() as i32
+    )
+    .fetch_one(&*pool)
+    .map_ok(|record| record.count)
+    .map_err(|e| {
+        error!("Failed to count unread articles. Error: {e:?}");
+        Error::Db("Failed to count unread articles.".to_string())
+    })
+    .await
+}
+
+/// The articles the user read since `since`.
+#[instrument(
+    name = "db.fetch_read_articles",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_read_articles(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    since: DateTime<Utc>,
+) -> Result<Vec<ReadArticle>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT resolved_url, given_url, tags, time_to_read
+    FROM pocket_articles
+    WHERE id IN (
+        SELECT pocket_article_id
+        FROM article_events
+        WHERE user_id = $1 AND kind = $2 AND occurred_at >= $3
+    )"#,
+        user_id,
+        ArticleEventKind::Archived.as_str(),
+        since
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| ReadArticle {
+                url: record.resolved_url.or(record.given_url),
+                tags: record.tags,
+                time_to_read: record.time_to_read,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch read articles. Error: {e:?}");
+        Error::Db("Failed to fetch read articles.".to_string())
+    })
+    .await
+}
+
+/// The user's article annotations are attached to, `None` when it isn't in their library.
+#[instrument(
+    name = "db.fetch_annotated_article",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_annotated_article(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    item_id: &str,
+) -> Result<Option<AnnotatedArticle>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT id, item_id, COALESCE(resolved_title, given_title) AS title, COALESCE(resolved_url, given_url) AS url
+    FROM pocket_articles
+    WHERE user_id = $1 AND item_id = $2"#,
+        user_id,
+        item_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| AnnotatedArticle {
+            id: record.id,
+            item_id: record.item_id,
+            title: record.title,
+            url: record.url,
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to fetch annotated article. Error: {e:?}");
+        Error::Db("Failed to fetch annotated article.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_article_notes",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_article_notes(
+    pool: Arc<PgPool>,
+    article_id: i32,
+) -> Result<Vec<ArticleNote>, Error> {
+    sqlx::query_as!(
+        ArticleNote,
+        r#"
+    SELECT id, body, created_at, updated_at
+    FROM article_notes
+    WHERE pocket_article_id = $1
+    ORDER BY created_at, id"#,
+        article_id
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch article notes. Error: {e:?}");
+        Error::Db("Failed to fetch article notes.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.insert_article_note",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_article_note(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    body: &str,
+) -> Result<ArticleNote, Error> {
+    sqlx::query_as!(
+        ArticleNote,
+        r#"
+    INSERT INTO article_notes (pocket_article_id, body)
+    VALUES ($1, $2)
+    RETURNING id, body, created_at, updated_at"#,
+        article_id,
+        body
+    )
+    .fetch_one(&*pool)
+    .map_err(|e| {
+        error!("Failed to insert article note. Error: {e:?}");
+        Error::Db("Failed to insert article note.".to_string())
+    })
+    .await
+}
+
+/// `None` when the article has no such note.
+#[instrument(
+    name = "db.update_article_note",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_article_note(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    note_id: i32,
+    body: &str,
+) -> Result<Option<ArticleNote>, Error> {
+    sqlx::query_as!(
+        ArticleNote,
+        r#"
+    UPDATE article_notes
+    SET body = $3, updated_at = NOW()
+    WHERE pocket_article_id = $1 AND id = $2
+    RETURNING id, body, created_at, updated_at"#,
+        article_id,
+        note_id,
+        body
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to update article note. Error: {e:?}");
+        Error::Db("Failed to update article note.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.delete_article_note",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_article_note(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    note_id: i32,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM article_notes
+    WHERE pocket_article_id = $1 AND id = $2"#,
+        article_id,
+        note_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete article note. Error: {e:?}");
+        Error::Db("Failed to delete article note.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_article_highlights",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_article_highlights(
+    pool: Arc<PgPool>,
+    article_id: i32,
+) -> Result<Vec<ArticleHighlight>, Error> {
+    sqlx::query_as!(
+        ArticleHighlight,
+        r#"
+    SELECT id, quote, start_offset, end_offset, note, created_at, updated_at
+    FROM article_highlights
+    WHERE pocket_article_id = $1
+    ORDER BY start_offset, id"#,
+        article_id
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch article highlights. Error: {e:?}");
+        Error::Db("Failed to fetch article highlights.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.insert_article_highlight",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_article_highlight(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    highlight: &NewHighlight,
+) -> Result<ArticleHighlight, Error> {
+    sqlx::query_as!(
+        ArticleHighlight,
+        r#"
+    INSERT INTO article_highlights (pocket_article_id, quote, start_offset, end_offset, note)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id, quote, start_offset, end_offset, note, created_at, updated_at"#,
+        article_id,
+        highlight.quote,
+        highlight.start_offset as i32,
+        highlight.end_offset as i32,
+        highlight.note
+    )
+    .fetch_one(&*pool)
+    .map_err(|e| {
+        error!("Failed to insert article highlight. Error: {e:?}");
+        Error::Db("Failed to insert article highlight.".to_string())
+    })
+    .await
+}
+
+/// `None` when the article has no such highlight.
+#[instrument(
+    name = "db.update_article_highlight_note",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_article_highlight_note(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    highlight_id: i32,
+    note: Option<&str>,
+) -> Result<Option<ArticleHighlight>, Error> {
+    sqlx::query_as!(
+        ArticleHighlight,
+        r#"
+    UPDATE article_highlights
+    SET note = $3, updated_at = NOW()
+    WHERE pocket_article_id = $1 AND id = $2
+    RETURNING id, quote, start_offset, end_offset, note, created_at, updated_at"#,
+        article_id,
+        highlight_id,
+        note
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to update article highlight. Error: {e:?}");
+        Error::Db("Failed to update article highlight.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.delete_article_highlight",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_article_highlight(
+    pool: Arc<PgPool>,
+    article_id: i32,
+    highlight_id: i32,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM article_highlights
+    WHERE pocket_article_id = $1 AND id = $2"#,
+        article_id,
+        highlight_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete article highlight. Error: {e:?}");
+        Error::Db("Failed to delete article highlight.".to_string())
+    })
+    .await
+}
+
+/// Full-text search of the user's articles by their title, excerpt and tags, and by their notes
+/// and highlights, best matches first. `query` uses the syntax of web search engines, e.g.
+/// `"exact phrase" -excluded`.
+#[instrument(
+    name = "db.search_articles",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn search_articles(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SearchResult>, Error> {
+    sqlx::query!(
+        r#"
+    WITH query AS (
+        SELECT websearch_to_tsquery('simple', $2) AS query
+    ),
+    matches AS (
+        SELECT a.id, 'article' AS source, ts_rank(a.search_vector, query.query) AS rank
+        FROM pocket_articles a, query
+        WHERE a.user_id = $1 AND a.search_vector @@ query.query
+        UNION ALL
+        SELECT a.id, 'note', ts_rank(n.search_vector, query.query)
+        FROM article_notes n
+        JOIN pocket_articles a ON a.id = n.pocket_article_id, query
+        WHERE a.user_id = $1 AND n.search_vector @@ query.query
+        UNION ALL
+        SELECT a.id, 'highlight', ts_rank(h.search_vector, query.query)
+        FROM article_highlights h
+        JOIN pocket_articles a ON a.id = h.pocket_article_id, query
+        WHERE a.user_id = $1 AND h.search_vector @@ query.query
+    )
+    SELECT
+        a.item_id,
+        COALESCE(a.resolved_title, a.given_title) AS title,
+        COALESCE(a.resolved_url, a.given_url) AS url,
+        ARRAY_AGG(DISTINCT m.source) AS "matched_in!",
+        MAX(m.rank) AS "rank!"
+    FROM matches m
+    JOIN pocket_articles a ON a.id = m.id
+    GROUP BY a.id
+    ORDER BY 5 DESC, a.id
+    LIMIT $3"#,
+        user_id,
+        query,
+        limit
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| SearchResult {
+                item_id: record.item_id,
+                title: record.title,
+                url: record.url,
+                matched_in: record
+                    .matched_in
+                    .iter()
+                    .filter_map(|source| MatchSource::parse(source))
+                    .collect(),
+                rank: record.rank,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to search articles. Error: {e:?}");
+        Error::Db("Failed to search articles.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_collections",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_collections(
+    pool: Arc<PgPool>,
+    owner: CollectionOwner,
+) -> Result<Vec<Collection>, Error> {
+    sqlx::query_as!(
+        Collection,
+        r#"
+    SELECT
+        c.id,
+        c.title,
+        c.description,
+        (SELECT COUNT(*) FROM collection_items i WHERE i.collection_id = c.id) AS "item_count!",
+        c.created_at,
+        c.updated_at
+    FROM collections c
+    WHERE c.team_id IS NOT DISTINCT FROM $2 AND (c.team_id IS NOT NULL OR c.user_id = $1)
+    ORDER BY c.updated_at DESC, c.id DESC"#,
+        owner.user_id(),
+        owner.team_id()
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch collections. Error: {e:?}");
+        Error::Db("Failed to fetch collections.".to_string())
+    })
+    .await
+}
+
+/// `None` when the owner has no such collection.
+#[instrument(
+    name = "db.fetch_collection",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_collection(
+    pool: Arc<PgPool>,
+    owner: CollectionOwner,
+    collection_id: i32,
+) -> Result<Option<Collection>, Error> {
+    sqlx::query_as!(
+        Collection,
+        r#"
+    SELECT
+        c.id,
+        c.title,
+        c.description,
+        (SELECT COUNT(*) FROM collection_items i WHERE i.collection_id = c.id) AS "item_count!",
+        c.created_at,
+        c.updated_at
+    FROM collections c
+    WHERE c.id = $3
+        AND c.team_id IS NOT DISTINCT FROM $2
+        AND (c.team_id IS NOT NULL OR c.user_id = $1)"#,
+        owner.user_id(),
+        owner.team_id(),
+        collection_id
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch collection. Error: {e:?}");
+        Error::Db("Failed to fetch collection.".to_string())
+    })
+    .await
+}
+
+/// Creates a collection of `owner`, `user_id` being the user who creates it.
+#[instrument(
+    name = "db.insert_collection",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_collection(
+    pool: Arc<PgPool>,
+    owner: CollectionOwner,
+    user_id: i32,
+    collection: &CollectionInput,
+) -> Result<Collection, Error> {
+    sqlx::query_as!(
+        Collection,
+        r#"
+    INSERT INTO collections (user_id, team_id, title, description)
+    VALUES ($1, $2, $3, $4)
+    RETURNING id, title, description, 0::BIGINT AS "item_count!", created_at, updated_at"#,
+        user_id,
+        owner.team_id(),
+        collection.title,
+        collection.description
+    )
+    .fetch_one(&*pool)
+    .map_err(|e| {
+        error!("Failed to insert collection. Error: {e:?}");
+        Error::Db("Failed to insert collection.".to_string())
+    })
+    .await
+}
+
+/// `None` when the owner has no such collection.
+#[instrument(
+    name = "db.update_collection",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_collection(
+    pool: Arc<PgPool>,
+    owner: CollectionOwner,
+    collection_id: i32,
+    collection: &CollectionInput,
+) -> Result<Option<Collection>, Error> {
+    sqlx::query_as!(
+        Collection,
+        r#"
+    UPDATE collections c
+    SET title = $4, description = $5, updated_at = NOW()
+    WHERE c.id = $3
+        AND c.team_id IS NOT DISTINCT FROM $2
+        AND (c.team_id IS NOT NULL OR c.user_id = $1)
+    RETURNING
+        c.id,
+        c.title,
+        c.description,
+        (SELECT COUNT(*) FROM collection_items i WHERE i.collection_id = c.id) AS "item_count!",
+        c.created_at,
+        c.updated_at"#,
+        owner.user_id(),
+        owner.team_id(),
+        collection_id,
+        collection.title,
+        collection.description
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to update collection. Error: {e:?}");
+        Error::Db("Failed to update collection.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.delete_collection",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_collection(
+    pool: Arc<PgPool>,
+    owner: CollectionOwner,
+    collection_id: i32,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM collections
+    WHERE id = $3
+        AND team_id IS NOT DISTINCT FROM $2
+        AND (team_id IS NOT NULL OR user_id = $1)"#,
+        owner.user_id(),
+        owner.team_id(),
+        collection_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete collection. Error: {e:?}");
+        Error::Db("Failed to delete collection.".to_string())
+    })
+    .await
+}
+
+/// The articles of a collection in its order, the oldest first among equal positions.
+#[instrument(
+    name = "db.fetch_collection_items",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_collection_items(
+    pool: Arc<PgPool>,
+    collection_id: i32,
+) -> Result<Vec<CollectionItem>, Error> {
+    sqlx::query_as!(
+        CollectionItem,
+        r#"
+    SELECT
+        a.item_id,
+        COALESCE(a.resolved_title, a.given_title) AS title,
+        COALESCE(a.resolved_url, a.given_url) AS url,
+        a.excerpt,
+        a.top_image_url,
+        i.position,
+        i.commentary,
+        i.added_at
+    FROM collection_items i
+    JOIN pocket_articles a ON a.id = i.pocket_article_id
+    WHERE i.collection_id = $1
+    ORDER BY i.position, i.added_at, a.id"#,
+        collection_id
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch collection items. Error: {e:?}");
+        Error::Db("Failed to fetch collection items.".to_string())
+    })
+    .await
+}
+
+/// Id of an article of the user that can be added to a collection, `None` when it isn't in their
+/// library or was deleted from Pocket.
+#[instrument(
+    name = "db.fetch_collectable_article_id",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_collectable_article_id(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    item_id: &str,
+) -> Result<Option<i32>, Error> {
+    sqlx::query_scalar!(
+        r#"
+    SELECT id
+    FROM pocket_articles
+    WHERE user_id = $1 AND item_id = $2 AND status <> $3"#,
+        user_id,
+        item_id,
+        ItemStatus::Deleted.as_u8() as i32
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch collectable article. Error: {e:?}");
+        Error::Db("Failed to fetch collectable article.".to_string())
+    })
+    .await
+}
+
+/// Adds the article at the end of the collection, `None` when it's already in it. Members of a
+/// team may have saved the same Pocket item, which is only added once to a team collection.
+#[instrument(
+    name = "db.insert_collection_item",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_collection_item(
+    pool: Arc<PgPool>,
+    collection_id: i32,
+    article_id: i32,
+    commentary: Option<&str>,
+) -> Result<Option<CollectionItem>, Error> {
+    sqlx::query_as!(
+        CollectionItem,
+        r#"
+    WITH inserted AS (
+        INSERT INTO collection_items (collection_id, pocket_article_id, position, commentary)
+        SELECT
+            $1,
+            $2,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM collection_items WHERE collection_id = $1),
+            $3
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM collection_items i
+            JOIN pocket_articles a ON a.id = i.pocket_article_id
+            WHERE i.collection_id = $1
+                AND a.item_id = (SELECT item_id FROM pocket_articles WHERE id = $2)
+        )
+        ON CONFLICT DO NOTHING
+        RETURNING pocket_article_id, position, commentary, added_at
+    )
+    SELECT
+        a.item_id,
+        COALESCE(a.resolved_title, a.given_title) AS title,
+        COALESCE(a.resolved_url, a.given_url) AS url,
+        a.excerpt,
+        a.top_image_url,
+        i.position,
+        i.commentary,
+        i.added_at
+    FROM inserted i
+    JOIN pocket_articles a ON a.id = i.pocket_article_id"#,
+        collection_id,
+        article_id,
+        commentary
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to insert collection item. Error: {e:?}");
+        Error::Db("Failed to insert collection item.".to_string())
+    })
+    .await
+}
+
+/// `None` when the article isn't in the collection.
+#[instrument(
+    name = "db.update_collection_item",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_collection_item(
+    pool: Arc<PgPool>,
+    collection_id: i32,
+    item_id: &str,
+    commentary: Option<&str>,
+) -> Result<Option<CollectionItem>, Error> {
+    sqlx::query_as!(
+        CollectionItem,
+        r#"
+    UPDATE collection_items i
+    SET commentary = $3
+    FROM pocket_articles a
+    WHERE i.collection_id = $1 AND a.id = i.pocket_article_id AND a.item_id = $2
+    RETURNING
+        a.item_id,
+        COALESCE(a.resolved_title, a.given_title) AS title,
+        COALESCE(a.resolved_url, a.given_url) AS url,
+        a.excerpt,
+        a.top_image_url,
+        i.position,
+        i.commentary,
+        i.added_at"#,
+        collection_id,
+        item_id,
+        commentary
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to update collection item. Error: {e:?}");
+        Error::Db("Failed to update collection item.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.delete_collection_item",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_collection_item(
+    pool: Arc<PgPool>,
+    collection_id: i32,
+    item_id: &str,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM collection_items i
+    USING pocket_articles a
+    WHERE i.collection_id = $1 AND a.id = i.pocket_article_id AND a.item_id = $2"#,
+        collection_id,
+        item_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to delete collection item. Error: {e:?}");
+        Error::Db("Failed to delete collection item.".to_string())
+    })
+    .await
+}
+
+/// Moves the articles of the collection to the positions of `order`, which is checked against the
+/// articles while they're locked.
+#[instrument(
+    name = "db.reorder_collection_items",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn reorder_collection_items(
+    pool: Arc<PgPool>,
+    collection_id: i32,
+    order: &CollectionOrder,
+) -> Result<(), Error> {
+    let mut tx = pool
+        .begin()
+        .map_err(|e| {
+            error!("Failed to begin transaction. Error: {e:?}");
+            Error::Db("Failed to reorder collection items.".to_string())
+        })
+        .await?;
+
+    let current = sqlx::query_scalar!(
+        r#"
+    SELECT a.item_id
+    FROM collection_items i
+    JOIN pocket_articles a ON a.id = i.pocket_article_id
+    WHERE i.collection_id = $1
+    FOR UPDATE OF i"#,
+        collection_id
+    )
+    .fetch_all(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to fetch collection items. Error: {e:?}");
+        Error::Db("Failed to reorder collection items.".to_string())
+    })
+    .await?;
+    order.check(&current)?;
+
+    sqlx::query!(
+        r#"
+    UPDATE collection_items i
+    SET position = (o.position - 1)::INT
+    FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS o(item_id, position), pocket_articles a
+    WHERE i.collection_id = $1 AND a.id = i.pocket_article_id AND a.item_id = o.item_id"#,
+        collection_id,
+        &order.item_ids
+    )
+    .execute(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to store collection order. Error: {e:?}");
+        Error::Db("Failed to reorder collection items.".to_string())
+    })
+    .await?;
+
+    tx.commit()
+        .map_err(|e| {
+            error!("Failed to commit collection order. Error: {e:?}");
+            Error::Db("Failed to reorder collection items.".to_string())
+        })
+        .await
+}
+
+/// Drops an article deleted from Pocket from every collection it was in.
+#[instrument(
+    name = "db.remove_article_from_collections",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn remove_article_from_collections(
+    pool: Arc<PgPool>,
+    article_id: i32,
+) -> Result<u64, Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM collection_items
+    WHERE pocket_article_id = $1"#,
+        article_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected())
+    .map_err(|e| {
+        error!("Failed to remove article from collections. Error: {e:?}");
+        Error::Db("Failed to remove article from collections.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.fetch_share_links",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_share_links(pool: Arc<PgPool>, user_id: i32) -> Result<Vec<ShareLink>, Error> {
+    sqlx::query_as!(
+        ShareLink,
+        r#"
+    SELECT
+        s.id,
+        s.collection_id,
+        a.item_id AS "item_id?",
+        s.tag,
+        s.created_at,
+        s.expires_at,
+        s.revoked_at
+    FROM share_links s
+    LEFT JOIN pocket_articles a ON a.id = s.pocket_article_id
+    WHERE s.user_id = $1
+    ORDER BY s.created_at DESC, s.id DESC"#,
+        user_id
+    )
+    .fetch_all(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch share links. Error: {e:?}");
+        Error::Db("Failed to fetch share links.".to_string())
+    })
+    .await
+}
+
+/// Stores a link sharing exactly one of `collection_id`, `article_id` and `tag`, which the caller
+/// checked belong to the user.
+#[instrument(
+    name = "db.insert_share_link",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_share_link(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    token_hash: &str,
+    share: &ActiveShare,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<ShareLink, Error> {
+    sqlx::query_as!(
+        ShareLink,
+        r#"
+    WITH inserted AS (
+        INSERT INTO share_links (user_id, token_hash, collection_id, pocket_article_id, tag, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+    )
+    SELECT
+        s.id,
+        s.collection_id,
+        a.item_id AS "item_id?",
+        s.tag,
+        s.created_at,
+        s.expires_at,
+        s.revoked_at
+    FROM inserted s
+    LEFT JOIN pocket_articles a ON a.id = s.pocket_article_id"#,
+        user_id,
+        token_hash,
+        share.collection_id,
+        share.pocket_article_id,
+        share.tag,
+        expires_at
+    )
+    .fetch_one(&*pool)
+    .map_err(|e| {
+        error!("Failed to insert share link. Error: {e:?}");
+        Error::Db("Failed to insert share link.".to_string())
+    })
+    .await
+}
+
+/// `false` when the user has no such link. Revoking a revoked link keeps its first revocation.
+#[instrument(
+    name = "db.revoke_share_link",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn revoke_share_link(
+    pool: Arc<PgPool>,
+    user_id: i32,
+    share_id: i32,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE share_links
+    SET revoked_at = COALESCE(revoked_at, NOW())
+    WHERE user_id = $1 AND id = $2"#,
+        user_id,
+        share_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to revoke share link. Error: {e:?}");
+        Error::Db("Failed to revoke share link.".to_string())
+    })
+    .await
+}
+
+/// What the link with the token hashing to `token_hash` shares, `None` when there's no such link
+/// or it was revoked or expired.
+#[instrument(
+    name = "db.fetch_active_share",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_active_share(
+    pool: Arc<PgPool>,
+    token_hash: &str,
+) -> Result<Option<ActiveShare>, Error> {
+    sqlx::query_as!(
+        ActiveShare,
+        r#"
+    SELECT user_id, collection_id, pocket_article_id, tag
+    FROM share_links
+    WHERE token_hash = $1
+        AND revoked_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())"#,
+        token_hash
+    )
+    .fetch_optional(&*pool)
+    .map_err(|e| {
+        error!("Failed to fetch share link. Error: {e:?}");
+        Error::Db("Failed to fetch share link.".to_string())
+    })
+    .await
+}
+
+/// `None` when the article was deleted from Pocket.
+#[instrument(
+    name = "db.fetch_shared_article",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_shared_article(
+    pool: Arc<PgPool>,
+    article_id: i32,
+) -> Result<Option<SharedArticle>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT
+        COALESCE(resolved_title, given_title) AS title,
+        COALESCE(resolved_url, given_url) AS url,
+        excerpt,
+        top_image_url
+    FROM pocket_articles
+    WHERE id = $1 AND status <> $2"#,
+        article_id,
+        ItemStatus::Deleted.as_u8() as i32
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| {
+            SharedArticle::new(
+                record.title,
+                record.url,
+                record.excerpt,
+                record.top_image_url,
+            )
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to fetch shared article. Error: {e:?}");
+        Error::Db("Failed to fetch shared article.".to_string())
+    })
+    .await
+}
+
+/// Creates a team with `user_id` as its owner.
+#[instrument(
+    name = "db.insert_team",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_team(pool: Arc<PgPool>, user_id: i32, name: &str) -> Result<Team, Error> {
+    sqlx::query!(
+        r#"
+    WITH team AS (
+        INSERT INTO teams (name)
+        VALUES ($2)
+        RETURNING id, name, created_at
+    ),
+    member AS (
+        INSERT INTO team_members (team_id, user_id, role)
+        SELECT id, $1, $3 FROM team
+    )
+    SELECT id, name, created_at FROM team"#,
+        user_id,
+        name,
+        TeamRole::Owner.as_str()
+    )
+    .fetch_one(&*pool)
+    .map_ok(|record| Team {
+        id: record.id,
+        name: record.name,
+        role: TeamRole::Owner,
+        member_count: 1,
+        created_at: record.created_at,
+    })
+    .map_err(|e| {
+        error!("Failed to insert team. Error: {e:?}");
+        Error::Db("Failed to insert team.".to_string())
+    })
+    .await
+}
+
+/// Teams `user_id` is a member of, with their role in each.
+#[instrument(
+    name = "db.fetch_teams",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_teams(pool: Arc<PgPool>, user_id: i32) -> Result<Vec<Team>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT
+        t.id,
+        t.name,
+        m.role,
+        (SELECT COUNT(*) FROM team_members o WHERE o.team_id = t.id) AS "member_count!",
+        t.created_at
+    FROM teams t
+    JOIN team_members m ON m.team_id = t.id
+    WHERE m.user_id = $1
+    ORDER BY t.name, t.id"#,
+        user_id
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| Team {
+                id: record.id,
+                name: record.name,
+                role: TeamRole::parse(&record.role).unwrap_or_default(),
+                member_count: record.member_count,
+                created_at: record.created_at,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch teams. Error: {e:?}");
+        Error::Db("Failed to fetch teams.".to_string())
+    })
+    .await
+}
+
+/// `None` when `user_id` isn't a member of the team.
+#[instrument(
+    name = "db.fetch_team",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_team(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    user_id: i32,
+) -> Result<Option<Team>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT
+        t.id,
+        t.name,
+        m.role,
+        (SELECT COUNT(*) FROM team_members o WHERE o.team_id = t.id) AS "member_count!",
+        t.created_at
+    FROM teams t
+    JOIN team_members m ON m.team_id = t.id
+    WHERE t.id = $1 AND m.user_id = $2"#,
+        team_id,
+        user_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|record| {
+        record.map(|record| Team {
+            id: record.id,
+            name: record.name,
+            role: TeamRole::parse(&record.role).unwrap_or_default(),
+            member_count: record.member_count,
+            created_at: record.created_at,
+        })
+    })
+    .map_err(|e| {
+        error!("Failed to fetch team. Error: {e:?}");
+        Error::Db("Failed to fetch team.".to_string())
+    })
+    .await
+}
+
+/// Role of `user_id` in the team, `None` when they aren't a member.
+#[instrument(
+    name = "db.fetch_team_role",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_team_role(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    user_id: i32,
+) -> Result<Option<TeamRole>, Error> {
+    sqlx::query_scalar!(
+        r#"
+    SELECT role
+    FROM team_members
+    WHERE team_id = $1 AND user_id = $2"#,
+        team_id,
+        user_id
+    )
+    .fetch_optional(&*pool)
+    .map_ok(|role| role.and_then(|role| TeamRole::parse(&role)))
+    .map_err(|e| {
+        error!("Failed to fetch team role. Error: {e:?}");
+        Error::Db("Failed to fetch team role.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.update_team_name",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_team_name(pool: Arc<PgPool>, team_id: i32, name: &str) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+    UPDATE teams
+    SET name = $2, updated_at = NOW()
+    WHERE id = $1"#,
+        team_id,
+        name
+    )
+    .execute(&*pool)
+    .map_ok(|_| ())
+    .map_err(|e| {
+        error!("Failed to update team. Error: {e:?}");
+        Error::Db("Failed to update team.".to_string())
+    })
+    .await
+}
+
+/// Deletes the team along with its collections.
+#[instrument(
+    name = "db.delete_team",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_team(pool: Arc<PgPool>, team_id: i32) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+    DELETE FROM teams
+    WHERE id = $1"#,
+        team_id
+    )
+    .execute(&*pool)
+    .map_ok(|_| ())
+    .map_err(|e| {
+        error!("Failed to delete team. Error: {e:?}");
+        Error::Db("Failed to delete team.".to_string())
+    })
+    .await
+}
+
+/// Members of the team, owners first.
+#[instrument(
+    name = "db.fetch_team_members",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_team_members(pool: Arc<PgPool>, team_id: i32) -> Result<Vec<TeamMember>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT u.username, m.role, m.joined_at
+    FROM team_members m
+    JOIN users u ON u.id = m.user_id
+    WHERE m.team_id = $1
+    ORDER BY CASE m.role WHEN 'owner' THEN 0 WHEN 'editor' THEN 1 ELSE 2 END, u.username"#,
+        team_id
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| TeamMember {
+                username: record.username,
+                role: TeamRole::parse(&record.role).unwrap_or_default(),
+                joined_at: record.joined_at,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch team members. Error: {e:?}");
+        Error::Db("Failed to fetch team members.".to_string())
+    })
+    .await
+}
+
+#[instrument(
+    name = "db.insert_team_invitation",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn insert_team_invitation(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    invited_by: i32,
+    id_hash: &str,
+    role: TeamRole,
+    expires_at: DateTime<Utc>,
+) -> Result<PendingTeamInvitation, Error> {
+    sqlx::query!(
+        r#"
+    WITH inserted AS (
+        INSERT INTO team_invitations (team_id, id_hash, role, invited_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+    )
+    SELECT i.id, u.username, i.created_at, i.expires_at
+    FROM inserted i
+    JOIN users u ON u.id = i.invited_by"#,
+        team_id,
+        id_hash,
+        role.as_str(),
+        invited_by,
+        expires_at
+    )
+    .fetch_one(&*pool)
+    .map_ok(|record| PendingTeamInvitation {
+        id: record.id,
+        role,
+        invited_by: record.username,
+        created_at: record.created_at,
+        expires_at: record.expires_at,
+    })
+    .map_err(|e| {
+        error!("Failed to insert team invitation. Error: {e:?}");
+        Error::Db("Failed to insert team invitation.".to_string())
+    })
+    .await
+}
+
+/// Invitations of the team that can still be accepted, latest first.
+#[instrument(
+    name = "db.fetch_team_invitations",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn fetch_team_invitations(
+    pool: Arc<PgPool>,
+    team_id: i32,
+) -> Result<Vec<PendingTeamInvitation>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT i.id, i.role, u.username, i.created_at, i.expires_at
+    FROM team_invitations i
+    JOIN users u ON u.id = i.invited_by
+    WHERE i.team_id = $1 AND i.used_at IS NULL AND i.revoked_at IS NULL AND i.expires_at > NOW()
+    ORDER BY i.created_at DESC, i.id DESC"#,
+        team_id
+    )
+    .fetch_all(&*pool)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| PendingTeamInvitation {
+                id: record.id,
+                role: TeamRole::parse(&record.role).unwrap_or_default(),
+                invited_by: record.username,
+                created_at: record.created_at,
+                expires_at: record.expires_at,
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to fetch team invitations. Error: {e:?}");
+        Error::Db("Failed to fetch team invitations.".to_string())
+    })
+    .await
+}
+
+/// `false` when the team has no such invitation waiting to be accepted.
+#[instrument(
+    name = "db.revoke_team_invitation",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn revoke_team_invitation(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    invitation_id: i32,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"
+    UPDATE team_invitations
+    SET revoked_at = NOW()
+    WHERE team_id = $1 AND id = $2 AND used_at IS NULL AND revoked_at IS NULL"#,
+        team_id,
+        invitation_id
+    )
+    .execute(&*pool)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to revoke team invitation. Error: {e:?}");
+        Error::Db("Failed to revoke team invitation.".to_string())
+    })
+    .await
+}
+
+/// Adds `user_id` to the team of the invitation whose token id hashes to `id_hash`, using it up.
+/// `None` when the team has no such invitation, or it was used, revoked or expired, or its sender
+/// isn't an owner of the team anymore.
+#[instrument(
+    name = "db.accept_team_invitation",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn accept_team_invitation(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    id_hash: &str,
+    user_id: i32,
+) -> Result<Option<AcceptedTeamInvitation>, Error> {
+    let mut tx = pool
+        .begin()
+        .map_err(|e| {
+            error!("Failed to begin transaction. Error: {e:?}");
+            Error::Db("Failed to accept team invitation.".to_string())
+        })
+        .await?;
+
+    let Some(invitation) = sqlx::query!(
+        r#"
+    SELECT i.id, i.team_id, i.role
+    FROM team_invitations i
+    JOIN team_members m ON m.team_id = i.team_id AND m.user_id = i.invited_by
+    WHERE i.team_id = $1
+        AND i.id_hash = $2
+        AND i.used_at IS NULL
+        AND i.revoked_at IS NULL
+        AND i.expires_at > NOW()
+        AND m.role = 'owner'
+    FOR UPDATE OF i"#,
+        team_id,
+        id_hash
+    )
+    .fetch_optional(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to fetch team invitation. Error: {e:?}");
+        Error::Db("Failed to accept team invitation.".to_string())
+    })
+    .await?
+    else {
+        return Ok(None);
+    };
+    let role = TeamRole::parse(&invitation.role).unwrap_or_default();
+
+    let joined = sqlx::query!(
+        r#"
+    INSERT INTO team_members (team_id, user_id, role)
+    VALUES ($1, $2, $3)
+    ON CONFLICT DO NOTHING"#,
+        invitation.team_id,
+        user_id,
+        role.as_str()
+    )
+    .execute(&mut *tx)
+    .map_ok(|result| result.rows_affected() > 0)
+    .map_err(|e| {
+        error!("Failed to insert team member. Error: {e:?}");
+        Error::Db("Failed to accept team invitation.".to_string())
+    })
+    .await?;
+
+    if joined {
+        sqlx::query!(
+            r#"
+    UPDATE team_invitations
+    SET used_at = NOW(), used_by = $2
+    WHERE id = $1"#,
+            invitation.id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .map_err(|e| {
+            error!("Failed to use up team invitation. Error: {e:?}");
+            Error::Db("Failed to accept team invitation.".to_string())
+        })
+        .await?;
+    }
+
+    tx.commit()
+        .map_err(|e| {
+            error!("Failed to commit team invitation. Error: {e:?}");
+            Error::Db("Failed to accept team invitation.".to_string())
+        })
+        .await?;
+
+    Ok(Some(AcceptedTeamInvitation {
+        team_id: invitation.team_id,
+        role,
+        joined,
+    }))
+}
+
+/// Locks the members of the team until the end of `tx`, returning them with their ids.
+async fn lock_team_members(
+    tx: &mut Transaction<'_, Postgres>,
+    team_id: i32,
+) -> Result<Vec<(i32, String, TeamRole)>, Error> {
+    sqlx::query!(
+        r#"
+    SELECT m.user_id, u.username, m.role
+    FROM team_members m
+    JOIN users u ON u.id = m.user_id
+    WHERE m.team_id = $1
+    FOR UPDATE OF m"#,
+        team_id
+    )
+    .fetch_all(&mut **tx)
+    .map_ok(|records| {
+        records
+            .into_iter()
+            .map(|record| {
+                (
+                    record.user_id,
+                    record.username,
+                    TeamRole::parse(&record.role).unwrap_or_default(),
+                )
+            })
+            .collect()
+    })
+    .map_err(|e| {
+        error!("Failed to lock team members. Error: {e:?}");
+        Error::Db("Failed to lock team members.".to_string())
+    })
+    .await
+}
+
+fn last_owner(members: &[(i32, String, TeamRole)], user_id: i32) -> bool {
+    members
+        .iter()
+        .all(|(member_id, _, role)| *member_id == user_id || *role != TeamRole::Owner)
+}
+
+/// Changes the role of the member, `None` when there's no such member. The last owner of a team
+/// can't step down.
+#[instrument(
+    name = "db.update_team_member_role",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn update_team_member_role(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    username: &str,
+    role: TeamRole,
+) -> Result<Option<TeamMember>, Error> {
+    let mut tx = pool
+        .begin()
+        .map_err(|e| {
+            error!("Failed to begin transaction. Error: {e:?}");
+            Error::Db("Failed to update team member.".to_string())
+        })
+        .await?;
+
+    let members = lock_team_members(&mut tx, team_id).await?;
+    let Some((user_id, _, _)) = members.iter().find(|(_, name, _)| name == username) else {
+        return Ok(None);
+    };
+    if role != TeamRole::Owner && last_owner(&members, *user_id) {
+        return Err(Error::Api(ApiError::BadRequest(
+            "A team needs at least one owner".to_string(),
+        )));
+    }
+
+    let member = sqlx::query!(
+        r#"
+    UPDATE team_members
+    SET role = $3
+    WHERE team_id = $1 AND user_id = $2
+    RETURNING joined_at"#,
+        team_id,
+        user_id,
+        role.as_str()
+    )
+    .fetch_one(&mut *tx)
+    .map_ok(|record| TeamMember {
+        username: username.to_string(),
+        role,
+        joined_at: record.joined_at,
+    })
+    .map_err(|e| {
+        error!("Failed to update team member. Error: {e:?}");
+        Error::Db("Failed to update team member.".to_string())
+    })
+    .await?;
+
+    tx.commit()
+        .map_err(|e| {
+            error!("Failed to commit team member. Error: {e:?}");
+            Error::Db("Failed to update team member.".to_string())
+        })
+        .await?;
+
+    Ok(Some(member))
+}
+
+/// Removes the member from the team along with the articles of their library they added to the
+/// team collections, `false` when there's no such member. The last owner of a team can't leave
+/// it, they delete it instead.
+#[instrument(
+    name = "db.delete_team_member",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql")
+)]
+pub async fn delete_team_member(
+    pool: Arc<PgPool>,
+    team_id: i32,
+    username: &str,
+) -> Result<bool, Error> {
+    let mut tx = pool
+        .begin()
+        .map_err(|e| {
+            error!("Failed to begin transaction. Error: {e:?}");
+            Error::Db("Failed to remove team member.".to_string())
+        })
+        .await?;
+
+    let members = lock_team_members(&mut tx, team_id).await?;
+    let Some((user_id, _, _)) = members.iter().find(|(_, name, _)| name == username) else {
+        return Ok(false);
+    };
+    if last_owner(&members, *user_id) {
+        return Err(Error::Api(ApiError::BadRequest(
+            "A team needs at least one owner".to_string(),
+        )));
+    }
+
+    sqlx::query!(
+        r#"
+    DELETE FROM collection_items i
+    USING collections c, pocket_articles a
+    WHERE c.id = i.collection_id
+        AND a.id = i.pocket_article_id
+        AND c.team_id = $1
+        AND a.user_id = $2"#,
+        team_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to remove articles of team member. Error: {e:?}");
+        Error::Db("Failed to remove team member.".to_string())
+    })
+    .await?;
+
+    sqlx::query!(
+        r#"
+    DELETE FROM team_members
+    WHERE team_id = $1 AND user_id = $2"#,
+        team_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .map_err(|e| {
+        error!("Failed to remove team member. Error: {e:?}");
+        Error::Db("Failed to remove team member.".to_string())
+    })
+    .await?;
+
+    tx.commit()
+        .map_err(|e| {
+            error!("Failed to commit team member removal. Error: {e:?}");
+            Error::Db("Failed to remove team member.".to_string())
+        })
+        .await?;
+
+    Ok(true)
+}
+
 #[async_trait]
 pub trait ArticleStore {
     async fn upsert_article(&self, article_model: ArticleModel) -> Result<i32, Error>;
@@ -296,6 +2721,11 @@ pub trait ArticleStore {
 
 #[async_trait]
 impl ArticleStore for Arc<Pool<Postgres>> {
+    #[instrument(
+        name = "db.upsert_article",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql")
+    )]
     async fn upsert_article(&self, article_model: ArticleModel) -> Result<i32, Error> {
         sqlx::query!(
             r#"
@@ -320,6 +2750,7 @@ impl ArticleStore for Arc<Pool<Postgres>> {
                 has_image,
                 has_video,
                 word_count,
+                tags,
                 lang,
                 time_to_read,
                 listen_duration_estimate,
@@ -349,7 +2780,8 @@ impl ArticleStore for Arc<Pool<Postgres>> {
                 $21,
                 $22,
                 $23,
-                $24
+                $24,
+                $25
             )
             ON CONFLICT (
                 user_id,
@@ -375,6 +2807,7 @@ impl ArticleStore for Arc<Pool<Postgres>> {
             has_image = EXCLUDED.has_image,
             has_video = EXCLUDED.has_video,
             word_count = EXCLUDED.word_count,
+            tags = EXCLUDED.tags,
             lang = EXCLUDED.lang,
             time_to_read = EXCLUDED.time_to_read,
             listen_duration_estimate = EXCLUDED.listen_duration_estimate,
@@ -400,6 +2833,7 @@ impl ArticleStore for Arc<Pool<Postgres>> {
             article_model.has_image,
             article_model.has_video,
             article_model.word_count,
+            article_model.tags,
             article_model.lang,
             article_model.time_to_read,
             article_model.listen_duration_estimate,
@@ -414,6 +2848,11 @@ impl ArticleStore for Arc<Pool<Postgres>> {
         .await
     }
 
+    #[instrument(
+        name = "db.upsert_article_image",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql")
+    )]
     async fn upsert_article_image(
         &self,
         article_id: i32,
@@ -472,6 +2911,11 @@ impl ArticleStore for Arc<Pool<Postgres>> {
         .await
     }
 
+    #[instrument(
+        name = "db.upsert_article_video",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql")
+    )]
     async fn upsert_article_video(
         &self,
         article_id: i32,
@@ -531,6 +2975,11 @@ impl ArticleStore for Arc<Pool<Postgres>> {
         .await
     }
 
+    #[instrument(
+        name = "db.upsert_article_author",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql")
+    )]
     async fn upsert_article_author(
         &self,
         article_id: i32,
@@ -0,0 +1,145 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::{header::LINK, HeaderMap, HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, TimeZone, Utc};
+
+pub static DEPRECATION_HEADER_NAME: HeaderName = HeaderName::from_static("deprecation");
+pub static SUNSET_HEADER_NAME: HeaderName = HeaderName::from_static("sunset");
+
+/// Routes under this prefix serve articles as Pocket sends them. Deprecated, along with their
+/// unversioned aliases.
+pub const API_V1_PREFIX: &str = "/v1";
+
+/// Routes under this prefix are the current version of the api.
+pub const API_V2_PREFIX: &str = "/v2";
+
+/// When the `/v1` routes and their unversioned aliases were deprecated.
+pub fn api_v1_deprecated_at() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap()
+}
+
+/// Marks the routes it's layered on as deprecated, with `mark_deprecated`.
+///
+/// Responses carry `Deprecation` (RFC 9745), `Sunset` (RFC 8594) once a removal date is known,
+/// and a `Link` to the successor route when there is one, so clients can migrate before the
+/// response shape changes or the route goes away.
+#[derive(Debug, Clone)]
+pub struct Deprecation {
+    since: DateTime<Utc>,
+    sunset: Option<DateTime<Utc>>,
+    successor: Option<(&'static str, &'static str)>,
+}
+
+impl Deprecation {
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            sunset: None,
+            successor: None,
+        }
+    }
+
+    pub fn sunset(self, sunset: Option<DateTime<Utc>>) -> Self {
+        Self { sunset, ..self }
+    }
+
+    /// The successor of a route is found by replacing the `from` prefix of its path with `to`,
+    /// e.g. `""` and `"/v2"` for the unversioned aliases.
+    pub fn successor(self, from: &'static str, to: &'static str) -> Self {
+        Self {
+            successor: Some((from, to)),
+            ..self
+        }
+    }
+
+    fn headers(&self, path: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if let Ok(value) = HeaderValue::from_str(&format!("@{}", self.since.timestamp())) {
+            headers.insert(DEPRECATION_HEADER_NAME.clone(), value);
+        }
+        if let Some(sunset) = self.sunset {
+            if let Ok(value) = HeaderValue::from_str(&http_date(sunset)) {
+                headers.insert(SUNSET_HEADER_NAME.clone(), value);
+            }
+        }
+        let successor = self.successor.and_then(|(from, to)| {
+            path.strip_prefix(from)
+                .map(|rest| format!("<{to}{rest}>; rel=\"successor-version\""))
+        });
+        if let Some(Ok(value)) = successor.map(|link| HeaderValue::from_str(&link)) {
+            headers.insert(LINK, value);
+        }
+
+        headers
+    }
+}
+
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Adds the deprecation headers of `Deprecation` to every response.
+pub async fn mark_deprecated<B>(
+    State(deprecation): State<Deprecation>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // Nested routers only see the path relative to where they're mounted
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.0.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    tracing::debug!("Deprecated route called: {path}");
+
+    let mut response = next.run(request).await;
+    response.headers_mut().extend(deprecation.headers(&path));
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn points_unversioned_aliases_to_v2() {
+        let deprecation = Deprecation::new(api_v1_deprecated_at())
+            .sunset(Some(Utc.with_ymd_and_hms(2027, 4, 1, 0, 0, 0).unwrap()))
+            .successor("", API_V2_PREFIX);
+        let headers = deprecation.headers("/articles");
+
+        assert_eq!(headers[&DEPRECATION_HEADER_NAME], "@1792281600");
+        assert_eq!(
+            headers[&SUNSET_HEADER_NAME],
+            "Thu, 01 Apr 2027 00:00:00 GMT"
+        );
+        assert_eq!(headers[LINK], "</v2/articles>; rel=\"successor-version\"");
+    }
+
+    #[test]
+    fn omits_unknown_sunset_and_successor() {
+        let headers = Deprecation::new(api_v1_deprecated_at())
+            .successor(API_V1_PREFIX, API_V2_PREFIX)
+            .headers("/articles");
+
+        assert!(headers.contains_key(&DEPRECATION_HEADER_NAME));
+        assert!(!headers.contains_key(&SUNSET_HEADER_NAME));
+        assert!(!headers.contains_key(LINK));
+    }
+
+    #[test]
+    fn points_v1_routes_to_v2() {
+        let headers = Deprecation::new(api_v1_deprecated_at())
+            .successor(API_V1_PREFIX, API_V2_PREFIX)
+            .headers("/v1/articles/sync");
+
+        assert_eq!(
+            headers[LINK],
+            "</v2/articles/sync>; rel=\"successor-version\""
+        );
+    }
+}
@@ -8,3 +8,83 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncRunStatus {
+    Succeeded,
+    /// Stopped by an error, or some articles couldn't be stored
+    Failed,
+    /// Didn't start, e.g. because the user has to sign in again or the Pocket quota is exhausted
+    Skipped,
+}
+
+impl SyncRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncRunStatus::Succeeded => "succeeded",
+            SyncRunStatus::Failed => "failed",
+            SyncRunStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Outcome of one sync of a user's library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncRun {
+    pub user_id: i32,
+    /// What started the sync, e.g. `scheduled`
+    pub trigger: &'static str,
+    pub status: SyncRunStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub articles_stored: i32,
+    pub articles_failed: i32,
+    pub error: Option<String>,
+}
+
+/// What the events of an article are found from when a sync stores a new version of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArticleState {
+    pub status: i32,
+    pub favorite: bool,
+}
+
+/// An article of the user's library that was just opened through its tracked link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenedArticle {
+    pub id: i32,
+    pub item_id: String,
+    pub given_url: Option<String>,
+    pub resolved_url: Option<String>,
+    pub archived: bool,
+    /// Whether an earlier open already archived it
+    pub auto_archived: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArticleEventKind {
+    Added,
+    Opened,
+    Archived,
+    Favorited,
+    Deleted,
+}
+
+impl ArticleEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArticleEventKind::Added => "added",
+            ArticleEventKind::Opened => "opened",
+            ArticleEventKind::Archived => "archived",
+            ArticleEventKind::Favorited => "favorited",
+            ArticleEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// Something that happened to an article of the user's library, recorded for their reading stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArticleEvent {
+    pub kind: ArticleEventKind,
+    pub occurred_at: DateTime<Utc>,
+}
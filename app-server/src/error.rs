@@ -1,15 +1,25 @@
 use axum::{
-    http::StatusCode,
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER},
+        HeaderValue, StatusCode,
+    },
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+use crate::request_id::current_request_id;
+
+const PROBLEM_TYPE_BASE_URL: &str = "https://just-links.dev/problems";
+const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
 
 #[derive(Debug, Clone)]
 pub enum Error {
     Cookie(String),
     Session(String),
-    Pocket(String),
+    Pocket(PocketError),
     Jwt(String),
     Api(ApiError),
     Db(String),
@@ -20,11 +30,197 @@ pub enum ApiError {
     BadRequest(String),
     InternalServerError(String),
     Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    /// The user's Pocket authorization is no longer valid and has to be renewed
+    ReauthRequired(String),
+    /// Carries the number of seconds until the client may retry
+    TooManyRequests(u64),
+    /// The server is temporarily refusing work, e.g. while shutting down
+    ServiceUnavailable(String),
+}
+
+/// Failures of calls to Pocket, classified by the http status Pocket responded with.
+#[derive(Debug, Clone)]
+pub enum PocketError {
+    /// 401, the access token is invalid or was revoked
+    Unauthorized(String),
+    /// 403, access was denied, e.g. to a consumer key without the required permission. Pocket
+    /// answers exhausted rate limits with a 403 too, `PocketQuotaStore::record_failed_pocket_call`
+    /// tells those apart with the stored quota.
+    AccessDenied(String),
+    /// 429
+    RateLimited(String),
+    /// 5xx
+    Unavailable(String),
+    Other(String),
+}
+
+impl PocketError {
+    /// pockety only exposes http failures through its error message, e.g.
+    /// `HTTP status client error (401 Unauthorized) for url (...)`, so the status is read from it.
+    fn from_message(message: String) -> Self {
+        match parse_status_code(&message).map(|status| status.as_u16()) {
+            Some(401) => PocketError::Unauthorized(message),
+            Some(403) => PocketError::AccessDenied(message),
+            Some(429) => PocketError::RateLimited(message),
+            Some(500..=599) => PocketError::Unavailable(message),
+            _ => PocketError::Other(message),
+        }
+    }
+}
+
+/// Only reads the status where pockety and reqwest write it, `status: 401` or
+/// `HTTP status client error (401 Unauthorized)`, so other numbers of the message, like the port of
+/// the url, aren't mistaken for it.
+fn parse_status_code(message: &str) -> Option<StatusCode> {
+    ["status: ", "error ("]
+        .into_iter()
+        .flat_map(|marker| {
+            message
+                .match_indices(marker)
+                .map(move |(start, _)| &message[start + marker.len()..])
+        })
+        .filter_map(|rest| {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            (digits == 3)
+                .then(|| rest[..3].parse::<u16>().ok())
+                .flatten()
+        })
+        .filter(|code| (400..=599).contains(code))
+        .find_map(|code| StatusCode::from_u16(code).ok())
+}
+
+impl Error {
+    /// Classifies a failed call to Pocket by the status code in its error message.
+    pub fn from_pocket_message(message: String) -> Self {
+        Error::Pocket(PocketError::from_message(message))
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Cookie(_) | Error::Api(ApiError::BadRequest(_)) => StatusCode::BAD_REQUEST,
+            Error::Jwt(_)
+            | Error::Api(ApiError::Unauthorized(_))
+            | Error::Api(ApiError::ReauthRequired(_)) => StatusCode::UNAUTHORIZED,
+            Error::Api(ApiError::Forbidden(_)) => StatusCode::FORBIDDEN,
+            Error::Api(ApiError::NotFound(_)) => StatusCode::NOT_FOUND,
+            Error::Api(ApiError::TooManyRequests(_)) => StatusCode::TOO_MANY_REQUESTS,
+            Error::Pocket(PocketError::Unauthorized(_)) => StatusCode::UNAUTHORIZED,
+            Error::Pocket(PocketError::RateLimited(_)) => StatusCode::TOO_MANY_REQUESTS,
+            Error::Pocket(PocketError::Unavailable(_))
+            | Error::Api(ApiError::ServiceUnavailable(_)) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Pocket(PocketError::AccessDenied(_) | PocketError::Other(_)) => {
+                StatusCode::BAD_GATEWAY
+            }
+            Error::Session(_) | Error::Db(_) | Error::Api(ApiError::InternalServerError(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Stable, machine readable identifier of the error. Clients should branch on this rather
+    /// than on the status code or the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Cookie(_) => "invalid_cookie",
+            Error::Session(_) => "session_store_error",
+            Error::Pocket(PocketError::Unauthorized(_)) => "pocket_unauthorized",
+            Error::Pocket(PocketError::AccessDenied(_)) => "pocket_access_denied",
+            Error::Pocket(PocketError::RateLimited(_)) => "pocket_rate_limited",
+            Error::Pocket(PocketError::Unavailable(_)) => "pocket_unavailable",
+            Error::Pocket(PocketError::Other(_)) => "pocket_error",
+            Error::Jwt(_) => "invalid_token",
+            Error::Api(ApiError::BadRequest(_)) => "bad_request",
+            Error::Api(ApiError::InternalServerError(_)) => "internal_error",
+            Error::Api(ApiError::Unauthorized(_)) => "unauthorized",
+            Error::Api(ApiError::Forbidden(_)) => "forbidden",
+            Error::Api(ApiError::NotFound(_)) => "not_found",
+            Error::Api(ApiError::ReauthRequired(_)) => "reauth_required",
+            Error::Api(ApiError::TooManyRequests(_)) => "too_many_requests",
+            Error::Api(ApiError::ServiceUnavailable(_)) => "service_unavailable",
+            Error::Db(_) => "database_error",
+        }
+    }
+
+    /// Message safe to show to the client. Internal failures don't leak their details.
+    fn detail(&self) -> Option<String> {
+        match self {
+            Error::Cookie(detail)
+            | Error::Api(ApiError::BadRequest(detail))
+            | Error::Api(ApiError::Unauthorized(detail))
+            | Error::Api(ApiError::Forbidden(detail))
+            | Error::Api(ApiError::NotFound(detail))
+            | Error::Api(ApiError::ReauthRequired(detail))
+            | Error::Api(ApiError::ServiceUnavailable(detail)) => Some(detail.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Cookie(message)
+            | Error::Session(message)
+            | Error::Jwt(message)
+            | Error::Db(message)
+            | Error::Pocket(
+                PocketError::Unauthorized(message)
+                | PocketError::AccessDenied(message)
+                | PocketError::RateLimited(message)
+                | PocketError::Unavailable(message)
+                | PocketError::Other(message),
+            )
+            | Error::Api(
+                ApiError::BadRequest(message)
+                | ApiError::InternalServerError(message)
+                | ApiError::Unauthorized(message)
+                | ApiError::Forbidden(message)
+                | ApiError::NotFound(message)
+                | ApiError::ReauthRequired(message)
+                | ApiError::ServiceUnavailable(message),
+            ) => write!(f, "{}: {message}", self.code()),
+            Error::Api(ApiError::TooManyRequests(retry_after)) => {
+                write!(f, "{}: retry after {retry_after}s", self.code())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// RFC 7807 problem details
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub code: String,
+    pub request_id: Option<String>,
+}
+
+impl From<&Error> for ProblemDetails {
+    fn from(error: &Error) -> Self {
+        let status = error.status_code();
+        Self {
+            problem_type: format!("{PROBLEM_TYPE_BASE_URL}/{}", error.code()),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: error.detail(),
+            code: error.code().to_string(),
+            request_id: current_request_id(),
+        }
+    }
 }
 
 impl From<pockety::Error> for Error {
     fn from(error: pockety::Error) -> Self {
-        Error::Pocket(error.to_string())
+        Error::from_pocket_message(error.to_string())
     }
 }
 
@@ -48,20 +244,78 @@ impl From<biscuit::errors::Error> for Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        tracing::error!("{self:?}");
+        let problem = ProblemDetails::from(&self);
 
-        let (status, error_message) = match self {
-            Error::Cookie(_) | Error::Api(ApiError::BadRequest(_)) => {
-                (StatusCode::BAD_REQUEST, "Bad Request")
-            }
-            Error::Api(ApiError::Unauthorized(_)) => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error"),
+        if problem.status >= 500 {
+            tracing::error!("{self:?}");
+        } else {
+            tracing::debug!("{self:?}");
+        }
+
+        let mut response = (self.status_code(), Json(problem)).into_response();
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+        );
+        if let Error::Api(ApiError::TooManyRequests(retry_after)) = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classifies_pocket_errors_by_status() {
+        let classify = |message: &str| {
+            Error::Pocket(PocketError::from_message(message.to_string())).status_code()
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        assert_eq!(
+            classify("HTTP status client error (401 Unauthorized) for url (https://getpocket.com/v3/get)"),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            classify(
+                "HTTP status client error (403 Forbidden) for url (https://getpocket.com/v3/get)"
+            ),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            classify("HTTP status server error (503 Service Unavailable) for url (https://getpocket.com/v3/get)"),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            classify("Http(\"status: 429, body: limit exceeded\")"),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            classify("error decoding response body"),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            classify("error sending request for url (https://getpocket.com:443/v3/get): timed out after 500ms"),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[test]
+    fn hides_internal_details() {
+        let problem = ProblemDetails::from(&Error::Db("connection refused".to_string()));
+        assert_eq!(problem.status, 500);
+        assert_eq!(problem.code, "database_error");
+        assert!(problem.detail.is_none());
 
-        (status, body).into_response()
+        let problem = ProblemDetails::from(&Error::Api(ApiError::Forbidden(
+            "CSRF token doesn't match".to_string(),
+        )));
+        assert_eq!(problem.status, 403);
+        assert_eq!(problem.detail.as_deref(), Some("CSRF token doesn't match"));
     }
 }
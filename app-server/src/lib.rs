@@ -1,5 +1,6 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+use api::articles::GetArticlesResponse;
 use axum::{
     extract::FromRef,
     http::{HeaderMap, StatusCode},
@@ -8,17 +9,42 @@ use axum::{
 };
 use bb8_redis::RedisConnectionManager;
 use biscuit::{jwk::JWK, jws::Secret};
+use credentials::CredentialsKeys;
 use error::Error;
 use oauth::OAuthState;
 use pockety::{Pockety, RateLimits as PocketyRateLimits};
 use serde::{Deserialize, Serialize};
+use settings::Settings;
+use shutdown::Shutdown;
+use utoipa::ToSchema;
 
+pub mod annotations;
 pub mod api;
+pub mod collections;
+pub mod credentials;
+pub mod csrf;
 pub mod db;
+pub mod deprecation;
 pub mod domain;
 pub mod error;
+pub mod logging;
+pub mod metrics;
 pub mod oauth;
+pub mod quota;
+pub mod rate_limit;
+pub mod reading_time;
+pub mod request_id;
+pub mod scheduler;
 pub mod session;
+pub mod settings;
+pub mod shares;
+pub mod shutdown;
+pub mod stats;
+pub mod sync;
+pub mod teams;
+pub mod telemetry;
+pub mod user_settings;
+pub mod validation;
 
 pub static SESSION_ID_COOKIE_NAME: &str = "ID";
 
@@ -89,7 +115,7 @@ where
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RateLimits {
     pub user_limit: Option<u32>,
@@ -107,7 +133,8 @@ impl From<PocketyRateLimits> for RateLimits {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, ToSchema)]
+#[aliases(ArticlesWithRateLimits = WithRateLimits<GetArticlesResponse>)]
 #[serde(rename_all = "camelCase")]
 pub struct WithRateLimits<T> {
     pub rate_limits: RateLimits,
@@ -118,6 +145,32 @@ pub struct WithRateLimits<T> {
 pub struct Config {
     pub jws_signing_secret: Secret,
     pub jwe_encryption_key: JWK<OAuthState>,
+    pub pocket_credentials_keys: CredentialsKeys,
+    pub user_agent_url: String,
+    pub session_lifetime: Duration,
+    pub pocket_consumer_key: String,
+    pub redis_max_connections: u32,
+    pub readiness_check_pocket: bool,
+    pub metrics_token: Option<String>,
+}
+
+impl From<&Settings> for Config {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            jws_signing_secret: Secret::Bytes(settings.jws_signing_secret.as_bytes().to_vec()),
+            jwe_encryption_key: JWK::new_octet_key(
+                settings.jwe_encryption_key.as_bytes(),
+                Default::default(),
+            ),
+            pocket_credentials_keys: CredentialsKeys::new(&settings.pocket_credentials_keys),
+            user_agent_url: settings.user_agent_url.clone(),
+            session_lifetime: settings.session_lifetime,
+            pocket_consumer_key: settings.pocket_consumer_key.clone(),
+            redis_max_connections: settings.redis_max_connections,
+            readiness_check_pocket: settings.readiness_check_pocket,
+            metrics_token: settings.metrics_token.clone(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -126,6 +179,7 @@ pub struct AppState {
     pub session_store: Cache,
     pub db: Store,
     pub config: Config,
+    pub shutdown: Shutdown,
 }
 
 impl FromRef<AppState> for Pockety {
@@ -151,3 +205,9 @@ impl FromRef<AppState> for Store {
         state.db.clone()
     }
 }
+
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
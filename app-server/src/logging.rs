@@ -0,0 +1,218 @@
+use std::{borrow::Cow, fmt, io, str::FromStr};
+
+use once_cell::sync::Lazy;
+use opentelemetry_sdk::trace::Tracer;
+use regex::Regex;
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+};
+
+use crate::SESSION_ID_COOKIE_NAME;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per event, for log aggregation
+    Json,
+    /// Human readable, for local development
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "text" => Ok(LogFormat::Text),
+            _ => Err(format!("Expected `json` or `text`, got `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Json => write!(f, "json"),
+            LogFormat::Text => write!(f, "text"),
+        }
+    }
+}
+
+/// Installs the global subscriber. Everything written goes through `Redacting`, so secrets that
+/// end up in a log line by accident don't leave the process. Spans are also exported with
+/// `tracer` when one is given.
+pub fn init_logging<W>(filter: &str, format: LogFormat, writer: W, tracer: Option<Tracer>)
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let writer = Redacting(writer);
+    let (json_layer, text_layer) = match format {
+        LogFormat::Json => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_writer(writer),
+            ),
+            None,
+        ),
+        LogFormat::Text => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .compact()
+                    .with_writer(writer),
+            ),
+        ),
+    };
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(filter))
+        .with(json_layer)
+        .with(text_layer)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .init();
+}
+
+/// Values following these keys are masked, whether they're formatted by `Debug`, as JSON or as
+/// query parameters. Keys are matched whole and case insensitively, with or without separators, so
+/// e.g. `sync_state` isn't mistaken for the OAuth `state`.
+static SECRET_VALUES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\b((?:encrypted_)?access_?token|request_?token|csrf_?token|session_?id|state|cookie|authorization)\b(\\?"?\s*[:=]\s*\\?"?)([^"\\,;&\s})\]]+)"#,
+    )
+    .unwrap()
+});
+
+/// Compact JWS/JWE tokens, e.g. the encrypted OAuth state
+static COMPACT_TOKENS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"eyJ[\w-]*(?:\.[\w-]*){2,4}").unwrap());
+
+static SESSION_COOKIES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r#"\b{}=[^"\\;,\s]+"#,
+        regex::escape(SESSION_ID_COOKIE_NAME)
+    ))
+    .unwrap()
+});
+
+pub fn redact(line: &str) -> Cow<'_, str> {
+    let replacements = [
+        (&*SECRET_VALUES, format!("${{1}}${{2}}{REDACTED}")),
+        (&*COMPACT_TOKENS, REDACTED.to_string()),
+        (
+            &*SESSION_COOKIES,
+            format!("{SESSION_ID_COOKIE_NAME}={REDACTED}"),
+        ),
+    ];
+
+    let mut line = Cow::Borrowed(line);
+    for (regex, replacement) in replacements {
+        let redacted = match regex.replace_all(&line, replacement.as_str()) {
+            Cow::Owned(redacted) => Some(redacted),
+            Cow::Borrowed(_) => None,
+        };
+        if let Some(redacted) = redacted {
+            line = Cow::Owned(redacted);
+        }
+    }
+    line
+}
+
+/// Wraps a `MakeWriter`, masking secrets in every event before it's written.
+pub struct Redacting<M>(pub M);
+
+impl<'a, M> MakeWriter<'a> for Redacting<M>
+where
+    M: MakeWriter<'a>,
+{
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.0.make_writer(),
+            buffer: vec![],
+        }
+    }
+}
+
+/// Buffers a whole event, since a secret may be split across several writes, and redacts it on
+/// flush or drop.
+pub struct RedactingWriter<W: io::Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let event = String::from_utf8_lossy(&self.buffer);
+            self.inner.write_all(redact(&event).as_bytes())?;
+            self.buffer.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: io::Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redacts_debug_formatted_secrets() {
+        let line = r#"AuthzedSessionData { access_token: "5678defg-5678", username: "user" }"#;
+        assert_eq!(
+            redact(line),
+            r#"AuthzedSessionData { access_token: "<redacted>", username: "user" }"#
+        );
+    }
+
+    #[test]
+    fn redacts_secrets_in_json_logs() {
+        let line = r#"{"message":"session {\"accessToken\":\"abc\",\"csrfToken\":\"def\"}"}"#;
+        let redacted = redact(line);
+        assert!(!redacted.contains("abc"), "{redacted}");
+        assert!(!redacted.contains("def"), "{redacted}");
+    }
+
+    #[test]
+    fn redacts_cookies_and_tokens() {
+        let line =
+            "cookies: ID=c2Vzc2lvbg; theme=dark, uri: /login?state=eyJhbGciOiJkaXIifQ..iv.ct.tag";
+        let redacted = redact(line);
+        assert!(!redacted.contains("c2Vzc2lvbg"), "{redacted}");
+        assert!(!redacted.contains("eyJhbGciOiJkaXIifQ"), "{redacted}");
+        assert!(redacted.contains("theme=dark"), "{redacted}");
+    }
+
+    #[test]
+    fn leaves_other_lines_alone() {
+        let line = "fetched 30 articles for user alice";
+        assert!(matches!(redact(line), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn matches_whole_keys_only() {
+        let line = "sync_state=running, lease_state: held";
+        assert!(matches!(redact(line), Cow::Borrowed(_)), "{}", redact(line));
+
+        let redacted = redact("/login?code=1&state=opaque-state");
+        assert!(!redacted.contains("opaque-state"), "{redacted}");
+    }
+}
@@ -1,127 +1,587 @@
-use std::{env, net::SocketAddr, sync::Arc};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    process,
+    sync::Arc,
+};
 
 use app_server::{
     api::{
-        articles::{get_articles, simulate_sync_articles, sync_articles},
+        annotations::{
+            create_highlight, create_note, delete_highlight, delete_note, export_annotations,
+            get_highlights, get_notes, search, update_highlight, update_note,
+        },
+        articles::{
+            get_articles, get_articles_v1, get_reading_queue, get_reading_time,
+            simulate_sync_articles, sync_articles,
+        },
         auth::{get_access_token, get_request_token, get_session},
+        collections::{
+            add_collection_item, create_collection, get_collection, get_collections,
+            remove_collection, remove_collection_item, reorder_collection,
+            update_collection_details, update_collection_item_commentary,
+        },
+        health::{livez, readyz},
         health_check,
+        me::{disconnect_pocket, get_settings, get_stats, patch_settings},
+        openapi::get_openapi,
+        pocket::get_pocket_quota,
+        redirect::open_article,
+        shares::{create_share_link, get_share_links, revoke_share, view_share},
+        teams::{
+            add_team_collection_item, create_team, create_team_collection, create_team_invitation,
+            get_team, get_team_collection, get_team_collections, get_team_invitations, get_teams,
+            join_team, remove_team, remove_team_collection, remove_team_collection_item,
+            remove_team_invitation, remove_team_member, reorder_team_collection, update_team,
+            update_team_collection, update_team_collection_item, update_team_member,
+        },
+    },
+    credentials::CredentialsKeys,
+    csrf::{verify_csrf, CSRF_TOKEN_HEADER_NAME},
+    db::MIGRATOR,
+    deprecation::{
+        api_v1_deprecated_at, mark_deprecated, Deprecation, API_V1_PREFIX, API_V2_PREFIX,
+        DEPRECATION_HEADER_NAME, SUNSET_HEADER_NAME,
     },
-    oauth::OAuthState,
+    logging::init_logging,
+    metrics::{get_metrics, track_http_metrics},
+    rate_limit::{enforce_rate_limit, RateLimiter},
+    request_id::{make_request_span, propagate_request_id},
+    scheduler::AutoSync,
+    settings::Settings,
+    shutdown::{shutdown_signal, Shutdown},
+    telemetry::{init_tracer, shutdown_tracer},
     AppState, Config,
 };
 use axum::{
     http::{
-        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, ORIGIN},
-        Method,
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, LINK, ORIGIN},
+        HeaderValue, Method,
     },
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router, Server,
 };
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
-use biscuit::{jwk::JWK, jws::Secret};
 use dotenvy::dotenv;
 use pockety::Pockety;
-use sqlx::{migrate, postgres::PgPoolOptions};
+use sqlx::postgres::PgPoolOptions;
 use tower_http::{cors::CorsLayer, trace};
-use tracing::{debug, info, Level};
+use tracing::{debug, info, warn, Level};
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
-    tracing_subscriber::fmt()
-        .pretty()
-        .with_ansi(false)
-        .with_max_level(tracing::Level::DEBUG)
-        .with_env_filter("app_server=debug,pockety=debug")
-        .compact()
-        .init();
-
-    let jws_signing_secret = Secret::Bytes(
-        env::var("JWS_SIGNING_SECRET")
-            .expect("Missing JWS_SIGNING_SECRET")
-            .as_bytes()
-            .to_vec(),
-    );
-    let jwe_encryption_key: JWK<OAuthState> = JWK::new_octet_key(
-        env::var("JWE_ENCRYPTION_KEY")
-            .expect("Missing JWE_ENCRYPTION_KEY")
-            .as_bytes(),
-        Default::default(),
+    let settings = Settings::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+
+    let tracer = settings
+        .otel_exporter_otlp_endpoint
+        .as_ref()
+        .map(|endpoint| {
+            init_tracer(endpoint, &settings.otel_service_name).unwrap_or_else(|e| {
+                eprintln!("Failed to initialize trace exporter: {e}");
+                process::exit(1);
+            })
+        });
+    init_logging(
+        &settings.log_filter,
+        settings.log_format,
+        io::stdout,
+        tracer,
     );
 
-    let config = Config {
-        jws_signing_secret,
-        jwe_encryption_key,
-    };
+    debug!("Loaded settings: {settings:?}");
+
+    let config = Config::from(&settings);
 
-    let redis_url: String = env::var("REDIS_URL").expect("Missing REDIS_URL");
-    let redis_connection_manager =
-        RedisConnectionManager::new(redis_url).expect("Failed to build redis connection manager");
+    let redis_connection_manager = RedisConnectionManager::new(settings.redis_url.as_str())
+        .expect("Failed to build redis connection manager");
     let redis_connection_pool = Pool::builder()
+        .max_size(settings.redis_max_connections)
         .build(redis_connection_manager)
         .await
         .expect("Failed to build redis pool");
     debug!("Initialized Redis connection pool");
 
-    let postgres_url: String = env::var("DATABASE_URL").expect("Missing DATABASE_URL");
     let postgres_connection_pool = PgPoolOptions::new()
-        .connect_lazy(&postgres_url)
+        .max_connections(settings.database_max_connections)
+        .connect_lazy(&settings.database_url)
         .expect("Failed to build postgres connection pool");
     debug!("Initialized Postgres connection pool");
 
-    migrate!("./migrations")
-        .run(&postgres_connection_pool)
-        .await
-        .expect("Failed to migrate database");
-    debug!("Migrated Postgres database");
+    if settings.run_migrations_on_startup {
+        MIGRATOR
+            .run(&postgres_connection_pool)
+            .await
+            .expect("Failed to migrate database");
+        debug!("Migrated Postgres database");
+    }
 
-    let user_agent_url = env::var("USER_AGENT_URL").expect("Missing USER_AGENT_URL");
+    let allowed_origins = std::iter::once(&settings.user_agent_url)
+        .chain(&settings.cors_allowed_origins)
+        .map(|origin| {
+            origin
+                .trim_end_matches('/')
+                .parse()
+                .expect("Invalid CORS origin")
+        })
+        .collect::<Vec<HeaderValue>>();
     let cors_layer = CorsLayer::new()
-        .allow_origin([
-            user_agent_url.parse().unwrap(),
-            "https://getpocket.com".parse().unwrap(),
+        .allow_origin(allowed_origins)
+        .allow_headers([
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            ACCEPT,
+            ORIGIN,
+            CSRF_TOKEN_HEADER_NAME.clone(),
+        ])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .expose_headers([
+            DEPRECATION_HEADER_NAME.clone(),
+            SUNSET_HEADER_NAME.clone(),
+            LINK,
         ])
-        .allow_headers([CONTENT_TYPE, AUTHORIZATION, ACCEPT, ORIGIN])
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
         .allow_credentials(true);
 
-    let pocket_consumer_key = env::var("POCKET_CONSUMER_KEY").expect("Missing POCKET_CONSUMER_KEY");
-    let pocket_redirect_uri = env::var("POCKET_REDIRECT_URI").expect("Missing POCKET_REDIRECT_URI");
     debug!("Initializing Pockety instance.");
 
-    let pockety = Pockety::new(pocket_consumer_key, pocket_redirect_uri.as_str())
-        .expect("Failed to initialize Pockety instance.");
+    let pockety = Pockety::new(
+        settings.pocket_consumer_key.clone(),
+        settings.pocket_redirect_uri.as_str(),
+    )
+    .expect("Failed to initialize Pockety instance.");
+
+    let session_store = Arc::new(redis_connection_pool);
+
+    let trusted_proxies: Arc<[IpAddr]> = settings.trusted_proxies.clone().into();
+    let rate_limiter = |scope, limit| {
+        RateLimiter::new(scope, limit, session_store.clone(), trusted_proxies.clone())
+    };
+    let auth_rate_limiter = rate_limiter("auth", settings.rate_limit_auth);
+    let api_rate_limiter = rate_limiter("api", settings.rate_limit_api);
+    let sync_rate_limiter = rate_limiter("sync", settings.rate_limit_sync);
+
+    let db = Arc::new(postgres_connection_pool);
+    let shutdown = Shutdown::new();
+
+    let auto_sync = settings.auto_sync_enabled.then(|| {
+        AutoSync {
+            pockety: pockety.clone(),
+            store: db.clone(),
+            cache: session_store.clone(),
+            shutdown: shutdown.clone(),
+            credentials_keys: CredentialsKeys::new(&settings.pocket_credentials_keys),
+            interval: settings.auto_sync_interval,
+            jitter: settings.auto_sync_jitter,
+        }
+        .spawn()
+    });
 
     let app_state = AppState {
         pockety,
-        session_store: Arc::new(redis_connection_pool),
-        db: Arc::new(postgres_connection_pool),
+        session_store,
+        db: db.clone(),
         config,
+        shutdown: shutdown.clone(),
     };
 
+    // Routes whose shape is the same in every version of the api
+    let common = Router::new()
+        .route(
+            "/articles/sync",
+            get(sync_articles).layer(middleware::from_fn_with_state(
+                sync_rate_limiter.clone(),
+                enforce_rate_limit,
+            )),
+        )
+        .route(
+            "/articles/simulated-sync",
+            get(simulate_sync_articles).layer(middleware::from_fn_with_state(
+                sync_rate_limiter,
+                enforce_rate_limit,
+            )),
+        )
+        .route(
+            "/auth/authn",
+            post(get_request_token).layer(middleware::from_fn_with_state(
+                auth_rate_limiter.clone(),
+                enforce_rate_limit,
+            )),
+        )
+        .route(
+            "/auth/authz",
+            post(get_access_token).layer(middleware::from_fn_with_state(
+                auth_rate_limiter,
+                enforce_rate_limit,
+            )),
+        )
+        .route(
+            "/auth/session",
+            get(get_session).layer(middleware::from_fn_with_state(
+                api_rate_limiter.clone(),
+                enforce_rate_limit,
+            )),
+        )
+        .route(
+            "/pocket/quota",
+            get(get_pocket_quota).layer(middleware::from_fn_with_state(
+                api_rate_limiter.clone(),
+                enforce_rate_limit,
+            )),
+        );
+
+    // Links to articles, opened by the browser rather than called by clients, so unversioned
+    let redirects = Router::new().route(
+        "/r/:item_id",
+        get(open_article).layer(middleware::from_fn_with_state(
+            api_rate_limiter.clone(),
+            enforce_rate_limit,
+        )),
+    );
+    // Public pages of share links, opened by anyone who has one
+    let shares = Router::new().route(
+        "/s/:token",
+        get(view_share).layer(middleware::from_fn_with_state(
+            api_rate_limiter.clone(),
+            enforce_rate_limit,
+        )),
+    );
+
+    // Routes added since `/v2` only exist there, `/v1` is frozen
+    let v2 =
+        common
+            .clone()
+            .route(
+                "/articles",
+                get(get_articles).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/articles/reading-time",
+                get(get_reading_time).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/articles/reading-queue",
+                get(get_reading_queue).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/articles/search",
+                get(search).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/articles/:item_id/notes",
+                get(get_notes)
+                    .post(create_note)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/articles/:item_id/notes/:note_id",
+                patch(update_note)
+                    .delete(delete_note)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/articles/:item_id/highlights",
+                get(get_highlights)
+                    .post(create_highlight)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/articles/:item_id/highlights/:highlight_id",
+                patch(update_highlight).delete(delete_highlight).layer(
+                    middleware::from_fn_with_state(api_rate_limiter.clone(), enforce_rate_limit),
+                ),
+            )
+            .route(
+                "/articles/:item_id/annotations.md",
+                get(export_annotations).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/collections",
+                get(get_collections)
+                    .post(create_collection)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/collections/:collection_id",
+                get(get_collection)
+                    .patch(update_collection_details)
+                    .delete(remove_collection)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/collections/:collection_id/items",
+                post(add_collection_item).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/collections/:collection_id/items/:item_id",
+                patch(update_collection_item_commentary)
+                    .delete(remove_collection_item)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/collections/:collection_id/order",
+                put(reorder_collection).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/shares",
+                get(get_share_links)
+                    .post(create_share_link)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/shares/:share_id",
+                delete(revoke_share).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/teams",
+                get(get_teams)
+                    .post(create_team)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/teams/join",
+                post(join_team).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/teams/:team_id",
+                get(get_team).patch(update_team).delete(remove_team).layer(
+                    middleware::from_fn_with_state(api_rate_limiter.clone(), enforce_rate_limit),
+                ),
+            )
+            .route(
+                "/teams/:team_id/invitations",
+                get(get_team_invitations)
+                    .post(create_team_invitation)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/teams/:team_id/invitations/:invitation_id",
+                delete(remove_team_invitation).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/teams/:team_id/members/:username",
+                patch(update_team_member).delete(remove_team_member).layer(
+                    middleware::from_fn_with_state(api_rate_limiter.clone(), enforce_rate_limit),
+                ),
+            )
+            .route(
+                "/teams/:team_id/collections",
+                get(get_team_collections)
+                    .post(create_team_collection)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/teams/:team_id/collections/:collection_id",
+                get(get_team_collection)
+                    .patch(update_team_collection)
+                    .delete(remove_team_collection)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/teams/:team_id/collections/:collection_id/items",
+                post(add_team_collection_item).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/teams/:team_id/collections/:collection_id/items/:item_id",
+                patch(update_team_collection_item)
+                    .delete(remove_team_collection_item)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/teams/:team_id/collections/:collection_id/order",
+                put(reorder_team_collection).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/me/settings",
+                get(get_settings)
+                    .patch(patch_settings)
+                    .layer(middleware::from_fn_with_state(
+                        api_rate_limiter.clone(),
+                        enforce_rate_limit,
+                    )),
+            )
+            .route(
+                "/me/stats",
+                get(get_stats).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            )
+            .route(
+                "/me/pocket",
+                delete(disconnect_pocket).layer(middleware::from_fn_with_state(
+                    api_rate_limiter.clone(),
+                    enforce_rate_limit,
+                )),
+            );
+    // Kept, along with its unversioned aliases, until clients have moved to `/v2`
+    let v1 = common.route(
+        "/articles",
+        get(get_articles_v1).layer(middleware::from_fn_with_state(
+            api_rate_limiter,
+            enforce_rate_limit,
+        )),
+    );
+    let v1_deprecation = Deprecation::new(api_v1_deprecated_at()).sunset(settings.api_v1_sunset);
+
     let app = Router::new()
         .route("/health-check", get(health_check))
-        .route("/articles", get(get_articles))
-        .route("/articles/sync", get(sync_articles))
-        .route("/articles/simulated-sync", get(simulate_sync_articles))
-        .route("/auth/authn", post(get_request_token))
-        .route("/auth/authz", post(get_access_token))
-        .route("/auth/session", get(get_session))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        .merge(redirects)
+        .merge(shares)
+        .nest(API_V2_PREFIX, v2)
+        .nest(
+            API_V1_PREFIX,
+            v1.clone().route_layer(middleware::from_fn_with_state(
+                v1_deprecation
+                    .clone()
+                    .successor(API_V1_PREFIX, API_V2_PREFIX),
+                mark_deprecated,
+            )),
+        )
+        .merge(v1.route_layer(middleware::from_fn_with_state(
+            v1_deprecation.successor("", API_V2_PREFIX),
+            mark_deprecated,
+        )))
+        .route("/metrics", get(get_metrics))
+        .route("/openapi.json", get(get_openapi))
+        .route_layer(middleware::from_fn(track_http_metrics))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            verify_csrf,
+        ))
         .layer(cors_layer)
         .layer(
             trace::TraceLayer::new_for_http()
-                .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
+                .make_span_with(make_request_span)
                 .on_response(trace::DefaultOnResponse::new().level(Level::INFO)),
         )
+        .layer(middleware::from_fn(propagate_request_id))
         .with_state(app_state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+    let addr = settings.socket_addr();
     info!("Listening on {addr}");
 
-    Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .expect("failed to launch server");
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            info!("Received shutdown signal, draining in-flight requests");
+            shutdown.begin_drain();
+        }
+    });
+
+    let server = Server::bind(&addr)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown.draining());
+    // Scheduled syncs are drained along with the requests
+    let server = async {
+        let result = server.await;
+        if let Some(auto_sync) = auto_sync {
+            let _ = auto_sync.await;
+        }
+        result
+    };
+    let drain_timeout = async {
+        shutdown.draining().await;
+        tokio::time::sleep(settings.shutdown_drain_timeout).await;
+    };
+
+    tokio::select! {
+        result = server => result.expect("failed to launch server"),
+        _ = drain_timeout => warn!(
+            "Drain timeout elapsed with {} sync(s) in flight, closing remaining connections",
+            shutdown.in_flight_syncs()
+        ),
+    }
+
+    // The redis pool is closed once the last handle to it, owned by the server, is dropped
+    db.close().await;
+    shutdown_tracer().await;
+    info!("Closed connection pools, shut down");
 }
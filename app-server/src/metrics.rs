@@ -0,0 +1,326 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Request,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, IntGaugeVec,
+    Registry, TextEncoder,
+};
+
+use crate::{
+    csrf::constant_time_eq,
+    error::{ApiError, Error},
+    Cache, Store,
+};
+
+/// Process wide metrics, recorded from wherever the measured work happens and served by
+/// `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    pocket_calls_total: IntCounterVec,
+    pocket_errors_total: IntCounterVec,
+    pocket_rate_limit_remaining: IntGaugeVec,
+    sync_duration_seconds: HistogramVec,
+    sync_items_total: IntCounterVec,
+    upsert_failures_total: IntCounterVec,
+    pool_connections: IntGaugeVec,
+}
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("just_links".to_string()), None)
+            .expect("Invalid metrics prefix");
+
+        let http_requests_total = IntCounterVec::new(
+            opts!("http_requests_total", "Handled http requests"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration_seconds = HistogramVec::new(
+            histogram_opts!(
+                "http_request_duration_seconds",
+                "Time to respond to http requests"
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let pocket_calls_total = IntCounterVec::new(
+            opts!("pocket_calls_total", "Calls made to the Pocket api"),
+            &["endpoint"],
+        )
+        .unwrap();
+        let pocket_errors_total = IntCounterVec::new(
+            opts!("pocket_errors_total", "Failed calls to the Pocket api"),
+            &["endpoint", "code"],
+        )
+        .unwrap();
+        let pocket_rate_limit_remaining = IntGaugeVec::new(
+            opts!(
+                "pocket_rate_limit_remaining",
+                "Calls left in the current Pocket rate limit window of the consumer key"
+            ),
+            &["scope"],
+        )
+        .unwrap();
+        let sync_duration_seconds = HistogramVec::new(
+            histogram_opts!(
+                "sync_duration_seconds",
+                "Time taken by article syncs",
+                exponential_buckets(0.5, 2.0, 10).unwrap()
+            ),
+            &["trigger"],
+        )
+        .unwrap();
+        let sync_items_total = IntCounterVec::new(
+            opts!("sync_items_total", "Articles processed by syncs"),
+            &["outcome"],
+        )
+        .unwrap();
+        let upsert_failures_total = IntCounterVec::new(
+            opts!(
+                "upsert_failures_total",
+                "Failed upserts of articles and their attachments"
+            ),
+            &["kind"],
+        )
+        .unwrap();
+        let pool_connections = IntGaugeVec::new(
+            opts!(
+                "pool_connections",
+                "Connections of the Postgres and Redis pools"
+            ),
+            &["pool", "state"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(http_requests_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration_seconds.clone()),
+            Box::new(pocket_calls_total.clone()),
+            Box::new(pocket_errors_total.clone()),
+            Box::new(pocket_rate_limit_remaining.clone()),
+            Box::new(sync_duration_seconds.clone()),
+            Box::new(sync_items_total.clone()),
+            Box::new(upsert_failures_total.clone()),
+            Box::new(pool_connections.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Failed to register metric");
+        }
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            pocket_calls_total,
+            pocket_errors_total,
+            pocket_rate_limit_remaining,
+            sync_duration_seconds,
+            sync_items_total,
+            upsert_failures_total,
+            pool_connections,
+        }
+    }
+
+    /// Counts a call to Pocket's `endpoint`, classifying failures the same way api errors are.
+    pub fn record_pocket_call<T>(&self, endpoint: &str, result: &Result<T, pockety::Error>) {
+        self.pocket_calls_total.with_label_values(&[endpoint]).inc();
+        if let Err(e) = result {
+            let code = Error::from_pocket_message(e.to_string()).code();
+            self.pocket_errors_total
+                .with_label_values(&[endpoint, code])
+                .inc();
+        }
+    }
+
+    pub fn set_pocket_rate_limit_remaining(&self, scope: &str, remaining: u32) {
+        self.pocket_rate_limit_remaining
+            .with_label_values(&[scope])
+            .set(remaining as i64);
+    }
+
+    /// Starts timing a sync, which is observed once the returned timer is dropped.
+    pub fn start_sync(&self, trigger: &'static str) -> SyncTimer {
+        SyncTimer {
+            trigger,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn record_sync_item(&self, stored: bool) {
+        let outcome = if stored { "stored" } else { "failed" };
+        self.sync_items_total.with_label_values(&[outcome]).inc();
+    }
+
+    /// `kind` is one of `article`, `image`, `video` or `author`.
+    pub fn record_upsert_failure(&self, kind: &str) {
+        self.upsert_failures_total.with_label_values(&[kind]).inc();
+    }
+
+    fn record_pool(&self, pool: &str, connections: u32, idle: u32, max: u32) {
+        for (state, value) in [
+            ("open", connections),
+            ("idle", idle),
+            ("in_use", connections.saturating_sub(idle)),
+            ("max", max),
+        ] {
+            self.pool_connections
+                .with_label_values(&[pool, state])
+                .set(value as i64);
+        }
+    }
+
+    fn encode(&self) -> Result<String, Error> {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| Error::Api(ApiError::InternalServerError(e.to_string())))?;
+        String::from_utf8(buffer)
+            .map_err(|e| Error::Api(ApiError::InternalServerError(e.to_string())))
+    }
+}
+
+pub struct SyncTimer {
+    trigger: &'static str,
+    start: Instant,
+}
+
+impl Drop for SyncTimer {
+    fn drop(&mut self) {
+        metrics()
+            .sync_duration_seconds
+            .with_label_values(&[self.trigger])
+            .observe(self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Meant to be attached with `Router::route_layer`, so that only matched routes are recorded and
+/// the route label stays bounded.
+pub async fn track_http_metrics<B>(request: Request<B>, next: Next<B>) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    let metrics = metrics();
+    metrics.http_requests_total.with_label_values(&labels).inc();
+    metrics
+        .http_request_duration_seconds
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+
+    response
+}
+
+/// Whether the request carries `Authorization: Bearer <metrics_token>`.
+fn is_authorized_scrape(headers: &HeaderMap, metrics_token: &str) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), metrics_token.as_bytes()))
+}
+
+/// Serves every metric in the Prometheus text format to scrapers holding the metrics token, and
+/// isn't served at all without one. Pool usage is sampled on each scrape.
+pub async fn get_metrics(
+    State(store): State<Store>,
+    State(cache): State<Cache>,
+    State(config): State<crate::Config>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let Some(metrics_token) = config.metrics_token.as_deref() else {
+        return Err(Error::Api(ApiError::NotFound("Not found".to_string())));
+    };
+    if !is_authorized_scrape(&headers, metrics_token) {
+        return Err(Error::Api(ApiError::Unauthorized(
+            "Missing or invalid metrics token".to_string(),
+        )));
+    }
+
+    let metrics = metrics();
+
+    metrics.record_pool(
+        "postgres",
+        store.size(),
+        store.num_idle() as u32,
+        store.options().get_max_connections(),
+    );
+    let redis = cache.state();
+    metrics.record_pool(
+        "redis",
+        redis.connections,
+        redis.idle_connections,
+        config.redis_max_connections,
+    );
+
+    let mut response = metrics.encode()?.into_response();
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_recorded_metrics() {
+        let metrics = metrics();
+        metrics.record_sync_item(false);
+        metrics.record_upsert_failure("image");
+        metrics.set_pocket_rate_limit_remaining("consumer_key", 9_999);
+        drop(metrics.start_sync("manual"));
+
+        let encoded = metrics.encode().unwrap();
+        assert!(encoded.contains(r#"just_links_sync_items_total{outcome="failed"}"#));
+        assert!(encoded.contains(r#"just_links_upsert_failures_total{kind="image"}"#));
+        assert!(encoded
+            .contains(r#"just_links_pocket_rate_limit_remaining{scope="consumer_key"} 9999"#));
+        assert!(encoded.contains("just_links_sync_duration_seconds_count"));
+    }
+
+    #[test]
+    fn requires_metrics_token() {
+        let token = "0123456789abcdef0123456789abcdef";
+        let headers = |authorization: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+            headers
+        };
+
+        assert!(is_authorized_scrape(
+            &headers(&format!("Bearer {token}")),
+            token
+        ));
+        assert!(!is_authorized_scrape(&headers("Bearer wrong"), token));
+        assert!(!is_authorized_scrape(&headers(token), token));
+        assert!(!is_authorized_scrape(&HeaderMap::new(), token));
+    }
+}
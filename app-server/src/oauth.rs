@@ -154,7 +154,7 @@ impl Jwt {
         }
     }
 
-    fn generated_nonce() -> Vec<u8> {
+    pub(crate) fn generated_nonce() -> Vec<u8> {
         let mut nonce = vec![0u8; 96 / 8];
         thread_rng().fill_bytes(&mut nonce);
         nonce
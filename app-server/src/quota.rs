@@ -0,0 +1,352 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::TryFutureExt;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::{error, Instrument};
+use utoipa::ToSchema;
+
+use crate::{
+    error::{ApiError, Error, PocketError},
+    metrics::metrics,
+    telemetry::redis_span,
+    Cache, RateLimits,
+};
+
+const USER_QUOTA_KEY_PREFIX: &str = "pocket_quota:user";
+const CONSUMER_KEY_QUOTA_KEY_PREFIX: &str = "pocket_quota:key";
+
+/// Pocket allows 10,000 calls per hour for a consumer key, shared by every user of the app.
+pub const CONSUMER_KEY_LIMIT: u32 = 10_000;
+const CONSUMER_KEY_WINDOW_SECS: i64 = 60 * 60;
+
+/// Share of a quota that background work (syncs, bulk jobs) leaves untouched, so that
+/// interactive requests keep working while a sync is running.
+const BACKGROUND_RESERVE_RATIO: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaPriority {
+    Interactive,
+    Background,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaWindow {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp in seconds at which the quota is refilled
+    pub reset_at: i64,
+}
+
+impl QuotaWindow {
+    fn from_user_rate_limits(rate_limits: RateLimits, now: i64) -> Option<Self> {
+        Some(Self {
+            limit: rate_limits.user_limit?,
+            remaining: rate_limits.user_remaining?,
+            reset_at: now + rate_limits.user_reset? as i64,
+        })
+    }
+
+    fn allows(&self, priority: QuotaPriority, now: i64) -> bool {
+        if self.reset_at <= now {
+            return true;
+        }
+
+        let reserve = match priority {
+            QuotaPriority::Interactive => 0,
+            QuotaPriority::Background => {
+                (self.limit as f64 * BACKGROUND_RESERVE_RATIO).ceil() as u32
+            }
+        };
+        self.remaining > reserve
+    }
+
+    fn retry_after(&self, now: i64) -> u64 {
+        (self.reset_at - now).max(1) as u64
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PocketQuota {
+    /// Last known quota of the user, as reported by Pocket. `None` until the first call is made.
+    pub user: Option<QuotaWindow>,
+    /// Quota of the consumer key, counted locally over fixed hourly windows.
+    pub consumer_key: QuotaWindow,
+}
+
+impl PocketQuota {
+    /// Refuses the call if either quota would be exceeded, returning the seconds until it resets.
+    pub fn check(&self, priority: QuotaPriority, now: i64) -> Result<(), Error> {
+        for window in self.user.iter().chain([&self.consumer_key]) {
+            if !window.allows(priority, now) {
+                return Err(Error::Api(ApiError::TooManyRequests(
+                    window.retry_after(now),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pocket answers an exhausted rate limit with a 403 or a 429 that carry no retry time. While
+    /// a known quota is used up, such a failure is turned into a `TooManyRequests` with the
+    /// seconds until that quota resets. A 403 otherwise means access was denied and is kept.
+    pub fn classify_failure(&self, error: Error, now: i64) -> Error {
+        if !matches!(
+            error,
+            Error::Pocket(PocketError::AccessDenied(_) | PocketError::RateLimited(_))
+        ) {
+            return error;
+        }
+
+        self.user
+            .iter()
+            .chain([&self.consumer_key])
+            .find(|window| window.reset_at > now && window.remaining == 0)
+            .map(|window| Error::Api(ApiError::TooManyRequests(window.retry_after(now))))
+            .unwrap_or(error)
+    }
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn user_quota_key(username: &str) -> String {
+    format!("{USER_QUOTA_KEY_PREFIX}:{username}")
+}
+
+fn consumer_key_quota_key(now: i64) -> (String, i64) {
+    let window = now / CONSUMER_KEY_WINDOW_SECS;
+    (
+        format!("{CONSUMER_KEY_QUOTA_KEY_PREFIX}:{window}"),
+        (window + 1) * CONSUMER_KEY_WINDOW_SECS,
+    )
+}
+
+#[async_trait]
+pub trait PocketQuotaStore {
+    /// Records a call made to Pocket, whether or not it succeeded. `rate_limits` should be passed
+    /// along whenever the response carried them, so the user's quota stays in sync with what
+    /// Pocket reports.
+    async fn record_pocket_call(
+        &self,
+        username: Option<&str>,
+        rate_limits: Option<RateLimits>,
+    ) -> Result<(), Error>;
+
+    /// Records a failed call to Pocket and classifies its error against the stored quota, see
+    /// `PocketQuota::classify_failure`.
+    async fn record_failed_pocket_call(&self, username: Option<&str>, error: Error) -> Error;
+
+    async fn fetch_pocket_quota(&self, username: Option<&str>) -> Result<PocketQuota, Error>;
+
+    /// Fails with `ApiError::TooManyRequests` when a call with the given priority should not be
+    /// made right now. Lookup failures let the call through.
+    async fn check_pocket_quota(
+        &self,
+        username: Option<&str>,
+        priority: QuotaPriority,
+    ) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl PocketQuotaStore for Cache {
+    async fn record_pocket_call(
+        &self,
+        username: Option<&str>,
+        rate_limits: Option<RateLimits>,
+    ) -> Result<(), Error> {
+        let now = unix_now();
+        let mut con = self
+            .get()
+            .map_err(|e| {
+                error!("Failed to establish redis connection. Error: {e:?}");
+                Error::Session("Connection error".to_string())
+            })
+            .await?;
+
+        let (key, reset_at) = consumer_key_quota_key(now);
+        let (used,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire_at(&key, reset_at as usize)
+            .ignore()
+            .query_async(&mut *con)
+            .instrument(redis_span("MULTI"))
+            .map_err(|e| {
+                error!("Failed to record consumer key quota. Error: {e:?}");
+                Error::Session("Failed to record consumer key quota".to_string())
+            })
+            .await?;
+        metrics().set_pocket_rate_limit_remaining(
+            "consumer_key",
+            CONSUMER_KEY_LIMIT.saturating_sub(used),
+        );
+
+        let user_window = username
+            .zip(rate_limits.and_then(|limits| QuotaWindow::from_user_rate_limits(limits, now)));
+        if let Some((username, window)) = user_window {
+            let ttl = (window.reset_at - now).max(1) as usize;
+            con.set_ex::<_, _, ()>(
+                user_quota_key(username),
+                serde_json::to_string(&window)?,
+                ttl,
+            )
+            .instrument(redis_span("SETEX"))
+            .map_err(|e| {
+                error!("Failed to record quota for user {username}. Error: {e:?}");
+                Error::Session("Failed to record user quota".to_string())
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_failed_pocket_call(&self, username: Option<&str>, error: Error) -> Error {
+        let _ = self.record_pocket_call(username, None).await;
+        match self.fetch_pocket_quota(username).await {
+            Ok(quota) => quota.classify_failure(error, unix_now()),
+            Err(_) => error,
+        }
+    }
+
+    async fn fetch_pocket_quota(&self, username: Option<&str>) -> Result<PocketQuota, Error> {
+        let now = unix_now();
+        let mut con = self
+            .get()
+            .map_err(|e| {
+                error!("Failed to establish redis connection. Error: {e:?}");
+                Error::Session("Connection error".to_string())
+            })
+            .await?;
+
+        let (key, reset_at) = consumer_key_quota_key(now);
+        let used: Option<u32> = con
+            .get(&key)
+            .instrument(redis_span("GET"))
+            .map_err(|e| {
+                error!("Failed to fetch consumer key quota. Error: {e:?}");
+                Error::Session("Failed to fetch consumer key quota".to_string())
+            })
+            .await?;
+        let consumer_key = QuotaWindow {
+            limit: CONSUMER_KEY_LIMIT,
+            remaining: CONSUMER_KEY_LIMIT.saturating_sub(used.unwrap_or(0)),
+            reset_at,
+        };
+
+        let user = match username {
+            Some(username) => {
+                let window: Option<String> = con
+                    .get(user_quota_key(username))
+                    .instrument(redis_span("GET"))
+                    .map_err(|e| {
+                        error!("Failed to fetch quota for user {username}. Error: {e:?}");
+                        Error::Session("Failed to fetch user quota".to_string())
+                    })
+                    .await?;
+                window
+                    .map(|window| serde_json::from_str::<QuotaWindow>(&window))
+                    .transpose()?
+            }
+            None => None,
+        };
+
+        Ok(PocketQuota { user, consumer_key })
+    }
+
+    async fn check_pocket_quota(
+        &self,
+        username: Option<&str>,
+        priority: QuotaPriority,
+    ) -> Result<(), Error> {
+        match self.fetch_pocket_quota(username).await {
+            Ok(quota) => quota.check(priority, unix_now()),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn window(limit: u32, remaining: u32) -> QuotaWindow {
+        QuotaWindow {
+            limit,
+            remaining,
+            reset_at: NOW + 60,
+        }
+    }
+
+    #[test]
+    fn background_work_leaves_a_reserve() {
+        let quota = PocketQuota {
+            user: Some(window(320, 20)),
+            consumer_key: window(CONSUMER_KEY_LIMIT, CONSUMER_KEY_LIMIT),
+        };
+        assert!(quota.check(QuotaPriority::Interactive, NOW).is_ok());
+        assert!(matches!(
+            quota.check(QuotaPriority::Background, NOW),
+            Err(Error::Api(ApiError::TooManyRequests(60)))
+        ));
+    }
+
+    #[test]
+    fn exhausted_consumer_key_refuses_every_call() {
+        let quota = PocketQuota {
+            user: None,
+            consumer_key: window(CONSUMER_KEY_LIMIT, 0),
+        };
+        assert!(quota.check(QuotaPriority::Interactive, NOW).is_err());
+    }
+
+    #[test]
+    fn quota_is_refilled_after_reset() {
+        let quota = PocketQuota {
+            user: Some(window(320, 0)),
+            consumer_key: window(CONSUMER_KEY_LIMIT, CONSUMER_KEY_LIMIT),
+        };
+        assert!(quota.check(QuotaPriority::Background, NOW + 60).is_ok());
+    }
+
+    #[test]
+    fn forbidden_calls_are_rate_limited_only_while_a_quota_is_used_up() {
+        let forbidden = || {
+            Error::from_pocket_message(
+                "HTTP status client error (403 Forbidden) for url (https://getpocket.com/v3/get)"
+                    .to_string(),
+            )
+        };
+        let exhausted = PocketQuota {
+            user: Some(window(320, 0)),
+            consumer_key: window(CONSUMER_KEY_LIMIT, CONSUMER_KEY_LIMIT),
+        };
+        assert!(matches!(
+            exhausted.classify_failure(forbidden(), NOW),
+            Error::Api(ApiError::TooManyRequests(60))
+        ));
+        assert!(matches!(
+            exhausted.classify_failure(forbidden(), NOW + 60),
+            Error::Pocket(PocketError::AccessDenied(_))
+        ));
+
+        let available = PocketQuota {
+            user: Some(window(320, 200)),
+            consumer_key: window(CONSUMER_KEY_LIMIT, CONSUMER_KEY_LIMIT),
+        };
+        assert!(matches!(
+            available.classify_failure(forbidden(), NOW),
+            Error::Pocket(PocketError::AccessDenied(_))
+        ));
+    }
+}
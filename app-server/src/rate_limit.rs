@@ -0,0 +1,286 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRef, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use futures::TryFutureExt;
+use redis::AsyncCommands;
+use tracing::Instrument;
+
+use crate::{
+    error::{ApiError, Error},
+    session::{AuthzedSessionData, ConPool},
+    telemetry::redis_span,
+    Cache,
+};
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit";
+
+/// Allows `max_requests` requests per sliding `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+        }
+    }
+}
+
+/// Parses limits written as `<max_requests>/<window_in_seconds>`, e.g. `10/60`.
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (max_requests, window) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Expected `<max_requests>/<window_in_seconds>`, got `{s}`"))?;
+        let max_requests = max_requests
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid max_requests in `{s}`: {e}"))?;
+        let window: u64 = window
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid window in `{s}`: {e}"))?;
+
+        if window == 0 {
+            return Err(format!("Window must be greater than zero in `{s}`"));
+        }
+
+        Ok(Self::new(max_requests, Duration::from_secs(window)))
+    }
+}
+
+/// Per-route rate limiter backed by a sliding window log in redis.
+///
+/// Requests carrying a valid session are counted per user, everything else is counted per client
+/// ip.
+#[derive(Clone)]
+pub struct RateLimiter {
+    pub scope: &'static str,
+    pub limit: RateLimit,
+    pub cache: Cache,
+    /// Proxies whose `x-forwarded-for` hops are believed
+    pub trusted_proxies: Arc<[IpAddr]>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        scope: &'static str,
+        limit: RateLimit,
+        cache: Cache,
+        trusted_proxies: Arc<[IpAddr]>,
+    ) -> Self {
+        Self {
+            scope,
+            limit,
+            cache,
+            trusted_proxies,
+        }
+    }
+
+    /// Records a hit for `subject` and returns the number of seconds the caller has to wait when
+    /// the limit is exceeded.
+    async fn hit(&self, subject: &str) -> Result<Option<u64>, Error> {
+        const LOG_TAG: &str = "[RateLimiter::hit]";
+
+        let key = format!("{RATE_LIMIT_KEY_PREFIX}:{}:{subject}", self.scope);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let window = self.limit.window.as_millis() as u64;
+        let member = format!("{now}-{}", nanoid::nanoid!(8));
+
+        let mut con = self
+            .cache
+            .get()
+            .map_err(|e| {
+                tracing::error!("{LOG_TAG} Failed to establish redis connection. Error: {e:?}");
+                Error::Session("Connection error".to_string())
+            })
+            .await?;
+
+        let (count, oldest): (u32, Vec<(String, f64)>) = redis::pipe()
+            .atomic()
+            .zrembyscore(&key, 0, now.saturating_sub(window))
+            .ignore()
+            .zadd(&key, &member, now)
+            .ignore()
+            .zcard(&key)
+            .zrange_withscores(&key, 0, 0)
+            .pexpire(&key, window as usize)
+            .ignore()
+            .query_async(&mut *con)
+            .instrument(redis_span("MULTI"))
+            .map_err(|e| {
+                tracing::error!("{LOG_TAG} Failed to record hit for {key}. Error: {e:?}");
+                Error::Session("Failed to record rate limit hit".to_string())
+            })
+            .await?;
+
+        if count <= self.limit.max_requests {
+            return Ok(None);
+        }
+
+        // Rejected requests shouldn't keep the window alive
+        let _: Result<(), _> = con.zrem(&key, &member).instrument(redis_span("ZREM")).await;
+
+        let oldest = oldest
+            .first()
+            .map(|(_, score)| *score as u64)
+            .unwrap_or(now);
+        let retry_after_ms = (oldest + window).saturating_sub(now);
+
+        Ok(Some(retry_after_ms.div_ceil(1000).max(1)))
+    }
+}
+
+impl FromRef<RateLimiter> for Arc<ConPool> {
+    fn from_ref(limiter: &RateLimiter) -> Self {
+        limiter.cache.clone()
+    }
+}
+
+/// Meant to be attached per route with
+/// `middleware::from_fn_with_state(RateLimiter::new(..), enforce_rate_limit)`.
+pub async fn enforce_rate_limit<B>(
+    State(limiter): State<RateLimiter>,
+    session_data: Option<AuthzedSessionData>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, Error> {
+    const LOG_TAG: &str = "[enforce_rate_limit]";
+
+    let subject = match session_data {
+        Some(session_data) => format!("user:{}", session_data.username),
+        None => format!(
+            "ip:{}",
+            client_ip(
+                &request,
+                connect_info.map(|ConnectInfo(addr)| addr),
+                &limiter.trusted_proxies
+            )
+        ),
+    };
+
+    match limiter.hit(&subject).await {
+        Ok(None) => Ok(next.run(request).await),
+        Ok(Some(retry_after)) => {
+            tracing::info!(
+                "{LOG_TAG} rate limited {subject} on {scope}, retry after {retry_after}s",
+                scope = limiter.scope
+            );
+            Err(Error::Api(ApiError::TooManyRequests(retry_after)))
+        }
+        // Don't take the whole api down with redis, let the request through instead
+        Err(_) => Ok(next.run(request).await),
+    }
+}
+
+/// The peer address, unless the peer is one of our proxies. Clients write whatever they like in
+/// `x-forwarded-for` and every proxy appends the address it got the request from, so the client is
+/// the right-most hop that wasn't added by a trusted proxy.
+fn client_ip<B>(
+    request: &Request<B>,
+    peer: Option<SocketAddr>,
+    trusted_proxies: &[IpAddr],
+) -> String {
+    let Some(peer) = peer.map(|addr| addr.ip()) else {
+        return "unknown".to_string();
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let mut client = peer;
+    for hop in request
+        .headers()
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .flat_map(|value| value.rsplit(','))
+    {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => {
+                client = ip;
+                if !trusted_proxies.contains(&ip) {
+                    break;
+                }
+            }
+            // Anything left of a hop we can't read can't be told apart from a forgery
+            Err(_) => break,
+        }
+    }
+    client.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_rate_limit() {
+        assert_eq!(
+            "10/60".parse::<RateLimit>().unwrap(),
+            RateLimit::new(10, Duration::from_secs(60))
+        );
+        assert!("10".parse::<RateLimit>().is_err());
+        assert!("10/0".parse::<RateLimit>().is_err());
+        assert!("ten/60".parse::<RateLimit>().is_err());
+    }
+
+    #[test]
+    fn ignores_forwarded_for_header_of_untrusted_peers() {
+        let peer = Some(SocketAddr::from(([198, 51, 100, 9], 1234)));
+        let spoofed = Request::builder()
+            .header("x-forwarded-for", "203.0.113.7")
+            .body(())
+            .unwrap();
+        assert_eq!(client_ip(&spoofed, peer, &[]), "198.51.100.9");
+
+        let proxy: IpAddr = [10, 0, 0, 1].into();
+        assert_eq!(client_ip(&spoofed, peer, &[proxy]), "198.51.100.9");
+        assert_eq!(client_ip(&spoofed, None, &[proxy]), "unknown");
+    }
+
+    #[test]
+    fn takes_right_most_untrusted_hop_behind_trusted_proxies() {
+        let trusted: [IpAddr; 2] = [[10, 0, 0, 1].into(), [10, 0, 0, 2].into()];
+        let peer = Some(SocketAddr::from(([10, 0, 0, 1], 1234)));
+
+        // The client sent `x-forwarded-for: 192.0.2.1` itself, the proxies appended the rest
+        let spoofed = Request::builder()
+            .header("x-forwarded-for", "192.0.2.1, 203.0.113.7, 10.0.0.2")
+            .body(())
+            .unwrap();
+        assert_eq!(client_ip(&spoofed, peer, &trusted), "203.0.113.7");
+
+        let garbled = Request::builder()
+            .header("x-forwarded-for", "203.0.113.7, not-an-ip, 10.0.0.2")
+            .body(())
+            .unwrap();
+        assert_eq!(client_ip(&garbled, peer, &trusted), "10.0.0.2");
+
+        let direct = Request::builder().body(()).unwrap();
+        assert_eq!(client_ip(&direct, peer, &trusted), "10.0.0.1");
+    }
+}
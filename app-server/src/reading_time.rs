@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use pockety::models::ItemStatus;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::articles::{domain_of, parse_tags},
+    db::ArticleModel,
+};
+
+/// Reading speed Pocket's own estimates are close to.
+const WORDS_PER_MINUTE: u32 = 230;
+
+/// Used when neither the article, its domain nor the rest of the library tell anything.
+const DEFAULT_MINUTES: u32 = 5;
+
+/// Where the reading time of an article comes from, from the most to the least reliable.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EstimateSource {
+    /// `time_to_read` computed by Pocket
+    Pocket,
+    WordCount,
+    /// Average of the other articles of the same domain
+    DomainAverage,
+    /// Average of the whole library
+    LibraryAverage,
+    Default,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimatedArticle {
+    pub item_id: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub domain: Option<String>,
+    pub minutes: u32,
+    pub source: EstimateSource,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingTime {
+    pub unread_articles: usize,
+    /// Minutes it takes to read every unread article
+    pub total_minutes: u32,
+    /// Unread articles whose reading time Pocket didn't know, and was estimated
+    pub estimated_articles: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingQueue {
+    pub budget_minutes: u32,
+    pub total_minutes: u32,
+    pub articles: Vec<EstimatedArticle>,
+}
+
+/// Narrows the unread articles down to the ones with a tag or in a language.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadingFilter {
+    pub tag: Option<String>,
+    pub lang: Option<String>,
+}
+
+impl ReadingFilter {
+    fn matches(&self, article: &ArticleModel) -> bool {
+        let tag = self.tag.as_deref().map(str::trim);
+        let lang = self.lang.as_deref().map(str::trim);
+
+        tag.is_none_or(|tag| {
+            parse_tags(article.tags.as_deref())
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+        }) && lang.is_none_or(|lang| {
+            article
+                .lang
+                .as_deref()
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(lang))
+        })
+    }
+}
+
+/// Estimates reading times of the unread articles of a library. Articles without a time of their
+/// own get the average of their domain, or of the library, over every article known to take time.
+pub struct Estimator {
+    domain_averages: HashMap<String, u32>,
+    library_average: Option<u32>,
+}
+
+impl Estimator {
+    pub fn new(articles: &[ArticleModel]) -> Self {
+        let mut domains = HashMap::<String, (u32, u32)>::new();
+        let (mut total, mut count) = (0, 0);
+        for article in articles {
+            let Some((minutes, _)) = own_estimate(article) else {
+                continue;
+            };
+            total += minutes;
+            count += 1;
+            if let Some(domain) = article_domain(article) {
+                let (total, count) = domains.entry(domain).or_default();
+                *total += minutes;
+                *count += 1;
+            }
+        }
+
+        Self {
+            domain_averages: domains
+                .into_iter()
+                .map(|(domain, (total, count))| (domain, average(total, count)))
+                .collect(),
+            library_average: (count > 0).then(|| average(total, count)),
+        }
+    }
+
+    pub fn estimate(&self, article: &ArticleModel) -> EstimatedArticle {
+        let domain = article_domain(article);
+        let (minutes, source) = own_estimate(article)
+            .or_else(|| {
+                domain
+                    .as_ref()
+                    .and_then(|domain| self.domain_averages.get(domain))
+                    .map(|minutes| (*minutes, EstimateSource::DomainAverage))
+            })
+            .or_else(|| {
+                self.library_average
+                    .map(|minutes| (minutes, EstimateSource::LibraryAverage))
+            })
+            .unwrap_or((DEFAULT_MINUTES, EstimateSource::Default));
+
+        EstimatedArticle {
+            item_id: article.item_id.clone(),
+            title: article
+                .resolved_title
+                .clone()
+                .or_else(|| article.given_title.clone())
+                .filter(|title| !title.trim().is_empty()),
+            url: article_url(article).map(str::to_string),
+            domain,
+            minutes,
+            source,
+        }
+    }
+
+    /// The unread articles matching `filter`, oldest first.
+    pub fn unread(
+        &self,
+        articles: &[ArticleModel],
+        filter: &ReadingFilter,
+    ) -> Vec<EstimatedArticle> {
+        let mut unread = articles
+            .iter()
+            .filter(|article| article.status == ItemStatus::Normal.as_u8() as i32)
+            .filter(|article| filter.matches(article))
+            .collect::<Vec<_>>();
+        unread.sort_by_key(|article| article.time_added.unwrap_or(i64::MAX));
+        unread
+            .into_iter()
+            .map(|article| self.estimate(article))
+            .collect()
+    }
+}
+
+pub fn reading_time(unread: &[EstimatedArticle]) -> ReadingTime {
+    ReadingTime {
+        unread_articles: unread.len(),
+        total_minutes: unread.iter().map(|article| article.minutes).sum(),
+        estimated_articles: unread
+            .iter()
+            .filter(|article| article.source != EstimateSource::Pocket)
+            .count(),
+    }
+}
+
+/// Fills `budget_minutes` with the oldest unread articles, skipping the ones that don't fit in
+/// what's left so shorter ones further down the backlog get a chance.
+pub fn reading_queue(unread: Vec<EstimatedArticle>, budget_minutes: u32) -> ReadingQueue {
+    let mut left = budget_minutes;
+    let articles = unread
+        .into_iter()
+        .filter(|article| {
+            let fits = article.minutes <= left;
+            if fits {
+                left -= article.minutes;
+            }
+            fits
+        })
+        .collect::<Vec<_>>();
+
+    ReadingQueue {
+        budget_minutes,
+        total_minutes: budget_minutes - left,
+        articles,
+    }
+}
+
+fn own_estimate(article: &ArticleModel) -> Option<(u32, EstimateSource)> {
+    let time_to_read = article
+        .time_to_read
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| (minutes as u32, EstimateSource::Pocket));
+    let word_count = article.word_count.filter(|words| *words > 0).map(|words| {
+        (
+            (words as u32).div_ceil(WORDS_PER_MINUTE),
+            EstimateSource::WordCount,
+        )
+    });
+    time_to_read.or(word_count)
+}
+
+fn article_url(article: &ArticleModel) -> Option<&str> {
+    article
+        .resolved_url
+        .as_deref()
+        .or(article.given_url.as_deref())
+        .filter(|url| !url.trim().is_empty())
+}
+
+fn article_domain(article: &ArticleModel) -> Option<String> {
+    article_url(article).and_then(domain_of)
+}
+
+fn average(total: u32, count: u32) -> u32 {
+    ((total as f64 / count as f64).round() as u32).max(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn article(
+        item_id: &str,
+        url: &str,
+        time_to_read: Option<i32>,
+        words: Option<i32>,
+    ) -> ArticleModel {
+        ArticleModel {
+            item_id: item_id.to_string(),
+            given_url: Some(url.to_string()),
+            given_title: Some(item_id.to_string()),
+            time_added: item_id.parse().ok(),
+            word_count: words,
+            tags: Some(r#"{"rust":{"tag":"rust"}}"#.to_string()),
+            lang: Some("en".to_string()),
+            time_to_read,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn estimates_from_the_most_reliable_source() {
+        let articles = [
+            article("1", "https://a.com/1", Some(12), Some(5000)),
+            article("2", "https://a.com/2", None, Some(1000)),
+            article("3", "https://www.a.com/3", None, None),
+            article("4", "https://b.com/4", None, None),
+        ];
+        let estimator = Estimator::new(&articles);
+        let estimates = articles
+            .iter()
+            .map(|article| estimator.estimate(article))
+            .map(|estimate| (estimate.minutes, estimate.source))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            estimates,
+            [
+                (12, EstimateSource::Pocket),
+                (5, EstimateSource::WordCount),
+                (9, EstimateSource::DomainAverage),
+                (9, EstimateSource::LibraryAverage),
+            ]
+        );
+        assert_eq!(
+            Estimator::new(&[]).estimate(&articles[3]).source,
+            EstimateSource::Default
+        );
+    }
+
+    #[test]
+    fn fills_the_budget_with_the_oldest_articles_that_fit() {
+        let articles = [
+            article("1", "https://a.com/1", Some(10), None),
+            article("2", "https://a.com/2", Some(20), None),
+            article("3", "https://a.com/3", Some(8), None),
+            article("4", "https://a.com/4", Some(5), None),
+        ];
+        let estimator = Estimator::new(&articles);
+        let queue = reading_queue(estimator.unread(&articles, &Default::default()), 25);
+
+        let queued = queue
+            .articles
+            .iter()
+            .map(|article| article.item_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(queued, ["1", "3", "4"]);
+        assert_eq!(queue.total_minutes, 23);
+    }
+
+    #[test]
+    fn filters_by_tag_and_language() {
+        let mut french = article("2", "https://a.com/2", Some(3), None);
+        french.lang = Some("fr".to_string());
+        french.tags = Some("cooking".to_string());
+        let articles = [article("1", "https://a.com/1", Some(10), None), french];
+        let estimator = Estimator::new(&articles);
+
+        let unread = |tag: Option<&str>, lang: Option<&str>| {
+            let filter = ReadingFilter {
+                tag: tag.map(str::to_string),
+                lang: lang.map(str::to_string),
+            };
+            reading_time(&estimator.unread(&articles, &filter)).total_minutes
+        };
+        assert_eq!(unread(None, None), 13);
+        assert_eq!(unread(Some("Rust"), None), 10);
+        assert_eq!(unread(None, Some("fr")), 3);
+        assert_eq!(unread(Some("rust"), Some("fr")), 0);
+    }
+}
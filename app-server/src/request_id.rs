@@ -0,0 +1,137 @@
+use axum::{
+    http::{header::HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::global;
+use opentelemetry_http::HeaderExtractor;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::shares::SHARE_PATH_PREFIX;
+
+pub static REQUEST_ID_HEADER_NAME: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request currently being handled, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// Reuses the `x-request-id` sent by the client (or the load balancer) or generates a new one,
+/// makes it available to everything running inside the request through `current_request_id` and
+/// the request's headers, and echoes it back on the response.
+///
+/// Has to wrap the `TraceLayer`, so that `make_request_span` finds the id.
+pub async fn propagate_request_id<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(ToString::to_string)
+        .unwrap_or_else(|| nanoid::nanoid!());
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        request
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER_NAME.clone(), value);
+    }
+
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER_NAME.clone(), value);
+    }
+
+    response
+}
+
+/// Root span of every request, so each event logged while handling it carries the request id.
+/// Only the path is recorded, since query strings may carry OAuth state, and tokens of share links
+/// are left out of it.
+///
+/// Continues the trace of the caller when it sends a W3C `traceparent`, like the web server does.
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        otel.kind = "server",
+        method = %request.method(),
+        path = %loggable_path(request.uri().path()),
+        request_id,
+    );
+
+    let parent_context = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    span.set_parent(parent_context);
+
+    span
+}
+
+fn loggable_path(path: &str) -> &str {
+    if path.starts_with(SHARE_PATH_PREFIX) {
+        "/s/{token}"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::trace::{TraceContextExt, TraceId, TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::TracerProvider};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn continues_the_trace_of_the_caller() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        // Tracers only hold a weak reference to their provider
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        let request = Request::builder()
+            .uri("/articles")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .body(())
+            .unwrap();
+
+        let trace_id = tracing::subscriber::with_default(subscriber, || {
+            make_request_span(&request)
+                .context()
+                .span()
+                .span_context()
+                .trace_id()
+        });
+        assert_eq!(
+            trace_id,
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+    }
+
+    #[test]
+    fn leaves_share_tokens_out_of_paths() {
+        assert_eq!(loggable_path("/s/8fZq3uWk"), "/s/{token}");
+        assert_eq!(loggable_path("/v2/shares"), "/v2/shares");
+    }
+}
@@ -0,0 +1,353 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::{FutureExt, TryFutureExt};
+use pockety::{models::Timestamp, Pockety};
+use rand::Rng;
+use redis::{AsyncCommands, ExistenceCheck, Script, SetExpiry, SetOptions};
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::{
+    credentials::{load_pocket_credentials, revoke_on_pocket_auth_failure, CredentialsKeys},
+    db::{fetch_auto_sync_users, fetch_last_successful_sync, record_sync_run},
+    domain::{SyncRun, SyncRunStatus, User},
+    error::Error,
+    metrics::metrics,
+    quota::{PocketQuotaStore, QuotaPriority},
+    shutdown::Shutdown,
+    sync::store_article,
+    telemetry::{pocket_span, redis_span},
+    Cache, Store,
+};
+
+/// Only one replica runs the scheduled syncs of an interval, the one holding this lease.
+const LEASE_KEY: &str = "auto_sync:lease";
+const TRIGGER: &str = "scheduled";
+
+/// Periodically syncs the libraries of the users who turned on automatic sync.
+///
+/// Every replica ticks once per `interval`, but only the one that gets the lease in Redis runs
+/// the syncs. Users are spread over the `jitter` window at the start of the run, so their calls
+/// to Pocket don't all land at once. Syncs act with the users' stored Pocket credentials, only
+/// retrieve the articles changed since the last successful sync and are recorded in `sync_runs`.
+#[derive(Clone)]
+pub struct AutoSync {
+    pub pockety: Pockety,
+    pub store: Store,
+    pub cache: Cache,
+    pub shutdown: Shutdown,
+    pub credentials_keys: CredentialsKeys,
+    pub interval: Duration,
+    pub jitter: Duration,
+}
+
+/// Why a sync of a user didn't store their library.
+enum SyncRunError {
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Default)]
+struct SyncReport {
+    stored: i32,
+    failed: i32,
+}
+
+impl AutoSync {
+    /// Runs until the server starts draining. Syncs in progress stop at the next article.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(self) {
+        const LOG_TAG: &str = "[AutoSync::run]";
+
+        let holder = nanoid::nanoid!();
+        info!(
+            "{LOG_TAG} syncing every {:?} with a jitter of {:?}",
+            self.interval, self.jitter
+        );
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {}
+                _ = self.shutdown.draining() => break,
+            }
+
+            match acquire_lease(&self.cache, &holder, self.interval).await {
+                // Runs may outlast the interval, the lease is kept until they're done
+                Ok(true) => tokio::select! {
+                    _ = self.sync_users() => {}
+                    _ = self.keep_lease(&holder) => {}
+                },
+                Ok(false) => debug!("{LOG_TAG} another replica runs this interval's syncs"),
+                Err(e) => warn!("{LOG_TAG} failed to acquire the lease, skipping. Error: {e:?}"),
+            }
+        }
+
+        info!("{LOG_TAG} stopped");
+    }
+
+    /// Extends the lease for as long as it's polled. Never returns, so the syncs aren't cut short
+    /// when the lease is lost.
+    async fn keep_lease(&self, holder: &str) {
+        const LOG_TAG: &str = "[AutoSync::keep_lease]";
+
+        loop {
+            tokio::time::sleep(self.interval / 3).await;
+            match extend_lease(&self.cache, holder, self.interval).await {
+                Ok(true) => debug!("{LOG_TAG} extended the lease"),
+                Ok(false) => {
+                    warn!("{LOG_TAG} lost the lease, another replica may sync this interval too");
+                    return std::future::pending().await;
+                }
+                Err(e) => warn!("{LOG_TAG} failed to extend the lease. Error: {e:?}"),
+            }
+        }
+    }
+
+    async fn sync_users(&self) {
+        const LOG_TAG: &str = "[AutoSync::sync_users]";
+
+        let users = match fetch_auto_sync_users(self.store.clone()).await {
+            Ok(users) => users,
+            Err(e) => {
+                error!("{LOG_TAG} failed to fetch users to sync. Error: {e:?}");
+                return;
+            }
+        };
+        info!("{LOG_TAG} syncing {} user(s)", users.len());
+
+        let schedule = schedule(users, self.jitter, &mut rand::thread_rng());
+        let start = Instant::now();
+        for (offset, user) in schedule {
+            tokio::select! {
+                _ = tokio::time::sleep_until(start + offset) => {}
+                _ = self.shutdown.draining() => return,
+            }
+
+            let run = self.sync_user(&user).await;
+            info!(
+                "{LOG_TAG} sync of {username} {status}, stored {stored}, failed {failed}",
+                username = user.username,
+                status = run.status.as_str(),
+                stored = run.articles_stored,
+                failed = run.articles_failed,
+            );
+            let _ = record_sync_run(self.store.clone(), &run).await;
+        }
+    }
+
+    async fn sync_user(&self, user: &User) -> SyncRun {
+        let started_at = Utc::now();
+        let (status, report, error) = match self.try_sync_user(user).await {
+            Ok(report) if report.failed == 0 => (SyncRunStatus::Succeeded, report, None),
+            Ok(report) => (
+                SyncRunStatus::Failed,
+                report,
+                Some("Some articles couldn't be stored".to_string()),
+            ),
+            Err(SyncRunError::Skipped(reason)) => {
+                (SyncRunStatus::Skipped, SyncReport::default(), Some(reason))
+            }
+            Err(SyncRunError::Failed(reason)) => {
+                (SyncRunStatus::Failed, SyncReport::default(), Some(reason))
+            }
+        };
+
+        SyncRun {
+            user_id: user.id,
+            trigger: TRIGGER,
+            status,
+            started_at,
+            finished_at: Utc::now(),
+            articles_stored: report.stored,
+            articles_failed: report.failed,
+            error,
+        }
+    }
+
+    async fn try_sync_user(&self, user: &User) -> Result<SyncReport, SyncRunError> {
+        const LOG_TAG: &str = "[AutoSync::try_sync_user]";
+
+        let failed = |e: Error| SyncRunError::Failed(e.to_string());
+        let skipped = |e: Error| SyncRunError::Skipped(e.to_string());
+
+        let _sync_guard = self.shutdown.track_sync().map_err(skipped)?;
+        let _sync_timer = metrics().start_sync(TRIGGER);
+
+        let access_token = load_pocket_credentials(&self.store, &self.credentials_keys, user.id)
+            .await
+            .map_err(failed)?
+            .ok_or_else(|| {
+                SyncRunError::Skipped(
+                    "No valid Pocket credentials, the user has to sign in again".to_string(),
+                )
+            })?;
+
+        // Leaves the quota for interactive requests once it runs low
+        self.cache
+            .check_pocket_quota(Some(&user.username), QuotaPriority::Background)
+            .await
+            .map_err(skipped)?;
+
+        let since = fetch_last_successful_sync(self.store.clone(), user.id)
+            .await
+            .map_err(failed)?;
+
+        // Pocket only reports deleted articles to retrieves made with `since`
+        let mut retrieve = self.pockety.retrieve().access_token(access_token.clone());
+        if let Some(since) = since {
+            retrieve = retrieve.since(Timestamp(since.timestamp()));
+        }
+        let res = match retrieve
+            .execute()
+            .instrument(pocket_span("retrieve"))
+            .inspect(|result| metrics().record_pocket_call("retrieve", result))
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                let e = self
+                    .cache
+                    .record_failed_pocket_call(Some(&user.username), e.into())
+                    .await;
+                return Err(failed(
+                    revoke_on_pocket_auth_failure(
+                        &self.store,
+                        &self.credentials_keys,
+                        &user.username,
+                        &access_token,
+                        e,
+                    )
+                    .await,
+                ));
+            }
+        };
+        let _ = self
+            .cache
+            .record_pocket_call(Some(&user.username), Some(res.rate_limits.into()))
+            .await;
+
+        let mut report = SyncReport::default();
+        for item in res.data {
+            if self.shutdown.is_draining() {
+                return Err(SyncRunError::Failed(
+                    "Interrupted by a shutdown".to_string(),
+                ));
+            }
+
+            let item_id = item.item_id.0.clone();
+            let stored = store_article(&self.store, item, user.id).await;
+            if let Err(e) = &stored {
+                error!("{LOG_TAG} Failed to store article {item_id}. Error: {e:?}");
+                report.failed += 1;
+            } else {
+                report.stored += 1;
+            }
+            metrics().record_sync_item(stored.is_ok());
+        }
+
+        Ok(report)
+    }
+}
+
+/// Takes the lease for `ttl` unless another replica holds it. It's never released, so a replica
+/// ticking later in the same interval doesn't sync everyone again.
+async fn acquire_lease(cache: &Cache, holder: &str, ttl: Duration) -> Result<bool, Error> {
+    let mut con = cache
+        .get()
+        .map_err(|e| Error::Session(format!("Connection error: {e}")))
+        .await?;
+
+    con.set_options::<_, _, Option<String>>(
+        LEASE_KEY,
+        holder,
+        SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::PX(ttl.as_millis() as usize)),
+    )
+    .instrument(redis_span("SET"))
+    .map_ok(|reply| reply.is_some())
+    .map_err(|e| Error::Session(format!("Failed to acquire the sync lease: {e}")))
+    .await
+}
+
+/// Pushes the lease back to `ttl` from now, as long as `holder` still holds it.
+async fn extend_lease(cache: &Cache, holder: &str, ttl: Duration) -> Result<bool, Error> {
+    let mut con = cache
+        .get()
+        .map_err(|e| Error::Session(format!("Connection error: {e}")))
+        .await?;
+
+    Script::new(
+        r#"
+        if redis.call("GET", KEYS[1]) == ARGV[1] then
+            return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+        end
+        return 0"#,
+    )
+    .key(LEASE_KEY)
+    .arg(holder)
+    .arg(ttl.as_millis() as u64)
+    .invoke_async::<_, i32>(&mut *con)
+    .instrument(redis_span("EVALSHA"))
+    .map_ok(|extended| extended == 1)
+    .map_err(|e| Error::Session(format!("Failed to extend the sync lease: {e}")))
+    .await
+}
+
+/// Spreads the users randomly over `jitter`, ordered by when their sync starts.
+fn schedule(users: Vec<User>, jitter: Duration, rng: &mut impl Rng) -> Vec<(Duration, User)> {
+    let jitter = jitter.as_millis() as u64;
+    let mut schedule = users
+        .into_iter()
+        .map(|user| (Duration::from_millis(rng.gen_range(0..=jitter)), user))
+        .collect::<Vec<_>>();
+    schedule.sort_by_key(|(offset, _)| *offset);
+    schedule
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn user(id: i32) -> User {
+        User {
+            id,
+            uuid: String::new(),
+            username: format!("user{id}"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn spreads_users_over_the_jitter_window() {
+        let jitter = Duration::from_secs(60);
+        let schedule = schedule(
+            (0..20).map(user).collect(),
+            jitter,
+            &mut StdRng::seed_from_u64(7),
+        );
+
+        assert_eq!(schedule.len(), 20);
+        assert!(schedule.iter().all(|(offset, _)| *offset <= jitter));
+        assert!(schedule.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!(schedule.iter().any(|(offset, _)| *offset != schedule[0].0));
+    }
+
+    #[test]
+    fn starts_every_user_at_once_without_jitter() {
+        let schedule = schedule(
+            vec![user(1), user(2)],
+            Duration::ZERO,
+            &mut rand::thread_rng(),
+        );
+
+        assert!(schedule.iter().all(|(offset, _)| offset.is_zero()));
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use axum::{
@@ -11,23 +11,37 @@ use base64::{engine::general_purpose, Engine as _};
 use base64ct::{Base64, Encoding};
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
-use error::Error;
+use error::{ApiError, Error, PocketError};
 use futures::TryFutureExt;
 use rand::{thread_rng, RngCore};
-use redis::AsyncCommands;
+use redis::{aio::ConnectionLike, AsyncCommands, SetExpiry, SetOptions};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Sha3_256};
+use tracing::Instrument;
 
-use crate::{error, SESSION_ID_COOKIE_NAME};
+use crate::{
+    credentials::{revoke_on_pocket_auth_failure, CredentialsKeys},
+    error,
+    quota::unix_now,
+    telemetry::redis_span,
+    Store, SESSION_ID_COOKIE_NAME,
+};
 
 pub type ConPool = Pool<RedisConnectionManager>;
 
 const SESSION_ID_LEN: usize = 64;
+const TOKEN_LEN: usize = 32;
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions";
+/// Sessions loaded per round trip by maintenance tasks
+const SESSION_BATCH_SIZE: usize = 100;
+
+/// Request token sessions only have to survive the round trip through Pocket's authorization page.
+pub const REQUEST_TOKEN_SESSION_LIFETIME: Duration = Duration::from_secs(30 * 60);
 
 #[derive(Debug, Clone)]
 pub struct SessionId(pub String);
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct HashedSessionId(pub String);
 
 pub fn generate_session_id() -> Result<(SessionId, HashedSessionId), Error> {
@@ -39,6 +53,16 @@ pub fn generate_session_id() -> Result<(SessionId, HashedSessionId), Error> {
     Ok((SessionId(session_id), HashedSessionId(hashed)))
 }
 
+/// A new random token for links and invitations, and the hash it's stored under.
+pub fn generate_token() -> (String, String) {
+    let mut token = [0u8; TOKEN_LEN];
+    thread_rng().fill_bytes(&mut token);
+    let token = general_purpose::URL_SAFE_NO_PAD.encode(token);
+    let hashed = hash(&token);
+
+    (token, hashed)
+}
+
 pub fn hash(input: &str) -> String {
     let mut hasher = Sha3_256::new();
     hasher.update(input);
@@ -50,6 +74,9 @@ pub fn hash(input: &str) -> String {
 pub struct RequestTokenSessionData {
     pub request_token: String,
     pub csrf_token: String,
+    /// Unix timestamp in seconds. Missing on sessions created before it was recorded.
+    #[serde(default)]
+    pub created_at: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -57,6 +84,401 @@ pub struct RequestTokenSessionData {
 pub struct AuthzedSessionData {
     pub access_token: String,
     pub username: String,
+    #[serde(default)]
+    pub csrf_token: String,
+    /// Set once Pocket rejected `access_token`, e.g. because the user revoked access to
+    /// just-links. The user has to go through the authorization flow again.
+    #[serde(default)]
+    pub reauth_required: bool,
+    /// Unix timestamp in seconds. Missing on sessions created before it was recorded.
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    /// Key the session is stored under. Filled in when the session is loaded.
+    #[serde(skip)]
+    pub hashed_session_id: HashedSessionId,
+}
+
+/// Turns Pocket rejecting the session's access token into `ApiError::ReauthRequired` and marks the
+/// session as stale and the stored credentials as revoked, so that following requests and
+/// scheduled syncs don't hit Pocket with the same token again. Any other error is passed through
+/// as is.
+pub async fn invalidate_on_pocket_auth_failure(
+    pool: &ConPool,
+    store: &Store,
+    keys: &CredentialsKeys,
+    session_data: &AuthzedSessionData,
+    error: Error,
+) -> Error {
+    if !matches!(error, Error::Pocket(PocketError::Unauthorized(_))) {
+        return error;
+    }
+    let error = revoke_on_pocket_auth_failure(
+        store,
+        keys,
+        &session_data.username,
+        &session_data.access_token,
+        error,
+    )
+    .await;
+
+    tracing::info!(
+        "Pocket rejected the access token of user {}, invalidating session",
+        session_data.username
+    );
+
+    let stale_session_data = AuthzedSessionData {
+        reauth_required: true,
+        ..session_data.clone()
+    };
+    let stored = match (pool.get().await, serde_json::to_string(&stale_session_data)) {
+        (Ok(mut con), Ok(stringified_session_data)) => con
+            .set_options::<_, _, ()>(
+                &stale_session_data.hashed_session_id.0,
+                stringified_session_data,
+                SetOptions::default().with_expiration(SetExpiry::KEEPTTL),
+            )
+            .instrument(redis_span("SET"))
+            .await
+            .is_ok(),
+        _ => false,
+    };
+    if !stored {
+        tracing::error!(
+            "Failed to invalidate session of user {}",
+            session_data.username
+        );
+    }
+
+    error
+}
+
+/// Any kind of session found in the store.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum SessionData {
+    Authzed(AuthzedSessionData),
+    RequestToken(RequestTokenSessionData),
+}
+
+impl SessionData {
+    fn created_at(&self) -> Option<i64> {
+        match self {
+            SessionData::Authzed(session_data) => session_data.created_at,
+            SessionData::RequestToken(session_data) => session_data.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredSession {
+    pub hashed_session_id: HashedSessionId,
+    pub data: SessionData,
+    /// Remaining time to live in seconds, `None` if the session never expires.
+    pub ttl: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionExpiry {
+    Live,
+    Expired,
+    /// The session was stored without a ttl and should expire after the given number of seconds.
+    ExpiresIn(i64),
+}
+
+impl StoredSession {
+    /// Sessions stored before ttls were set on them live forever, these are expired based on
+    /// their age, or given a full lifetime when their age is unknown.
+    pub fn expiry(&self, session_lifetime: Duration, now: i64) -> SessionExpiry {
+        if self.ttl.is_some() {
+            return SessionExpiry::Live;
+        }
+
+        let lifetime = match self.data {
+            SessionData::Authzed(_) => session_lifetime,
+            SessionData::RequestToken(_) => REQUEST_TOKEN_SESSION_LIFETIME,
+        }
+        .as_secs() as i64;
+
+        match self.data.created_at() {
+            Some(created_at) if now - created_at >= lifetime => SessionExpiry::Expired,
+            Some(created_at) => SessionExpiry::ExpiresIn(lifetime - (now - created_at)),
+            None => SessionExpiry::ExpiresIn(lifetime),
+        }
+    }
+}
+
+/// Loads the sessions stored under `keys` along with their remaining time to live. Keys that
+/// hold something else, or whose session is gone, map to `None`.
+async fn load_sessions(
+    con: &mut (impl ConnectionLike + Send),
+    keys: &[String],
+) -> Result<Vec<Option<StoredSession>>, Error> {
+    let mut sessions = vec![];
+    for keys in keys.chunks(SESSION_BATCH_SIZE) {
+        let values: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(keys)
+            .query_async(con)
+            .instrument(redis_span("MGET"))
+            .map_err(|e| {
+                tracing::error!("Failed to get sessions. Error: {e:?}");
+                Error::Session("Failed to get sessions".to_string())
+            })
+            .await?;
+        let mut ttls = redis::pipe();
+        for key in keys {
+            ttls.ttl(key);
+        }
+        let ttls: Vec<i64> = ttls
+            .query_async(con)
+            .instrument(redis_span("PIPELINE"))
+            .map_err(|e| {
+                tracing::error!("Failed to get session ttls. Error: {e:?}");
+                Error::Session("Failed to get sessions".to_string())
+            })
+            .await?;
+
+        sessions.extend(
+            keys.iter()
+                .zip(values)
+                .zip(ttls)
+                .map(|((key, value), ttl)| {
+                    let data = serde_json::from_str(&value?).ok()?;
+                    Some(StoredSession {
+                        hashed_session_id: HashedSessionId(key.clone()),
+                        data,
+                        ttl: (ttl >= 0).then_some(ttl),
+                    })
+                }),
+        );
+    }
+
+    Ok(sessions)
+}
+
+/// Scans the store for keys of `key_type` matching `pattern`.
+async fn scan_keys(
+    con: &mut (impl ConnectionLike + Send),
+    pattern: &str,
+    key_type: &str,
+) -> Result<Vec<String>, Error> {
+    async {
+        let mut scan = redis::cmd("SCAN");
+        scan.cursor_arg(0)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("TYPE")
+            .arg(key_type);
+        let mut iter = scan
+            .iter_async::<String>(con)
+            .map_err(|e| {
+                tracing::error!("Failed to scan session store. Error: {e:?}");
+                Error::Session("Failed to scan session store".to_string())
+            })
+            .await?;
+        let mut keys = vec![];
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+    .instrument(redis_span("SCAN"))
+    .await
+}
+
+/// Redis set of the ids of a user's sessions. Ids are added on sign-in and dropped once their
+/// session expired, the next time the set is read.
+pub fn user_sessions_key(username: &str) -> String {
+    format!("{USER_SESSIONS_KEY_PREFIX}:{username}")
+}
+
+/// Live sessions of `username`, forgetting the ids of the expired ones.
+async fn user_sessions(
+    pool: &ConPool,
+    username: &str,
+) -> Result<Vec<(HashedSessionId, AuthzedSessionData)>, Error> {
+    let mut con = pool
+        .get()
+        .map_err(|e| {
+            tracing::error!("Failed to establish connection from connection pool. Error: {e:?}");
+            Error::Session("Failed to establish connection from connection pool".to_string())
+        })
+        .await?;
+
+    let key = user_sessions_key(username);
+    let ids: Vec<String> = con
+        .smembers(&key)
+        .instrument(redis_span("SMEMBERS"))
+        .map_err(|e| {
+            tracing::error!("Failed to get sessions of {username}. Error: {e:?}");
+            Error::Session("Failed to get sessions".to_string())
+        })
+        .await?;
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let values: Vec<Option<String>> = redis::cmd("MGET")
+        .arg(&ids)
+        .query_async(&mut *con)
+        .instrument(redis_span("MGET"))
+        .map_err(|e| {
+            tracing::error!("Failed to get sessions of {username}. Error: {e:?}");
+            Error::Session("Failed to get sessions".to_string())
+        })
+        .await?;
+
+    let mut sessions = vec![];
+    let mut expired = vec![];
+    for (id, value) in ids.into_iter().zip(values) {
+        match value.and_then(|value| serde_json::from_str::<AuthzedSessionData>(&value).ok()) {
+            Some(session_data) => sessions.push((HashedSessionId(id), session_data)),
+            None => expired.push(id),
+        }
+    }
+    if !expired.is_empty() {
+        let _: Result<(), _> = con
+            .srem(&key, &expired)
+            .instrument(redis_span("SREM"))
+            .await;
+    }
+
+    Ok(sessions)
+}
+
+/// Most recently created session of `username` that Pocket hasn't rejected yet.
+pub async fn find_authzed_session(
+    pool: &ConPool,
+    username: &str,
+) -> Result<Option<AuthzedSessionData>, Error> {
+    Ok(user_sessions(pool, username)
+        .await?
+        .into_iter()
+        .filter(|(_, session_data)| !session_data.reauth_required)
+        .map(|(hashed_session_id, session_data)| AuthzedSessionData {
+            hashed_session_id,
+            ..session_data
+        })
+        .max_by_key(|session_data| session_data.created_at))
+}
+
+/// Deletes every session of `username`, returning how many there were.
+pub async fn delete_user_sessions(pool: &ConPool, username: &str) -> Result<usize, Error> {
+    let sessions = user_sessions(pool, username).await?;
+
+    let mut con = pool
+        .get()
+        .map_err(|e| {
+            tracing::error!("Failed to establish connection from connection pool. Error: {e:?}");
+            Error::Session("Failed to establish connection from connection pool".to_string())
+        })
+        .await?;
+    let mut keys = sessions
+        .iter()
+        .map(|(hashed_session_id, _)| hashed_session_id.0.clone())
+        .collect::<Vec<_>>();
+    keys.push(user_sessions_key(username));
+    con.del::<_, ()>(&keys)
+        .instrument(redis_span("DEL"))
+        .map_err(|e| {
+            tracing::error!("Failed to delete sessions of {username}. Error: {e:?}");
+            Error::Session("Failed to delete sessions".to_string())
+        })
+        .await?;
+
+    Ok(sessions.len())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeReport {
+    pub deleted: usize,
+    pub expiry_set: usize,
+    /// Ids of sessions that were gone, dropped from the per-user index sets
+    pub stale_index_entries: usize,
+}
+
+/// Deletes expired sessions that were stored without a ttl, sets one on the remaining ones and
+/// drops the ids of sessions that are gone from the per-user index sets. Sessions are found
+/// through those sets. Sessions stored before the sets existed aren't in any of them,
+/// `include_unindexed` walks the whole keyspace to find those as well.
+pub async fn purge_expired_sessions(
+    pool: &ConPool,
+    session_lifetime: Duration,
+    include_unindexed: bool,
+) -> Result<PurgeReport, Error> {
+    let now = unix_now();
+    let mut con = pool
+        .get()
+        .map_err(|e| {
+            tracing::error!("Failed to establish connection from connection pool. Error: {e:?}");
+            Error::Session("Failed to establish connection from connection pool".to_string())
+        })
+        .await?;
+
+    let mut report = PurgeReport::default();
+    let mut sessions = vec![];
+    for index_key in scan_keys(&mut *con, &format!("{USER_SESSIONS_KEY_PREFIX}:*"), "set").await? {
+        let ids: Vec<String> = con
+            .smembers(&index_key)
+            .instrument(redis_span("SMEMBERS"))
+            .map_err(|e| {
+                tracing::error!("Failed to get sessions of {index_key}. Error: {e:?}");
+                Error::Session("Failed to get sessions".to_string())
+            })
+            .await?;
+        let mut gone = vec![];
+        for (id, session) in ids.iter().zip(load_sessions(&mut *con, &ids).await?) {
+            match session {
+                Some(session) => sessions.push(session),
+                None => gone.push(id),
+            }
+        }
+        if !gone.is_empty() {
+            report.stale_index_entries += gone.len();
+            con.srem::<_, _, ()>(&index_key, &gone)
+                .instrument(redis_span("SREM"))
+                .map_err(|e| {
+                    tracing::error!("Failed to prune {index_key}. Error: {e:?}");
+                    Error::Session("Failed to purge session".to_string())
+                })
+                .await?;
+        }
+    }
+    if include_unindexed {
+        let keys = scan_keys(&mut *con, "*", "string").await?;
+        sessions = load_sessions(&mut *con, &keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+
+    for session in sessions {
+        let key = &session.hashed_session_id.0;
+        let result = match session.expiry(session_lifetime, now) {
+            SessionExpiry::Live => continue,
+            SessionExpiry::Expired => {
+                report.deleted += 1;
+                con.del::<_, ()>(key).instrument(redis_span("DEL")).await
+            }
+            SessionExpiry::ExpiresIn(secs) => {
+                report.expiry_set += 1;
+                con.expire::<_, ()>(key, secs as usize)
+                    .instrument(redis_span("EXPIRE"))
+                    .await
+            }
+        };
+        result.map_err(|e| {
+            tracing::error!("Failed to purge session {key}. Error: {e:?}");
+            Error::Session("Failed to purge session".to_string())
+        })?;
+    }
+
+    Ok(report)
+}
+
+/// Requests without a session cookie are unauthenticated rather than malformed.
+fn not_signed_in() -> Error {
+    Error::Api(ApiError::Unauthorized("Not signed in".to_string()))
 }
 
 #[async_trait]
@@ -73,7 +495,7 @@ where
             .map_err(|e| match e.reason() {
                 TypedHeaderRejectionReason::Missing => {
                     tracing::debug!("missing Cookie header");
-                    Error::Cookie("missing Cookie header".to_string())
+                    not_signed_in()
                 }
                 _ => {
                     tracing::error!("unexpected error getting Cookie header(s): {e}");
@@ -82,11 +504,9 @@ where
             })
             .await?;
 
-        tracing::debug!("found cookies: {cookies:#?}");
-
         let session_cookie = cookies
             .get(SESSION_ID_COOKIE_NAME)
-            .ok_or(Error::Cookie("missing cookie".to_string()))?;
+            .ok_or_else(not_signed_in)?;
 
         let pool = Arc::<ConPool>::from_ref(state);
         let mut con = pool
@@ -100,13 +520,17 @@ where
             .await?;
 
         let hashed_session_id = hash(session_cookie);
-        let session_data: AuthzedSessionData = con
-            .get(hashed_session_id)
+        let mut session_data: AuthzedSessionData = con
+            .get(hashed_session_id.as_str())
+            .instrument(redis_span("GET"))
             .map_err(|e| {
-                tracing::error!("Failed to get SessionData with key: {session_cookie}. Error: {e}");
+                tracing::error!("Failed to get SessionData. Error: {e}");
                 Error::Session("Failed to get SessionData".to_string())
             })
-            .and_then(|v: String| async move {
+            .and_then(|v: Option<String>| async move {
+                let v = v.ok_or_else(|| {
+                    Error::Api(ApiError::Unauthorized("Session not found".to_string()))
+                })?;
                 serde_json::from_str(v.as_str()).map_err(|e| {
                     tracing::error!("Failed to deserialize string into SessionData. Error: {e}");
                     Error::Session("Failed to deserialize. internal error!".to_string())
@@ -114,6 +538,13 @@ where
             })
             .await?;
 
+        if session_data.reauth_required {
+            return Err(Error::Api(ApiError::ReauthRequired(
+                "Pocket authorization expired or was revoked".to_string(),
+            )));
+        }
+
+        session_data.hashed_session_id = HashedSessionId(hashed_session_id);
         Ok(session_data)
     }
 }
@@ -129,6 +560,60 @@ mod test {
         assert_ne!(input, output);
     }
 
+    fn stored_session(data: SessionData, ttl: Option<i64>) -> StoredSession {
+        StoredSession {
+            hashed_session_id: HashedSessionId("hashed".to_string()),
+            data,
+            ttl,
+        }
+    }
+
+    fn authzed_session(created_at: Option<i64>) -> SessionData {
+        SessionData::Authzed(AuthzedSessionData {
+            access_token: "access-token".to_string(),
+            username: "user".to_string(),
+            csrf_token: "csrf-token".to_string(),
+            reauth_required: false,
+            created_at,
+            hashed_session_id: HashedSessionId::default(),
+        })
+    }
+
+    #[test]
+    fn expires_sessions_without_ttl_by_age() {
+        let lifetime = Duration::from_secs(3600);
+        let now = 1_700_000_000;
+
+        let session = stored_session(authzed_session(Some(now - 3600)), None);
+        assert_eq!(session.expiry(lifetime, now), SessionExpiry::Expired);
+
+        let session = stored_session(authzed_session(Some(now - 600)), None);
+        assert_eq!(
+            session.expiry(lifetime, now),
+            SessionExpiry::ExpiresIn(3000)
+        );
+
+        let session = stored_session(authzed_session(None), None);
+        assert_eq!(
+            session.expiry(lifetime, now),
+            SessionExpiry::ExpiresIn(3600)
+        );
+
+        let session = stored_session(authzed_session(Some(now - 7200)), Some(60));
+        assert_eq!(session.expiry(lifetime, now), SessionExpiry::Live);
+    }
+
+    #[test]
+    fn tells_session_kinds_apart() {
+        let data: SessionData =
+            serde_json::from_str(r#"{"requestToken":"code","csrfToken":"csrf"}"#).unwrap();
+        assert!(matches!(data, SessionData::RequestToken(_)));
+
+        let data: SessionData =
+            serde_json::from_str(r#"{"accessToken":"token","username":"user"}"#).unwrap();
+        assert!(matches!(data, SessionData::Authzed(_)));
+    }
+
     #[test]
     fn test_generate_session_id() {
         let (session_id, hashed_session_id) = generate_session_id().unwrap();
@@ -0,0 +1,356 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use pockety::models::ItemStatus;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::{
+    api::articles::parse_tags,
+    collections::CollectionItem,
+    db::ArticleModel,
+    error::{ApiError, Error},
+    validation::{from_json, optional_text},
+};
+
+/// Shared pages are served under this path, followed by the token of the link.
+pub const SHARE_PATH_PREFIX: &str = "/s/";
+
+const MAX_EXPIRES_IN_DAYS: u32 = 365;
+const MAX_TAG_LEN: usize = 255;
+
+/// Tag views can be as long as the library, only the latest articles are shared.
+pub const MAX_SHARED_ARTICLES: usize = 200;
+
+/// A link to a read-only page of a collection, an article or the articles with a tag, which
+/// anyone with the link can open until it's revoked or expires.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub id: i32,
+    pub collection_id: Option<i32>,
+    pub item_id: Option<String>,
+    pub tag: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Only the hash of the token is stored, this is the only time it can be read.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedShareLink {
+    pub share: ShareLink,
+    pub token: String,
+    /// Path of the shared page on the app server
+    pub path: String,
+}
+
+/// Shares exactly one of a collection, an article or the articles with a tag.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NewShareLink {
+    pub collection_id: Option<i32>,
+    /// Pocket id of the article
+    pub item_id: Option<String>,
+    pub tag: Option<String>,
+    /// The link never expires when missing
+    pub expires_in_days: Option<u32>,
+}
+
+/// What a link that can still be opened shares, of the user who created it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveShare {
+    pub user_id: i32,
+    pub collection_id: Option<i32>,
+    pub pocket_article_id: Option<i32>,
+    pub tag: Option<String>,
+}
+
+/// The fields of an article that are safe to show to anyone.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedArticle {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub excerpt: Option<String>,
+    pub top_image_url: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPage {
+    pub title: String,
+    pub description: Option<String>,
+    pub articles: Vec<SharedArticle>,
+}
+
+impl NewShareLink {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "share link")?;
+
+        let item_id = input
+            .item_id
+            .map(|item_id| item_id.trim().to_string())
+            .filter(|item_id| !item_id.is_empty());
+        let tag = optional_text(input.tag.as_deref(), "tag", MAX_TAG_LEN)?;
+        let targets = [
+            input.collection_id.is_some(),
+            item_id.is_some(),
+            tag.is_some(),
+        ];
+        if targets.into_iter().filter(|target| *target).count() != 1 {
+            return Err(Error::Api(ApiError::BadRequest(
+                "Exactly one of collectionId, itemId and tag must be given".to_string(),
+            )));
+        }
+        if input
+            .expires_in_days
+            .is_some_and(|days| !(1..=MAX_EXPIRES_IN_DAYS).contains(&days))
+        {
+            return Err(Error::Api(ApiError::BadRequest(format!(
+                "expiresInDays must be between 1 and {MAX_EXPIRES_IN_DAYS}"
+            ))));
+        }
+
+        Ok(Self {
+            item_id,
+            tag,
+            ..input
+        })
+    }
+}
+
+impl SharedArticle {
+    /// Urls that aren't absolute http(s) ones are left out, they'd end up in links of the page.
+    pub fn new(
+        title: Option<String>,
+        url: Option<String>,
+        excerpt: Option<String>,
+        top_image_url: Option<String>,
+    ) -> Self {
+        Self {
+            title: title.filter(|title| !title.trim().is_empty()),
+            url: url.as_deref().and_then(web_url),
+            excerpt: excerpt.filter(|excerpt| !excerpt.trim().is_empty()),
+            top_image_url: top_image_url.as_deref().and_then(web_url),
+        }
+    }
+}
+
+impl From<CollectionItem> for SharedArticle {
+    fn from(item: CollectionItem) -> Self {
+        Self::new(item.title, item.url, item.excerpt, item.top_image_url)
+    }
+}
+
+impl From<&ArticleModel> for SharedArticle {
+    fn from(article: &ArticleModel) -> Self {
+        Self::new(
+            article
+                .resolved_title
+                .clone()
+                .or_else(|| article.given_title.clone()),
+            article
+                .resolved_url
+                .clone()
+                .or_else(|| article.given_url.clone()),
+            article.excerpt.clone(),
+            article.top_image_url.clone(),
+        )
+    }
+}
+
+fn web_url(url: &str) -> Option<String> {
+    Url::parse(url.trim())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+        .map(String::from)
+}
+
+/// The articles of the library with `tag`, which Pocket didn't delete, latest first.
+pub fn tagged_articles(articles: &[ArticleModel], tag: &str) -> Vec<SharedArticle> {
+    let mut tagged = articles
+        .iter()
+        .filter(|article| article.status != ItemStatus::Deleted.as_u8() as i32)
+        .filter(|article| {
+            parse_tags(article.tags.as_deref())
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+        })
+        .collect::<Vec<_>>();
+    tagged.sort_by_key(|article| std::cmp::Reverse(article.time_added));
+    tagged
+        .into_iter()
+        .take(MAX_SHARED_ARTICLES)
+        .map(SharedArticle::from)
+        .collect()
+}
+
+/// A standalone HTML page of the shared articles, with the Open Graph tags link previews are made
+/// of.
+pub fn shared_page_html(page: &SharedPage) -> String {
+    let title = escape_html(&page.title);
+    let description = page
+        .description
+        .as_deref()
+        .or_else(|| {
+            page.articles
+                .iter()
+                .find_map(|article| article.excerpt.as_deref())
+        })
+        .map(escape_html);
+    let image = page
+        .articles
+        .iter()
+        .find_map(|article| article.top_image_url.as_deref())
+        .map(escape_html);
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<meta name=\"robots\" content=\"noindex\">\n");
+    let _ = writeln!(html, "<title>{title}</title>");
+    let _ = writeln!(html, "<meta property=\"og:title\" content=\"{title}\">");
+    html.push_str("<meta property=\"og:type\" content=\"website\">\n");
+    html.push_str("<meta property=\"og:site_name\" content=\"just-links\">\n");
+    if let Some(description) = &description {
+        let _ = writeln!(
+            html,
+            "<meta name=\"description\" content=\"{description}\">"
+        );
+        let _ = writeln!(
+            html,
+            "<meta property=\"og:description\" content=\"{description}\">"
+        );
+    }
+    if let Some(image) = &image {
+        let _ = writeln!(html, "<meta property=\"og:image\" content=\"{image}\">");
+    }
+    html.push_str(
+        "<style>body{font-family:system-ui,sans-serif;max-width:42rem;margin:2rem auto;\
+         padding:0 1rem;line-height:1.5}li{margin-bottom:1.5rem}img{max-width:100%}</style>\n",
+    );
+    html.push_str("</head>\n<body>\n");
+    let _ = writeln!(html, "<h1>{title}</h1>");
+    if let Some(description) = page.description.as_deref().map(escape_html) {
+        let _ = writeln!(html, "<p>{description}</p>");
+    }
+
+    html.push_str("<ol>\n");
+    for article in &page.articles {
+        let title = escape_html(
+            article
+                .title
+                .as_deref()
+                .or(article.url.as_deref())
+                .unwrap_or("Untitled"),
+        );
+        html.push_str("<li>\n");
+        match &article.url {
+            Some(url) => {
+                let _ = writeln!(
+                    html,
+                    "<h2><a href=\"{}\" rel=\"noopener noreferrer nofollow\">{title}</a></h2>",
+                    escape_html(url)
+                );
+            }
+            None => {
+                let _ = writeln!(html, "<h2>{title}</h2>");
+            }
+        }
+        if let Some(image) = &article.top_image_url {
+            let _ = writeln!(
+                html,
+                "<img src=\"{}\" alt=\"\" loading=\"lazy\">",
+                escape_html(image)
+            );
+        }
+        if let Some(excerpt) = &article.excerpt {
+            let _ = writeln!(html, "<p>{}</p>", escape_html(excerpt));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ol>\n</body>\n</html>\n");
+
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shares_exactly_one_target() {
+        let link = NewShareLink::from_json(br#"{"tag":" rust ","expiresInDays":7}"#).unwrap();
+        assert_eq!(link.tag.as_deref(), Some("rust"));
+        assert_eq!(link.expires_in_days, Some(7));
+
+        for body in [
+            r#"{}"#,
+            r#"{"tag":"  "}"#,
+            r#"{"collectionId":1,"tag":"rust"}"#,
+            r#"{"itemId":"229279689","expiresInDays":0}"#,
+            r#"{"itemId":"229279689","expiresInDays":366}"#,
+            r#"{"collectionId":1,"userId":2}"#,
+        ] {
+            assert_eq!(
+                NewShareLink::from_json(body.as_bytes()).unwrap_err().code(),
+                "bad_request",
+                "{body}"
+            );
+        }
+    }
+
+    #[test]
+    fn renders_only_safe_html() {
+        let page = SharedPage {
+            title: "Rust <reads>".to_string(),
+            description: None,
+            articles: vec![
+                SharedArticle::new(
+                    Some("\"Ownership\" & borrowing".to_string()),
+                    Some("https://doc.rust-lang.org/book/".to_string()),
+                    Some("<script>alert(1)</script>".to_string()),
+                    Some("https://example.com/cover.png".to_string()),
+                ),
+                SharedArticle::new(
+                    None,
+                    Some("javascript:alert(1)".to_string()),
+                    None,
+                    Some("data:image/png;base64,AAAA".to_string()),
+                ),
+            ],
+        };
+        let html = shared_page_html(&page);
+
+        assert!(html.contains("<meta property=\"og:title\" content=\"Rust &lt;reads&gt;\">"));
+        assert!(html.contains(
+            "<meta property=\"og:description\" content=\"&lt;script&gt;alert(1)&lt;/script&gt;\">"
+        ));
+        assert!(
+            html.contains("<meta property=\"og:image\" content=\"https://example.com/cover.png\">")
+        );
+        assert!(html.contains("&quot;Ownership&quot; &amp; borrowing</a>"));
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains("<h2>Untitled</h2>"));
+    }
+}
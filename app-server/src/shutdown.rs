@@ -0,0 +1,169 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use axum::response::sse::Event;
+use futures::{future, stream, Stream, StreamExt};
+use tokio::sync::watch;
+
+use crate::error::{ApiError, Error};
+
+/// Name of the last event sent on a sync stream that was cut short by a shutdown.
+pub const SHUTDOWN_EVENT_NAME: &str = "shutdown";
+
+/// Coordinates a graceful shutdown: once draining starts, no new syncs are accepted and running
+/// ones stop at the next article, so they can be resumed by syncing again.
+#[derive(Clone)]
+pub struct Shutdown {
+    draining: Arc<watch::Sender<bool>>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            draining: Arc::new(watch::channel(false).0),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    pub fn begin_drain(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Resolves once draining has started.
+    pub async fn draining(&self) {
+        let mut draining = self.draining.subscribe();
+        // The sender lives as long as `self`, so this only fails once it's gone anyway
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+
+    /// Registers a new sync, refusing it with `503 Service Unavailable` while draining.
+    pub fn track_sync(&self) -> Result<SyncGuard, Error> {
+        if self.is_draining() {
+            return Err(Error::Api(ApiError::ServiceUnavailable(
+                "Server is shutting down".to_string(),
+            )));
+        }
+
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(SyncGuard {
+            shutdown: self.clone(),
+        })
+    }
+
+    pub fn in_flight_syncs(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Stops handing out `items` once draining starts. Items already handed out are processed to
+    /// completion, so work is only ever interrupted between two items.
+    pub fn until_draining<S>(&self, items: S) -> impl Stream<Item = S::Item>
+    where
+        S: Stream,
+    {
+        let shutdown = self.clone();
+        items.take_while(move |_| future::ready(!shutdown.is_draining()))
+    }
+
+    /// Emits a final `shutdown` event when the stream it's chained to was cut short by draining,
+    /// so the client knows the sync was interrupted rather than completed.
+    pub fn drain_notice(&self) -> impl Stream<Item = Result<Event, Infallible>> {
+        let shutdown = self.clone();
+        stream::once(async move {
+            shutdown.is_draining().then(|| {
+                Ok(Event::default()
+                    .event(SHUTDOWN_EVENT_NAME)
+                    .data("Server is shutting down, sync again to resume"))
+            })
+        })
+        .filter_map(future::ready)
+    }
+}
+
+/// Keeps a sync registered with `Shutdown` until it's dropped.
+pub struct SyncGuard {
+    shutdown: Shutdown,
+}
+
+impl Drop for SyncGuard {
+    fn drop(&mut self) {
+        self.shutdown.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Resolves on SIGINT or, on unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_new_syncs_while_draining() {
+        let shutdown = Shutdown::new();
+        let guard = shutdown.track_sync().unwrap();
+        assert_eq!(shutdown.in_flight_syncs(), 1);
+
+        shutdown.begin_drain();
+        assert!(shutdown.track_sync().is_err());
+
+        drop(guard);
+        assert_eq!(shutdown.in_flight_syncs(), 0);
+    }
+
+    #[tokio::test]
+    async fn stops_between_items_once_draining() {
+        let shutdown = Shutdown::new();
+        let items = shutdown
+            .until_draining(stream::iter(0..3))
+            .map(|idx| {
+                if idx == 1 {
+                    shutdown.begin_drain();
+                }
+                Ok(Event::default().data(idx.to_string()))
+            })
+            .chain(shutdown.drain_notice())
+            .collect::<Vec<_>>()
+            .await;
+
+        // 0, 1 and the shutdown event
+        assert_eq!(items.len(), 3);
+    }
+}
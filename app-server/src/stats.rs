@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::articles::{domain_of, parse_tags},
+    db::{count_unread_articles, fetch_daily_event_counts, fetch_read_articles, fetch_read_days},
+    domain::ArticleEventKind,
+    error::{ApiError, Error},
+    Store,
+};
+
+pub const DEFAULT_STATS_DAYS: u32 = 30;
+pub const MAX_STATS_DAYS: u32 = 365;
+const TOP_LIMIT: usize = 10;
+
+/// Number of events of one kind on one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyEventCount {
+    pub day: NaiveDate,
+    pub kind: String,
+    pub count: i64,
+}
+
+/// An article read in the period of the stats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadArticle {
+    pub url: Option<String>,
+    pub tags: Option<String>,
+    pub time_to_read: Option<i32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekCount {
+    /// Monday of the week
+    pub week_start: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BacklogSize {
+    pub date: NaiveDate,
+    /// Unread articles at the end of the day
+    pub size: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Reading activity of the user over the last days, in UTC. An article counts as read when it's
+/// archived.
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStats {
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    pub reads_per_day: Vec<DayCount>,
+    pub reads_per_week: Vec<WeekCount>,
+    /// Consecutive days with a read up to today, or up to yesterday when nothing was read yet today
+    pub current_streak: u32,
+    /// Most consecutive days with a read, ever
+    pub longest_streak: u32,
+    pub backlog: Vec<BacklogSize>,
+    /// Average estimated reading time in minutes of the articles read
+    pub average_time_to_read: Option<f64>,
+    pub top_domains: Vec<NamedCount>,
+    pub top_tags: Vec<NamedCount>,
+    pub saved: i64,
+    pub read: i64,
+}
+
+/// Stats of the `days` days up to and including `today`.
+pub async fn reading_stats(
+    store: &Store,
+    user_id: i32,
+    days: u32,
+    today: NaiveDate,
+) -> Result<ReadingStats, Error> {
+    if !(1..=MAX_STATS_DAYS).contains(&days) {
+        return Err(Error::Api(ApiError::BadRequest(format!(
+            "days must be between 1 and {MAX_STATS_DAYS}"
+        ))));
+    }
+
+    let since = today - Duration::days(days as i64 - 1);
+    let since_at = since.and_time(NaiveTime::MIN).and_utc();
+
+    let counts = fetch_daily_event_counts(store.clone(), user_id, since_at).await?;
+    let read_days = fetch_read_days(store.clone(), user_id).await?;
+    let unread = count_unread_articles(store.clone(), user_id).await?;
+    let read_articles = fetch_read_articles(store.clone(), user_id, since_at).await?;
+
+    let reads_per_day = per_day(&counts, ArticleEventKind::Archived, since, today);
+    let (current_streak, longest_streak) = streaks(&read_days, today);
+    let times_to_read = read_articles
+        .iter()
+        .filter_map(|article| article.time_to_read)
+        .filter(|time| *time > 0)
+        .collect::<Vec<_>>();
+
+    Ok(ReadingStats {
+        since,
+        until: today,
+        reads_per_week: per_week(&reads_per_day),
+        current_streak,
+        longest_streak,
+        backlog: backlog(unread, &counts, since, today),
+        average_time_to_read: (!times_to_read.is_empty()).then(|| {
+            times_to_read.iter().map(|time| *time as f64).sum::<f64>() / times_to_read.len() as f64
+        }),
+        top_domains: top(read_articles
+            .iter()
+            .filter_map(|article| article.url.as_deref().and_then(domain_of))),
+        top_tags: top(read_articles
+            .iter()
+            .flat_map(|article| parse_tags(article.tags.as_deref()))),
+        saved: total(&counts, ArticleEventKind::Added),
+        read: reads_per_day.iter().map(|day| day.count).sum(),
+        reads_per_day,
+    })
+}
+
+fn count_on(counts: &[DailyEventCount], kind: ArticleEventKind, day: NaiveDate) -> i64 {
+    counts
+        .iter()
+        .filter(|count| count.day == day && count.kind == kind.as_str())
+        .map(|count| count.count)
+        .sum()
+}
+
+fn total(counts: &[DailyEventCount], kind: ArticleEventKind) -> i64 {
+    counts
+        .iter()
+        .filter(|count| count.kind == kind.as_str())
+        .map(|count| count.count)
+        .sum()
+}
+
+/// Every day from `since` to `until`, including the days without events.
+fn per_day(
+    counts: &[DailyEventCount],
+    kind: ArticleEventKind,
+    since: NaiveDate,
+    until: NaiveDate,
+) -> Vec<DayCount> {
+    since
+        .iter_days()
+        .take_while(|day| *day <= until)
+        .map(|date| DayCount {
+            date,
+            count: count_on(counts, kind, date),
+        })
+        .collect()
+}
+
+fn per_week(days: &[DayCount]) -> Vec<WeekCount> {
+    let mut weeks: Vec<WeekCount> = vec![];
+    for day in days {
+        let week_start =
+            day.date - Duration::days(day.date.weekday().num_days_from_monday() as i64);
+        match weeks.last_mut() {
+            Some(week) if week.week_start == week_start => week.count += day.count,
+            _ => weeks.push(WeekCount {
+                week_start,
+                count: day.count,
+            }),
+        }
+    }
+    weeks
+}
+
+/// Current and longest streaks of consecutive days in `read_days`, which are sorted.
+fn streaks(read_days: &[NaiveDate], today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in read_days {
+        streak = match previous {
+            Some(previous) if *day - previous == Duration::days(1) => streak + 1,
+            Some(previous) if *day == previous => streak,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(*day);
+    }
+
+    let current = match previous {
+        Some(last) if today - last <= Duration::days(1) => streak,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+/// Unread articles at the end of each day, rewound from the current count: the articles added on
+/// a day weren't there before it, the ones read or deleted were.
+fn backlog(
+    unread: i64,
+    counts: &[DailyEventCount],
+    since: NaiveDate,
+    until: NaiveDate,
+) -> Vec<BacklogSize> {
+    let mut size = unread;
+    let mut backlog = vec![];
+    let mut date = until;
+    while date >= since {
+        backlog.push(BacklogSize {
+            date,
+            size: size.max(0),
+        });
+        size = size - count_on(counts, ArticleEventKind::Added, date)
+            + count_on(counts, ArticleEventKind::Archived, date)
+            + count_on(counts, ArticleEventKind::Deleted, date);
+        date -= Duration::days(1);
+    }
+    backlog.reverse();
+    backlog
+}
+
+/// The most frequent names, ties in alphabetical order.
+fn top(names: impl Iterator<Item = String>) -> Vec<NamedCount> {
+    let mut counts = HashMap::<String, i64>::new();
+    for name in names {
+        *counts.entry(name).or_default() += 1;
+    }
+
+    let mut top = counts
+        .into_iter()
+        .map(|(name, count)| NamedCount { name, count })
+        .collect::<Vec<_>>();
+    top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    top.truncate(TOP_LIMIT);
+    top
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn count(day: u32, kind: ArticleEventKind, count: i64) -> DailyEventCount {
+        DailyEventCount {
+            day: date(day),
+            kind: kind.as_str().to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn counts_streaks_of_consecutive_days() {
+        let days = [date(1), date(2), date(3), date(10), date(11)];
+
+        assert_eq!(streaks(&days, date(12)), (2, 3));
+        assert_eq!(streaks(&days, date(11)), (2, 3));
+        assert_eq!(streaks(&days, date(13)), (0, 3));
+        assert_eq!(streaks(&[], date(13)), (0, 0));
+    }
+
+    #[test]
+    fn groups_reads_by_monday_weeks() {
+        let counts = [
+            count(18, ArticleEventKind::Archived, 2),
+            count(19, ArticleEventKind::Archived, 1),
+            count(19, ArticleEventKind::Added, 5),
+            count(20, ArticleEventKind::Archived, 4),
+        ];
+        let days = per_day(&counts, ArticleEventKind::Archived, date(17), date(21));
+
+        assert_eq!(days.len(), 5);
+        assert_eq!(days[0].count, 0);
+        assert_eq!(
+            per_week(&days),
+            [
+                WeekCount {
+                    week_start: date(12),
+                    count: 2
+                },
+                WeekCount {
+                    week_start: date(19),
+                    count: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewinds_the_backlog_from_the_unread_count() {
+        let counts = [
+            count(19, ArticleEventKind::Added, 3),
+            count(20, ArticleEventKind::Archived, 1),
+            count(20, ArticleEventKind::Deleted, 1),
+        ];
+        let sizes = backlog(10, &counts, date(18), date(20))
+            .into_iter()
+            .map(|day| day.size)
+            .collect::<Vec<_>>();
+
+        assert_eq!(sizes, [9, 12, 10]);
+    }
+
+    #[test]
+    fn ranks_top_names_by_count() {
+        let names = ["b", "a", "c", "a", "b", "a"].map(str::to_string);
+        let top = top(names.into_iter());
+
+        assert_eq!(top[0].name, "a");
+        assert_eq!(top[1].name, "b");
+        assert_eq!(top[2].count, 1);
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, TimeZone, Utc};
+use futures::TryFutureExt;
+use pockety::models::{ItemStatus, PocketItem};
+use tracing::error;
+
+use crate::{
+    api::articles::PocketArticle,
+    db::{
+        convert_article_to_article_author_models, convert_article_to_article_image_models,
+        convert_article_to_article_model, convert_article_to_article_video_models,
+        fetch_article_state, record_article_events, remove_article_from_collections, ArticleModel,
+        ArticleStore,
+    },
+    domain::{ArticleEvent, ArticleEventKind, ArticleState},
+    error::Error,
+    metrics::metrics,
+    Store,
+};
+
+/// Upserts an article fetched from Pocket along with its videos, images and authors, returning
+/// the article's id. Failing to store one of the attachments doesn't fail the article.
+///
+/// What changed since the article was last stored is recorded as its events. They're left out
+/// when the previous version couldn't be read, rather than recording the article as new. An
+/// article deleted from Pocket is dropped from the collections it was in.
+pub async fn store_article(store: &Store, item: PocketItem, user_id: i32) -> Result<i32, Error> {
+    let article = PocketArticle::from(item);
+    let article_model = convert_article_to_article_model(article.clone(), user_id)?;
+    let deleted = leaves_collections(&article_model);
+    let events = fetch_article_state(store.clone(), user_id, &article_model.item_id)
+        .await
+        .map(|previous| article_events(previous, &article_model, Utc::now()));
+    let article_id = store
+        .upsert_article(article_model)
+        .inspect_err(|_| metrics().record_upsert_failure("article"))
+        .await?;
+
+    if let Ok(events) = events {
+        if let Err(e) = record_article_events(store.clone(), user_id, article_id, &events).await {
+            error!("Failed to record events of article {article_id}. Error: {e:?}");
+        }
+    }
+
+    if deleted {
+        remove_article_from_collections(store.clone(), article_id).await?;
+    }
+
+    for article_video_model in convert_article_to_article_video_models(article.clone(), article_id)?
+    {
+        if let Err(e) = store
+            .upsert_article_video(article_id, article_video_model)
+            .await
+        {
+            error!("Failed to store video of article {article_id}. Error: {e:?}");
+            metrics().record_upsert_failure("video");
+        }
+    }
+
+    for article_image_model in convert_article_to_article_image_models(article.clone(), article_id)?
+    {
+        if let Err(e) = store
+            .upsert_article_image(article_id, article_image_model)
+            .await
+        {
+            error!("Failed to store image of article {article_id}. Error: {e:?}");
+            metrics().record_upsert_failure("image");
+        }
+    }
+
+    for article_author_model in convert_article_to_article_author_models(article, article_id)? {
+        if let Err(e) = store
+            .upsert_article_author(article_id, article_author_model)
+            .await
+        {
+            error!("Failed to store author of article {article_id}. Error: {e:?}");
+            metrics().record_upsert_failure("author");
+        }
+    }
+
+    Ok(article_id)
+}
+
+/// Only articles Pocket reports as deleted leave their collections. Retrieves without `since`
+/// leave out archived articles as well as deleted ones, so an article missing from one is kept.
+fn leaves_collections(article: &ArticleModel) -> bool {
+    article.status == ItemStatus::Deleted.as_u8() as i32
+}
+
+/// Events between the previously stored version of an article and the one Pocket sent, dated by
+/// Pocket's timestamps when it has them. A new article brings the events of its whole history.
+fn article_events(
+    previous: Option<ArticleState>,
+    article: &ArticleModel,
+    now: DateTime<Utc>,
+) -> Vec<ArticleEvent> {
+    let at = |timestamp: Option<i64>| {
+        timestamp
+            .filter(|timestamp| *timestamp > 0)
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+            .unwrap_or(now)
+    };
+    let event = |kind, occurred_at| ArticleEvent { kind, occurred_at };
+    let (previous_status, previous_favorite) = match previous {
+        Some(previous) => (Some(previous.status), previous.favorite),
+        None => (None, false),
+    };
+
+    let mut events = vec![];
+    if previous.is_none() {
+        events.push(event(ArticleEventKind::Added, at(article.time_added)));
+    }
+    if previous_status != Some(article.status) {
+        if article.status == ItemStatus::Archived.as_u8() as i32 {
+            events.push(event(ArticleEventKind::Archived, at(article.time_read)));
+        } else if article.status == ItemStatus::Deleted.as_u8() as i32 {
+            events.push(event(ArticleEventKind::Deleted, now));
+        }
+    }
+    if article.favorite && !previous_favorite {
+        events.push(event(
+            ArticleEventKind::Favorited,
+            at(article.time_favorited),
+        ));
+    }
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn article(status: ItemStatus, favorite: bool) -> ArticleModel {
+        ArticleModel {
+            favorite,
+            status: status.as_u8() as i32,
+            time_added: Some(1_700_000_000),
+            time_read: Some(0),
+            ..Default::default()
+        }
+    }
+
+    fn kinds(events: &[ArticleEvent]) -> Vec<ArticleEventKind> {
+        events.iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn records_the_history_of_new_articles() {
+        let now = Utc::now();
+        let events = article_events(None, &article(ItemStatus::Archived, true), now);
+
+        assert_eq!(
+            kinds(&events),
+            [
+                ArticleEventKind::Added,
+                ArticleEventKind::Archived,
+                ArticleEventKind::Favorited
+            ]
+        );
+        assert_eq!(events[0].occurred_at.timestamp(), 1_700_000_000);
+        // Pocket sends `0` for unknown timestamps
+        assert_eq!(events[1].occurred_at, now);
+    }
+
+    #[test]
+    fn records_only_what_changed() {
+        let previous = ArticleState {
+            status: ItemStatus::Normal.as_u8() as i32,
+            favorite: true,
+        };
+
+        assert!(article_events(
+            Some(previous),
+            &article(ItemStatus::Normal, true),
+            Utc::now()
+        )
+        .is_empty());
+        assert_eq!(
+            kinds(&article_events(
+                Some(previous),
+                &article(ItemStatus::Deleted, true),
+                Utc::now()
+            )),
+            [ArticleEventKind::Deleted]
+        );
+    }
+
+    #[test]
+    fn keeps_archived_articles_in_collections() {
+        assert!(!leaves_collections(&article(ItemStatus::Normal, false)));
+        assert!(!leaves_collections(&article(ItemStatus::Archived, true)));
+        assert!(leaves_collections(&article(ItemStatus::Deleted, false)));
+    }
+}
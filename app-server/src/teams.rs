@@ -0,0 +1,292 @@
+use biscuit::{jwk::JWK, jws::Secret};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    error::{ApiError, Error},
+    oauth::{Jwt, OAuthState},
+    validation::{from_json, required_text},
+};
+
+const MAX_NAME_LEN: usize = 100;
+const DEFAULT_INVITATION_DAYS: u32 = 7;
+const MAX_INVITATION_DAYS: u32 = 30;
+
+/// What a member can do in a team, each role allowing everything the ones below it do.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TeamRole {
+    /// Manages the team, its members and invitations
+    Owner,
+    /// Curates the team collections
+    Editor,
+    /// Reads the team collections
+    #[default]
+    Viewer,
+}
+
+impl TeamRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TeamRole::Owner => "owner",
+            TeamRole::Editor => "editor",
+            TeamRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "owner" => Some(TeamRole::Owner),
+            "editor" => Some(TeamRole::Editor),
+            "viewer" => Some(TeamRole::Viewer),
+            _ => None,
+        }
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            TeamRole::Owner => 2,
+            TeamRole::Editor => 1,
+            TeamRole::Viewer => 0,
+        }
+    }
+
+    pub fn allows(self, required: TeamRole) -> bool {
+        self.rank() >= required.rank()
+    }
+}
+
+/// A team the signed in user is a member of.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Team {
+    pub id: i32,
+    pub name: String,
+    /// Role of the signed in user
+    pub role: TeamRole,
+    pub member_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamMember {
+    pub username: String,
+    pub role: TeamRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TeamInput {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MemberPatch {
+    pub role: TeamRole,
+}
+
+/// Invites whoever gets the token to join as an editor or a viewer, owners are promoted by
+/// other owners once they joined.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NewTeamInvitation {
+    pub role: TeamRole,
+    /// 7 by default, at most 30
+    pub expires_in_days: Option<u32>,
+}
+
+/// An invitation that wasn't accepted, revoked or expired yet.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTeamInvitation {
+    pub id: i32,
+    pub role: TeamRole,
+    /// Pocket username of the owner who sent it
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedTeamInvitation {
+    pub invitation: PendingTeamInvitation,
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AcceptTeamInvitation {
+    pub token: String,
+}
+
+/// Payload of invitation tokens, signed and encrypted like the OAuth state. An invitation only
+/// holds while the member who sent it is still an owner of the team.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TeamInvitation {
+    /// Random id of the invitation, stored hashed so it can be used only once and revoked
+    pub token_id: String,
+    pub team_id: i32,
+    pub role: TeamRole,
+    /// Id of the user who sent the invitation
+    pub invited_by: i32,
+    /// Unix timestamp in seconds
+    pub expires_at: i64,
+}
+
+/// The team a user joined with an invitation, `joined` is `false` when they already were a
+/// member, in which case the invitation is left for someone else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptedTeamInvitation {
+    pub team_id: i32,
+    pub role: TeamRole,
+    pub joined: bool,
+}
+
+impl TeamInput {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "team")?;
+        Ok(Self {
+            name: required_text(&input.name, "name", MAX_NAME_LEN)?,
+        })
+    }
+}
+
+impl MemberPatch {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        from_json(body, "member")
+    }
+}
+
+impl NewTeamInvitation {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let input: Self = from_json(body, "invitation")?;
+        if input.role == TeamRole::Owner {
+            return Err(Error::Api(ApiError::BadRequest(
+                "Invitations are for editors and viewers".to_string(),
+            )));
+        }
+        if input
+            .expires_in_days
+            .is_some_and(|days| !(1..=MAX_INVITATION_DAYS).contains(&days))
+        {
+            return Err(Error::Api(ApiError::BadRequest(format!(
+                "expiresInDays must be between 1 and {MAX_INVITATION_DAYS}"
+            ))));
+        }
+        Ok(input)
+    }
+
+    pub fn expires_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::days(self.expires_in_days.unwrap_or(DEFAULT_INVITATION_DAYS) as i64)
+    }
+}
+
+impl AcceptTeamInvitation {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        from_json(body, "invitation")
+    }
+}
+
+impl TeamInvitation {
+    pub fn into_token(
+        self,
+        jws_secret: impl Into<Secret>,
+        jwe_key: &JWK<OAuthState>,
+    ) -> Result<String, Error> {
+        let jwe_key = invitation_key(jwe_key)?;
+        Jwt::jws_encode(self, jws_secret).and_then(|signed| Jwt::jwe_encrypt(signed, jwe_key))
+    }
+
+    /// Refuses tokens that weren't issued by this server, and expired invitations.
+    pub fn from_token(
+        token: &str,
+        jws_secret: impl Into<Secret>,
+        jwe_key: &JWK<OAuthState>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let invalid = || Error::Api(ApiError::BadRequest("Invalid invitation".to_string()));
+        let invitation: Self =
+            Jwt::jwe_decrypt(token, invitation_key(jwe_key)?, jws_secret).map_err(|_| invalid())?;
+
+        let expires_at = Utc
+            .timestamp_opt(invitation.expires_at, 0)
+            .single()
+            .ok_or_else(invalid)?;
+        if expires_at <= now {
+            return Err(Error::Api(ApiError::BadRequest(
+                "Invitation expired".to_string(),
+            )));
+        }
+        Ok(invitation)
+    }
+}
+
+/// Invitations are encrypted with the key of the OAuth state.
+fn invitation_key(jwe_key: &JWK<OAuthState>) -> Result<JWK<TeamInvitation>, Error> {
+    Ok(JWK::new_octet_key(jwe_key.octet_key()?, Default::default()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY: &str = "i0N1ZdPuPFMjD/iJljE1p+JWZt/1uwSb";
+
+    fn keys() -> (Secret, JWK<OAuthState>) {
+        (
+            Secret::Bytes("secret".into()),
+            JWK::new_octet_key(KEY.as_bytes(), Default::default()),
+        )
+    }
+
+    #[test]
+    fn ranks_roles() {
+        assert!(TeamRole::Owner.allows(TeamRole::Editor));
+        assert!(TeamRole::Editor.allows(TeamRole::Editor));
+        assert!(TeamRole::Editor.allows(TeamRole::Viewer));
+        assert!(!TeamRole::Viewer.allows(TeamRole::Editor));
+        assert!(!TeamRole::Editor.allows(TeamRole::Owner));
+    }
+
+    #[test]
+    fn accepts_only_valid_invitations() {
+        let (jws_secret, jwe_key) = keys();
+        let now = Utc::now();
+        let invitation = TeamInvitation {
+            token_id: "token-id".to_string(),
+            team_id: 1,
+            role: TeamRole::Editor,
+            invited_by: 2,
+            expires_at: (now + Duration::days(7)).timestamp(),
+        };
+        let token = invitation
+            .clone()
+            .into_token(jws_secret.clone(), &jwe_key)
+            .unwrap();
+
+        assert_eq!(
+            TeamInvitation::from_token(&token, jws_secret.clone(), &jwe_key, now).unwrap(),
+            invitation
+        );
+
+        let later = now + Duration::days(8);
+        let expired = TeamInvitation::from_token(&token, jws_secret.clone(), &jwe_key, later);
+        assert_eq!(expired.unwrap_err().code(), "bad_request");
+
+        let other_key = JWK::new_octet_key(&[7u8; 32], Default::default());
+        let forged = TeamInvitation::from_token(&token, jws_secret.clone(), &other_key, now);
+        assert_eq!(forged.unwrap_err().code(), "bad_request");
+
+        let state = OAuthState::default()
+            .into_token(jws_secret.clone(), jwe_key.clone())
+            .unwrap();
+        let confused = TeamInvitation::from_token(&state, jws_secret, &jwe_key, now);
+        assert_eq!(confused.unwrap_err().code(), "bad_request");
+    }
+}
@@ -0,0 +1,59 @@
+use opentelemetry::{global, trace::TraceError, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, Tracer},
+    Resource,
+};
+use tracing::Span;
+
+/// Builds a tracer exporting spans in batches over OTLP/gRPC to `endpoint`, e.g. a local
+/// collector at `http://localhost:4317`, and makes W3C trace context the propagation format.
+pub fn init_tracer(endpoint: &str, service_name: &str) -> Result<Tracer, TraceError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config().with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )])),
+        )
+        .install_batch(runtime::Tokio)
+}
+
+/// Flushes the spans that haven't been exported yet. Blocks until the exporter is done, so it's
+/// called off the async workers.
+pub async fn shutdown_tracer() {
+    let _ = tokio::task::spawn_blocking(global::shutdown_tracer_provider).await;
+}
+
+/// Span around a single Redis command or pipeline, exported under the command's name.
+pub fn redis_span(command: &'static str) -> Span {
+    tracing::info_span!(
+        "redis",
+        otel.name = command,
+        otel.kind = "client",
+        db.system = "redis",
+        db.operation = command,
+    )
+}
+
+/// Span around a call to Pocket's `endpoint`, named like the endpoint label of the metrics.
+pub fn pocket_span(endpoint: &'static str) -> Span {
+    let name = format!("pocket {endpoint}");
+    tracing::info_span!(
+        "pocket",
+        otel.name = name.as_str(),
+        otel.kind = "client",
+        peer.service = "pocket",
+        endpoint,
+    )
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::{error::Error, validation::from_json};
+
+/// Version of the shape of `UserSettings`, stored along with them. Bump it whenever a field is
+/// renamed or changes meaning, and teach `upgrade` to rewrite settings stored with the previous
+/// version. Adding a field with a default doesn't need a new version.
+pub const SETTINGS_SCHEMA_VERSION: i32 = 1;
+
+/// Preferences of the settings page, stored on the server so they follow the user across devices.
+/// Everything is off until the user turns it on.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserSettings {
+    pub enable_dark_mode: bool,
+    /// Show the top image of articles alongside their link and title
+    pub enable_rich_view: bool,
+    /// Show metrics of the recent reading activity
+    pub enable_metrics_view: bool,
+    /// Mark articles as read when they're opened
+    pub enable_automatic_read: bool,
+    /// Sync the Pocket library in the background
+    pub enable_automatic_sync: bool,
+}
+
+/// Partial update of `UserSettings`, only the given fields change. Unknown fields are rejected so
+/// typos don't pass silently.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserSettingsPatch {
+    pub enable_dark_mode: Option<bool>,
+    pub enable_rich_view: Option<bool>,
+    pub enable_metrics_view: Option<bool>,
+    pub enable_automatic_read: Option<bool>,
+    pub enable_automatic_sync: Option<bool>,
+}
+
+impl UserSettings {
+    /// Reads settings stored with any schema version up to the current one.
+    pub fn from_stored(version: i32, settings: Value) -> Result<Self, Error> {
+        let settings = upgrade(version, settings)?;
+        serde_json::from_value(settings).map_err(|e| {
+            Error::Db(format!(
+                "Failed to parse settings of schema version {version}. Error: {e}"
+            ))
+        })
+    }
+
+    pub fn to_stored(&self) -> Result<(i32, Value), Error> {
+        serde_json::to_value(self)
+            .map(|settings| (SETTINGS_SCHEMA_VERSION, settings))
+            .map_err(|e| Error::Db(format!("Failed to serialize settings. Error: {e}")))
+    }
+
+    pub fn apply(self, patch: UserSettingsPatch) -> Self {
+        Self {
+            enable_dark_mode: patch.enable_dark_mode.unwrap_or(self.enable_dark_mode),
+            enable_rich_view: patch.enable_rich_view.unwrap_or(self.enable_rich_view),
+            enable_metrics_view: patch
+                .enable_metrics_view
+                .unwrap_or(self.enable_metrics_view),
+            enable_automatic_read: patch
+                .enable_automatic_read
+                .unwrap_or(self.enable_automatic_read),
+            enable_automatic_sync: patch
+                .enable_automatic_sync
+                .unwrap_or(self.enable_automatic_sync),
+        }
+    }
+}
+
+impl UserSettingsPatch {
+    pub fn from_json(body: &[u8]) -> Result<Self, Error> {
+        from_json(body, "settings")
+    }
+}
+
+/// Rewrites settings stored with an older schema version into the current shape, one version at a
+/// time.
+fn upgrade(version: i32, settings: Value) -> Result<Value, Error> {
+    match version {
+        SETTINGS_SCHEMA_VERSION => Ok(settings),
+        _ => Err(Error::Db(format!(
+            "Unknown settings schema version {version}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn fills_in_fields_missing_from_stored_settings() {
+        let settings = UserSettings::from_stored(1, json!({ "enableDarkMode": true })).unwrap();
+
+        assert_eq!(
+            settings,
+            UserSettings {
+                enable_dark_mode: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn refuses_unknown_schema_versions() {
+        assert!(UserSettings::from_stored(SETTINGS_SCHEMA_VERSION + 1, json!({})).is_err());
+    }
+
+    #[test]
+    fn patches_only_the_given_fields() {
+        let patch = UserSettingsPatch::from_json(br#"{"enableRichView":true}"#).unwrap();
+        let settings = UserSettings::default().apply(patch);
+
+        assert!(settings.enable_rich_view);
+        assert!(!settings.enable_automatic_read);
+        assert!(!settings.enable_dark_mode);
+    }
+
+    #[test]
+    fn rejects_invalid_patches() {
+        for body in [
+            r#"{"enableDarkMod":true}"#,
+            r#"{"enableDarkMode":"yes"}"#,
+            r#"[]"#,
+        ] {
+            let error = UserSettingsPatch::from_json(body.as_bytes()).unwrap_err();
+            assert_eq!(error.code(), "bad_request", "{body}");
+        }
+    }
+}
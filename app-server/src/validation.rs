@@ -0,0 +1,56 @@
+use serde::de::DeserializeOwned;
+
+use crate::error::{ApiError, Error};
+
+/// Parses a request body, refusing it as a bad request naming `what` it should have been.
+pub fn from_json<T: DeserializeOwned>(body: &[u8], what: &str) -> Result<T, Error> {
+    serde_json::from_slice(body)
+        .map_err(|e| Error::Api(ApiError::BadRequest(format!("Invalid {what}: {e}"))))
+}
+
+fn too_long(what: &str, max_len: usize) -> Error {
+    Error::Api(ApiError::BadRequest(format!(
+        "{what} can't be longer than {max_len} characters"
+    )))
+}
+
+/// Trims `text`, refusing it when it ends up empty or longer than `max_len` characters.
+pub fn required_text(text: &str, what: &str, max_len: usize) -> Result<String, Error> {
+    optional_text(Some(text), what, max_len)?
+        .ok_or_else(|| Error::Api(ApiError::BadRequest(format!("{what} can't be empty"))))
+}
+
+/// Trims `text`, blank text is no text.
+pub fn optional_text(
+    text: Option<&str>,
+    what: &str,
+    max_len: usize,
+) -> Result<Option<String>, Error> {
+    match text.map(str::trim).filter(|text| !text.is_empty()) {
+        Some(text) if text.chars().count() > max_len => Err(too_long(what, max_len)),
+        text => Ok(text.map(str::to_string)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trims_text() {
+        assert_eq!(required_text(" Rust ", "title", 4).unwrap(), "Rust");
+        assert_eq!(
+            required_text("  ", "title", 4).unwrap_err().code(),
+            "bad_request"
+        );
+        assert!(required_text("Rusty", "title", 4).is_err());
+
+        assert_eq!(optional_text(Some("  "), "note", 4).unwrap(), None);
+        assert_eq!(optional_text(None, "note", 4).unwrap(), None);
+        assert_eq!(
+            optional_text(Some(" née "), "note", 4).unwrap().as_deref(),
+            Some("née")
+        );
+        assert!(optional_text(Some("notes"), "note", 4).is_err());
+    }
+}
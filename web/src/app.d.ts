@@ -5,7 +5,9 @@ declare global {
     interface Locals {
       session: {
         username?: string;
+        csrfToken?: string;
       };
+      traceparent: string;
     }
   }
 }
@@ -1,7 +1,12 @@
 import type { Session } from "$lib/types";
 import type { Handle } from "@sveltejs/kit";
+import { makeTraceparent } from "$lib/server/tracing";
 
 export const handle: Handle = async ({ event, resolve }) => {
+  event.locals.traceparent = makeTraceparent(
+    event.request.headers.get("traceparent"),
+  );
+
   const ID = event.cookies.get("ID");
 
   if (!ID) {
@@ -9,11 +14,12 @@ export const handle: Handle = async ({ event, resolve }) => {
   }
 
   const session = await fetch(
-    `${import.meta.env.VITE_PUBLIC_APP_SERVER_BASE_URL}/auth/session`,
+    `${import.meta.env.VITE_PUBLIC_APP_SERVER_BASE_URL}/v2/auth/session`,
     {
       headers: {
         "Content-Type": "application/json",
         Accept: "application/json",
+        traceparent: event.locals.traceparent,
         Cookie: `ID=${ID}; SameSite=None; Secure; HttpOnly; Path=/;`,
       },
       credentials: "include",
@@ -25,9 +31,10 @@ export const handle: Handle = async ({ event, resolve }) => {
     return res.json() as Promise<Session>;
   });
 
-  if (session) {
+  if (session && !session.reauthRequired) {
     event.locals.session = {
       username: session.username,
+      csrfToken: session.csrfToken,
     };
   }
 
@@ -0,0 +1,19 @@
+const TRACEPARENT = /^00-([0-9a-f]{32})-([0-9a-f]{16})-([0-9a-f]{2})$/;
+
+const randomHex = (bytes: number) =>
+  Array.from(crypto.getRandomValues(new Uint8Array(bytes)), (byte) =>
+    byte.toString(16).padStart(2, "0"),
+  ).join("");
+
+/**
+ * W3C `traceparent` header sent with every app server request made while handling a page
+ * request, so the app server's spans end up in a single trace. Continues the trace of the
+ * incoming request when it carries a valid `traceparent`, e.g. from a load balancer.
+ */
+export const makeTraceparent = (incoming: string | null): string => {
+  const match = incoming?.match(TRACEPARENT);
+  const traceId = match && !/^0+$/.test(match[1]) ? match[1] : randomHex(16);
+  const flags = match ? match[3] : "01";
+
+  return `00-${traceId}-${randomHex(8)}-${flags}`;
+};
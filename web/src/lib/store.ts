@@ -14,6 +14,9 @@ export const session = writable<Session>({
 
 export const isLoggingIn = writable<boolean>(false);
 
+/** Csrf token of the session, handed out by `/auth/session`. */
+export const csrfToken = writable<string | null>(null);
+
 export const rateLimits = writable<RateLimits>({
   userLimit: null,
   userRemaining: null,
@@ -7,61 +7,71 @@ export const rateLimitsSchema = z.object({
 });
 export type RateLimits = z.infer<typeof rateLimitsSchema>;
 
-export const itemImageSchema = z.object({
-  itemId: z.string(),
+export const articleAuthorSchema = z.object({
+  authorId: z.string(),
+  name: z.string(),
+  url: z.string().nullable(),
+});
+export type ArticleAuthor = z.infer<typeof articleAuthorSchema>;
+
+export const articleImageSchema = z.object({
   imageId: z.string(),
   src: z.string(),
-  width: z.string(),
-  height: z.string(),
-  caption: z.string(),
-  credit: z.string(),
+  width: z.number().nullable(),
+  height: z.number().nullable(),
+  credit: z.string().nullable(),
+  caption: z.string().nullable(),
 });
-export type ItemImage = z.infer<typeof itemImageSchema>;
+export type ArticleImage = z.infer<typeof articleImageSchema>;
 
-export const itemVideoSchema = z.object({
-  itemId: z.string(),
-  imageId: z.string(),
+export const articleVideoSchema = z.object({
+  videoId: z.string(),
   src: z.string(),
-  width: z.string(),
-  length: z.string().optional(),
-  vid: z.string(),
+  width: z.number().nullable(),
+  height: z.number().nullable(),
+  length: z.number().nullable(),
+  vid: z.string().nullable(),
 });
-export type ItemVideo = z.infer<typeof itemVideoSchema>;
+export type ArticleVideo = z.infer<typeof articleVideoSchema>;
+
+const mediaPresenceSchema = z.enum(["none", "contains", "is"]);
 
 export const articleSchema = z.object({
   itemId: z.string(),
-  resolveId: z.string().optional(),
-  givenUrl: z.string().optional(),
-  givenTitle: z.string().optional(),
-  resolvedUrl: z.string().optional(),
-  resolvedTitle: z.string().optional(),
-  favorite: z.string().optional(),
-  status: z.string(),
-  timeAdded: z.number().optional(),
-  timeUpdated: z.number().optional(),
-  timeRead: z.number().optional(),
-  timeFavorited: z.number().optional(),
-  sortId: z.number().optional(),
-  excerpt: z.string().optional(),
-  isArticle: z.string().optional(),
-  isIndex: z.string().optional(),
-  hasImage: z.string().optional(),
-  hasVideo: z.string().optional(),
-  wordCount: z.string().optional(),
-  tags: z.string().optional(),
-  authors: z.string().optional(),
-  images: itemImageSchema.array().optional(),
-  videos: itemVideoSchema.array().optional(),
-  lang: z.string().optional(),
-  timeToRead: z.number().optional(),
-  listenDurationEstimate: z.number().optional(),
-  topImageUrl: z.string().optional(),
-  domainMetadata: z.any().optional(),
+  resolvedId: z.string().nullable(),
+  givenUrl: z.string().nullable(),
+  givenTitle: z.string().nullable(),
+  resolvedUrl: z.string().nullable(),
+  resolvedTitle: z.string().nullable(),
+  domain: z.string().nullable(),
+  excerpt: z.string().nullable(),
+  status: z.enum(["unread", "archived", "deleted"]),
+  favorite: z.boolean(),
+  isArticle: z.boolean(),
+  isIndex: z.boolean(),
+  hasImage: mediaPresenceSchema,
+  hasVideo: mediaPresenceSchema,
+  wordCount: z.number().nullable(),
+  tags: z.string().array(),
+  authors: articleAuthorSchema.array(),
+  images: articleImageSchema.array(),
+  videos: articleVideoSchema.array(),
+  lang: z.string().nullable(),
+  timeToRead: z.number().nullable(),
+  listenDurationEstimate: z.number().nullable(),
+  topImageUrl: z.string().nullable(),
+  sortId: z.number().nullable(),
+  timeAdded: z.string().datetime({ offset: true }).nullable(),
+  timeUpdated: z.string().datetime({ offset: true }).nullable(),
+  timeRead: z.string().datetime({ offset: true }).nullable(),
+  timeFavorited: z.string().datetime({ offset: true }).nullable(),
 });
 export type Article = z.infer<typeof articleSchema>;
 
 export const apiGetSessionResSchema = z.object({
   username: z.string().optional(),
+  csrfToken: z.string().optional(),
+  reauthRequired: z.boolean().optional(),
 });
 export type Session = z.infer<typeof apiGetSessionResSchema>;
 
@@ -71,6 +81,15 @@ export const apiGetArticlesResSchema = z.object({
 });
 export type ApiGetArticlesRes = z.infer<typeof apiGetArticlesResSchema>;
 
+export const apiUserSettingsSchema = z.object({
+  enableDarkMode: z.boolean(),
+  enableRichView: z.boolean(),
+  enableMetricsView: z.boolean(),
+  enableAutomaticRead: z.boolean(),
+  enableAutomaticSync: z.boolean(),
+});
+export type UserSettings = z.infer<typeof apiUserSettingsSchema>;
+
 export const apiAuthzResSchema = z.object({
   username: z.string().optional(),
 });
@@ -34,11 +34,12 @@ export const load: PageServerLoad = async (
     } = await fetch(
       `${
         import.meta.env.VITE_PUBLIC_APP_SERVER_BASE_URL
-      }/articles?page=${pageNumber}`,
+      }/v2/articles?page=${pageNumber}`,
       {
         headers: {
           "Content-Type": "application/json",
           Accept: "application/json",
+          traceparent: event.locals.traceparent,
           Cookie: `ID=${ID}; SameSite=None; Secure; HttpOnly; Path=/;`,
         },
         credentials: "include",
@@ -59,7 +60,9 @@ export const load: PageServerLoad = async (
       })
       .then((res): ApiGetArticlesRes => {
         const articles = res.data.articles.sort((a, b) => {
-          return (a.timeAdded || -1) > (b.timeAdded || -1) ? -1 : 1;
+          const timeAdded = (article: Article) =>
+            article.timeAdded ? Date.parse(article.timeAdded) : -1;
+          return timeAdded(a) > timeAdded(b) ? -1 : 1;
         });
 
         return {
@@ -1,10 +1,39 @@
 import { handleLoginRedirect } from "$lib/utils";
+import { apiUserSettingsSchema, type UserSettings } from "$lib/types";
 import { redirect } from "@sveltejs/kit";
 import type { PageServerLoad } from "./$types";
 
-export const load: PageServerLoad = async (event) => {
+export const load: PageServerLoad = async (
+  event,
+): Promise<{ settings: UserSettings | null }> => {
   const { session } = await event.parent();
   if (!session?.username) {
     throw redirect(302, handleLoginRedirect(event));
   }
+
+  const ID = event.cookies.get("ID");
+
+  try {
+    const settings = await fetch(
+      `${import.meta.env.VITE_PUBLIC_APP_SERVER_BASE_URL}/v2/me/settings`,
+      {
+        headers: {
+          Accept: "application/json",
+          traceparent: event.locals.traceparent,
+          Cookie: `ID=${ID}; SameSite=None; Secure; HttpOnly; Path=/;`,
+        },
+        credentials: "include",
+      },
+    ).then((res) => {
+      if (!res.ok) {
+        throw new Error(res.statusText);
+      }
+      return res.json();
+    });
+
+    return { settings: apiUserSettingsSchema.parse(settings) };
+  } catch (e) {
+    console.error(e);
+    return { settings: null };
+  }
 };